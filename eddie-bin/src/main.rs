@@ -1,24 +1,72 @@
-use support::env_param;
+use eddie_lib::{origin::Origin, Response};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use support::{
+    env_param,
+    traits::get::{ConstU128, ConstU64},
+};
 use tokio::{
     signal::unix::{signal, SignalKind},
     task,
 };
 use transport::{discord::DiscordTransport, telegram::TelegramTransport};
 
+/// How often the balances of the faucet wallets are checked.
+const BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How often the deposit addresses of users are checked for new deposits.
+const DEPOSIT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often networks that channels newly subscribed to are picked up, and
+/// dropped event subscriptions are restarted.
+const SUBSCRIPTION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often messages held back during quiet hours are checked for users
+/// whose quiet hours are over.
+const QUIET_HOURS_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How often referenda are checked for users to remind to vote.
+const VOTE_REMINDER_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// How often the validators users monitor are checked.
+const VALIDATOR_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// How often the payout queue is checked for jobs that are due for a retry.
+const JOB_QUEUE_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Clone)]
 struct App;
 
 impl support::traits::Config for App {}
 
 env_param!(Name, "EDDIE_NAME", "Eddie");
-env_param!(WalletSeed, "EDDIE_WALLET_SEED", "\\ALICE");
+// All faucet accounts, the hot wallet of the ledger and the deposit addresses
+// are derived from the wallet seed, so there's no default for it.
+env_param!(WalletSeed, "EDDIE_WALLET_SEED");
+// The seed encryption key has to be 32 random bytes, hex encoded, such as the
+// output of `openssl rand -hex 32`. Seeds encrypted with a passphrase by older
+// versions are still read when it's set as the previous key, and re-encrypted
+// with the new key by rotating the keys.
+env_param!(SeedEncryptionKey, "EDDIE_SEED_ENCRYPTION_KEY");
+env_param!(
+    PreviousSeedEncryptionKey,
+    "EDDIE_PREVIOUS_SEED_ENCRYPTION_KEY",
+    ""
+);
 env_param!(SubstrateRPC, "EDDIE_SUBSTRATE_RPC", "ws://127.0.0.1:9944");
 env_param!(DBPath, "EDDIE_DB_PATH", "db");
 impl eddie_lib::Config for App {
     type Name = Name;
     type WalletSeed = WalletSeed;
+    type SeedEncryptionKey = SeedEncryptionKey;
+    type PreviousSeedEncryptionKey = PreviousSeedEncryptionKey;
     type SubstrateRPC = SubstrateRPC;
     type DBPath = DBPath;
+    type DripAmount = ConstU128<1_000_000_000_000>;
+    type DripCooldown = ConstU64<{ 24 * 60 * 60 }>;
 }
 
 env_param!(DiscordToken, "EDDIE_DISCORD_TOKEN");
@@ -33,11 +81,79 @@ impl transport::telegram::Config for App {
     type Token = TelegramToken;
 }
 
+/// Send responses that don't answer a command through the transport of their
+/// origin.
+async fn deliver(responses: Vec<Response>) {
+    for response in responses {
+        send(response).await;
+    }
+}
+
+/// Send a response that doesn't answer a command, returning whether it was
+/// delivered.
+async fn send(response: Response) -> bool {
+    let origin = match &response {
+        Response::SayChan(origin, _) | Response::SayDirect(origin, _) => origin.clone(),
+        response => {
+            log::error!("Can't deliver {:?} without a command to answer", response);
+            return false;
+        }
+    };
+
+    let result = match origin {
+        Origin::Discord(_) => DiscordTransport::<App>::new()
+            .send(response)
+            .await
+            .map_err(|err| err.to_string()),
+        Origin::Telegram(_) => TelegramTransport::<App>::new()
+            .send(response)
+            .await
+            .map_err(|err| err.to_string()),
+    };
+    if let Err(err) = &result {
+        log::error!("Couldn't deliver message to {}: {}", origin, err);
+    }
+    result.is_ok()
+}
+
 #[tokio::main]
 async fn main() {
     // let telegram_token = env::var("EDDIE_TELEGRAM_TOKEN").expect("No EDDIE_TELEGRAM_TOKEN in env");
     pretty_env_logger::init();
 
+    // Refuse to start without a usable wallet seed, rather than failing on
+    // the first payout.
+    if let Err(err) = eddie_lib::chain::pair_from_seed(&WalletSeed::get()) {
+        panic!("Invalid EDDIE_WALLET_SEED: {}", err);
+    }
+
+    // Refuse to start with a seed encryption key that could be brute-forced.
+    if let Err(err) = eddie_lib::crypto::cipher_key(&SeedEncryptionKey::get()) {
+        panic!("Invalid EDDIE_SEED_ENCRYPTION_KEY: {}", err);
+    }
+
+    // Move faucets stored by older versions of the bot to the network they
+    // paid out on.
+    match eddie_lib::Bot::<App>::bind_unbound_faucets() {
+        Ok(0) => {}
+        Ok(count) => log::info!("Bound {} faucet(s) to their network", count),
+        Err(err) => log::error!("Couldn't bind faucets to their network: {}", err),
+    }
+
+    // Encrypt faucet seeds stored by older versions of the bot.
+    match eddie_lib::Bot::<App>::encrypt_plaintext_seeds() {
+        Ok(0) => {}
+        Ok(count) => log::info!("Encrypted {} plaintext faucet seed(s)", count),
+        Err(err) => log::error!("Couldn't encrypt plaintext faucet seeds: {}", err),
+    }
+
+    // Index the wallets of databases created by older versions of the bot,
+    // rebuilding it also repairs an index that got out of sync.
+    match eddie_lib::Bot::<App>::rebuild_wallet_index() {
+        Ok(count) => log::info!("Indexed {} wallet address(es)", count),
+        Err(err) => log::error!("Couldn't rebuild the wallet index: {}", err),
+    }
+
     let discord_task = task::spawn(async move {
         let discord = DiscordTransport::<App>::new();
         if let Err(err) = discord.serve().await {
@@ -50,6 +166,171 @@ async fn main() {
         telegram.serve().await;
     });
 
+    // Alert the faucet channels and admins when faucets run low on funds.
+    let monitor_task = task::spawn(async move {
+        let mut interval = tokio::time::interval(BALANCE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match task::spawn_blocking(eddie_lib::Bot::<App>::check_faucet_balances).await {
+                Ok(Ok(responses)) => deliver(responses).await,
+                Ok(Err(err)) => log::error!("Couldn't check faucet balances: {}", err),
+                Err(err) => log::error!("Faucet balance check panicked: {}", err),
+            }
+        }
+    });
+
+    // Resume the payout queue after a restart and retry failed submissions.
+    let jobs_task = task::spawn(async move {
+        let mut interval = tokio::time::interval(JOB_QUEUE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match task::spawn_blocking(eddie_lib::Bot::<App>::run_jobs).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => log::error!("Couldn't run the payout queue: {}", err),
+                Err(err) => log::error!("Payout queue panicked: {}", err),
+            }
+        }
+    });
+
+    // Move new deposits to the hot wallet, they're credited to the ledger
+    // once the sweep is final.
+    let deposits_task = task::spawn(async move {
+        let mut interval = tokio::time::interval(DEPOSIT_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match task::spawn_blocking(eddie_lib::Bot::<App>::watch_deposits).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => log::error!("Couldn't check deposits: {}", err),
+                Err(err) => log::error!("Deposit check panicked: {}", err),
+            }
+        }
+    });
+
+    // Post the events channels follow, with an event subscription per
+    // network. A subscription ends when the node drops it or no channel
+    // follows the network anymore, and is restarted on the next check.
+    let events_task = task::spawn(async move {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let watched = Arc::new(Mutex::new(HashSet::new()));
+        let mut interval = tokio::time::interval(SUBSCRIPTION_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                Some(response) = receiver.recv() => deliver(vec![response]).await,
+                _ = interval.tick() => {
+                    let networks = match task::spawn_blocking(
+                        eddie_lib::Bot::<App>::subscribed_networks,
+                    )
+                    .await
+                    {
+                        Ok(Ok(networks)) => networks,
+                        Ok(Err(err)) => {
+                            log::error!("Couldn't get the subscribed networks: {}", err);
+                            continue;
+                        }
+                        Err(err) => {
+                            log::error!("Getting the subscribed networks panicked: {}", err);
+                            continue;
+                        }
+                    };
+                    for network in networks {
+                        if !watched.lock().unwrap().insert(network.clone()) {
+                            continue;
+                        }
+                        let (sender, watched) = (sender.clone(), watched.clone());
+                        std::thread::spawn(move || {
+                            let result = eddie_lib::Bot::<App>::watch_events(&network, |response| {
+                                let _ = sender.send(response);
+                            });
+                            if let Err(err) = result {
+                                log::warn!("Event subscription of {} ended: {}", network, err);
+                            }
+                            watched.lock().unwrap().remove(&network);
+                        });
+                    }
+                }
+            }
+        }
+    });
+
+    // Send the messages about watched addresses that were held back during
+    // the quiet hours of their users.
+    let held_messages_task = task::spawn(async move {
+        let mut interval = tokio::time::interval(QUIET_HOURS_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let released = match task::spawn_blocking(eddie_lib::Bot::<App>::held_messages).await {
+                Ok(Ok(released)) => released,
+                Ok(Err(err)) => {
+                    log::error!("Couldn't release held messages: {}", err);
+                    continue;
+                }
+                Err(err) => {
+                    log::error!("Releasing held messages panicked: {}", err);
+                    continue;
+                }
+            };
+
+            // Messages are only removed once delivered, and kept in order by
+            // holding back the rest of a user's messages after a failure.
+            let mut failed = Vec::new();
+            for held in released {
+                if failed.contains(&held.origin) {
+                    continue;
+                }
+                if !send(held.response).await {
+                    failed.push(held.origin);
+                    continue;
+                }
+                let origin = held.origin.clone();
+                let removed = task::spawn_blocking(move || {
+                    eddie_lib::Bot::<App>::release_held_messages(&held.origin, held.count)
+                })
+                .await;
+                if !matches!(removed, Ok(Ok(()))) {
+                    log::error!("Couldn't remove the delivered messages of {}", origin);
+                    failed.push(origin);
+                }
+            }
+        }
+    });
+
+    // Remind users to vote on referenda that close soon.
+    let reminders_task = task::spawn(async move {
+        let mut interval = tokio::time::interval(VOTE_REMINDER_INTERVAL);
+        loop {
+            interval.tick().await;
+            match task::spawn_blocking(eddie_lib::Bot::<App>::remind_voters).await {
+                Ok(Ok(responses)) => deliver(responses).await,
+                Ok(Err(err)) => log::error!("Couldn't remind voters: {}", err),
+                Err(err) => log::error!("Vote reminders panicked: {}", err),
+            }
+        }
+    });
+
+    // Alert users about the validators they monitor.
+    let validators_task = task::spawn(async move {
+        let mut interval = tokio::time::interval(VALIDATOR_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match task::spawn_blocking(eddie_lib::Bot::<App>::check_validators).await {
+                Ok(Ok(responses)) => deliver(responses).await,
+                Ok(Err(err)) => log::error!("Couldn't check the monitored validators: {}", err),
+                Err(err) => log::error!("Validator check panicked: {}", err),
+            }
+        }
+    });
+
+    // Deliver messages to users that don't answer their own command.
+    let notifications_task = task::spawn(async move {
+        loop {
+            match task::spawn_blocking(eddie_lib::Bot::<App>::next_notification).await {
+                Ok(Some(response)) => deliver(vec![response]).await,
+                Ok(None) => break,
+                Err(err) => log::error!("Notification delivery panicked: {}", err),
+            }
+        }
+    });
+
     // Handle termination signal (CTRL+C)
     let mut sigint = signal(SignalKind::interrupt()).unwrap();
     let mut sigterm = signal(SignalKind::terminate()).unwrap();
@@ -65,5 +346,13 @@ async fn main() {
         } => {}
         _ = discord_task => {}
         _ = telegram_task => {}
+        _ = monitor_task => {}
+        _ = jobs_task => {}
+        _ = deposits_task => {}
+        _ = events_task => {}
+        _ = held_messages_task => {}
+        _ = reminders_task => {}
+        _ = validators_task => {}
+        _ = notifications_task => {}
     }
 }
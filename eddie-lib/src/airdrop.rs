@@ -0,0 +1,245 @@
+use crate::{
+    bot::Bot,
+    call::Response,
+    chain,
+    config::Config,
+    functions::now,
+    origin::Origin,
+    storage::{
+        storage::{JobData, StorageError},
+        Storage,
+    },
+};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+use support::traits::{dispatch::DispatchError, Get};
+
+/// Type to make function definitions a bit cleaner.
+type DispatchResult<T> = support::traits::dispatch::DispatchResult<Option<T>>;
+
+/// Number of minutes activity is remembered for, which is the longest window
+/// a rain can look back.
+pub const MAX_RAIN_WINDOW: u64 = 24 * 60;
+
+/// When members last posted, by channel and member.
+///
+/// It's only kept in memory, so a rain right after a restart only reaches
+/// the members who posted since.
+static ACTIVITY: OnceLock<Mutex<HashMap<String, HashMap<String, u64>>>> = OnceLock::new();
+
+fn activity() -> std::sync::MutexGuard<'static, HashMap<String, HashMap<String, u64>>> {
+    ACTIVITY
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+impl<T: Config> Bot<T> {
+    /// Remember that a member posted in a channel, so that they're included
+    /// in a rain.
+    pub fn record_activity(channel: &Origin, who: &Origin) {
+        let now = now();
+        let mut activity = activity();
+        let members = activity.entry(channel.to_string()).or_default();
+        members.insert(who.to_string(), now);
+        members.retain(|_, last| *last + MAX_RAIN_WINDOW * 60 >= now);
+    }
+
+    /// Get the members that posted in a channel during the last `window`
+    /// minutes.
+    ///
+    /// Telegram doesn't let bots list the members of a chat, so they're the
+    /// audience of airdrops there.
+    pub fn active_members(channel: &Origin, window: u64) -> Vec<Origin> {
+        let since = now().saturating_sub(window * 60);
+        activity()
+            .get(&channel.to_string())
+            .into_iter()
+            .flatten()
+            .filter(|(_, last)| **last >= since)
+            .filter_map(|(member, _)| Origin::try_from(member.clone()).ok())
+            .collect()
+    }
+
+    pub fn rain(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        total: &str,
+        window: u64,
+    ) -> DispatchResult<Response> {
+        if window == 0 || window > MAX_RAIN_WINDOW {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!("The window has to be 1 to {} minutes", MAX_RAIN_WINDOW),
+            ));
+        }
+        let audience = Self::active_members(channel, window);
+        Self::pay_audience(who, channel, network, total, audience, true)
+    }
+
+    pub fn airdrop(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        amount: &str,
+        audience: &[Origin],
+    ) -> DispatchResult<Response> {
+        Self::pay_audience(who, channel, network, amount, audience.to_vec(), false)
+    }
+
+    /// Reject users who aren't admins before the members of a server are
+    /// listed for an airdrop, which takes a while on large servers.
+    pub fn ensure_admin(who: &Origin) -> Result<(), DispatchError> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        if !storage.is_admin(who.clone()) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Pay the registered wallets of an audience from the faucet of a
+    /// channel.
+    ///
+    /// The amount is split between the recipients when `split` is set, and
+    /// paid to each of them otherwise. Members without a wallet are reported
+    /// to the admin in a direct message.
+    fn pay_audience(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        amount: &str,
+        audience: Vec<Origin>,
+        split: bool,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who.clone()) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        let faucet = Self::get_faucet(&storage, channel, network)?;
+        let network = Self::get_network(&storage, network)?;
+        let amount = faucet.chain.parse_balance(amount)?;
+
+        // Members with linked accounts are only paid once, and the admin
+        // isn't paid at all.
+        let mut identities = vec![storage
+            .get_identity(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?];
+        let (mut recipients, mut missing) = (Vec::new(), Vec::new());
+        for member in audience {
+            let identity = storage
+                .get_identity(&member)
+                .map_err(|err| DispatchError::Other(err.to_string()))?;
+            if identities.contains(&identity) {
+                continue;
+            }
+            identities.push(identity);
+
+            let wallet = storage
+                .get_user_wallets(&member)
+                .map_err(|err| DispatchError::Other(err.to_string()))?
+                .for_network(&network.name)
+                .map(|wallet| wallet.address.clone());
+            match wallet {
+                Some(address) => recipients.push(address),
+                None => missing.push(member),
+            }
+        }
+        if recipients.is_empty() {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!(
+                    "None of the {} member(s) in the audience registered a wallet",
+                    missing.len()
+                ),
+            ));
+        }
+
+        let each = match split {
+            true => amount / recipients.len() as u128,
+            false => amount,
+        };
+        if each == 0 {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "The amount is too small to share".into(),
+            ));
+        }
+
+        // Reject if the faucet can't pay everybody.
+        let account = chain::account_of(&Self::faucet_pair(
+            channel,
+            &network.name,
+            &faucet.wallet_seed,
+        )?);
+        let api = chain::connect_any(&network.rpc_urls)?;
+        let total = each * recipients.len() as u128;
+        if chain::free_balance(&api, &account)? < total {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!(
+                    "The faucet doesn't have the {} needed",
+                    faucet.chain.format_balance(total)
+                ),
+            ));
+        }
+
+        // The payouts go through the queue of the faucet, which batches them
+        // when the faucet is configured to.
+        let now = now();
+        let kind = if split { "rain" } else { "airdrop" };
+        for (index, address) in recipients.iter().enumerate() {
+            let job = JobData::new(
+                format!("{}/{}/{}/{}", kind, channel, now, index),
+                channel.to_string(),
+                network.name.clone(),
+                address.clone(),
+                each,
+                now,
+            );
+            storage.enqueue_job(&job).map_err(|err| match err {
+                StorageError::NotUnique => DispatchError::Module(
+                    crate::MODULE.into(),
+                    format!("Another {} is already being sent", kind),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+        }
+        Self::run_jobs()?;
+
+        let mut responses = vec![Response::Reply(format!(
+            "Sending {} each to {} member(s) on {}.",
+            faucet.chain.format_balance(each),
+            recipients.len(),
+            network.name
+        ))];
+        if !missing.is_empty() {
+            responses.push(Response::ReplyDirect(format!(
+                "These members of the {} didn't get anything, they have no registered wallet:\n{}",
+                kind,
+                missing
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )));
+        }
+        Ok(Some(Response::Multiple(responses)))
+    }
+}
@@ -1,9 +1,13 @@
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
 use support::traits::{
     dispatch::{DispatchError, DispatchResult},
     Dispatch,
 };
 
-use crate::{origin::Origin, Bot, Config};
+use crate::{origin::Origin, storage::storage::FaucetSetting, Bot, Config};
 
 #[derive(Clone, Debug, PartialEq)]
 /// Collection of all possible calls to the bot.
@@ -14,11 +18,151 @@ pub enum Call<T: Config> {
     Init,
     SetAdmin(Origin, bool),
     RegisterFaucetChannel(Origin),
+    RegisterWallet(String),
+    MyWallet,
+    AddWallet {
+        label: String,
+        address: String,
+        network: Option<String>,
+    },
+    RemoveWallet(String),
+    ListWallets,
+    SetDefaultWallet(String),
+    /// Start verifying the wallet with the given label, or the default one.
+    StartWalletVerification(Option<String>),
+    CompleteWalletVerification(String),
+    LinkAccount,
+    ConfirmLink(String),
     ActivateFaucetChannel {
         channel: Origin,
-        rpc_url: String,
+        network: String,
+    },
+    ImportFaucetSeed {
+        channel: Origin,
+        network: String,
         wallet_seed: String,
     },
+    /// Request tokens, sent to the given wallet label or address, or to the
+    /// caller's registered wallet.
+    Drip {
+        channel: Origin,
+        network: String,
+        address: Option<String>,
+    },
+    /// Look up the balances of a wallet label, an address or a user, on all
+    /// networks of the user's wallets when no network is given.
+    Balance {
+        network: Option<String>,
+        target: Option<String>,
+    },
+    /// Tip a user from the caller's ledger balance.
+    Tip {
+        to: Origin,
+        amount: String,
+        network: String,
+    },
+    /// Claim tips that were held before tips moved to the ledger.
+    ClaimTips,
+    /// Get the caller's deposit address on a network.
+    Deposit(String),
+    LedgerBalance,
+    /// Withdraw from the caller's ledger balance to the given wallet label or
+    /// address, or to the caller's registered wallet.
+    Withdraw {
+        channel: Origin,
+        network: String,
+        amount: String,
+        target: Option<String>,
+    },
+    /// Compare the ledger with the funds held on-chain.
+    Reconcile,
+    /// Split a total between the members with a wallet who posted in the
+    /// channel during the last `window` minutes, paid by its faucet.
+    Rain {
+        channel: Origin,
+        network: String,
+        total: String,
+        window: u64,
+    },
+    /// Send an amount to each member of an audience with a wallet, paid by
+    /// the faucet of the channel.
+    Airdrop {
+        channel: Origin,
+        network: String,
+        amount: String,
+        audience: Vec<Origin>,
+    },
+    /// Post the events of a network that pass a filter, like `Referenda.*`
+    /// or `Balances.Transfer to <address>`, to a channel.
+    Subscribe {
+        channel: Origin,
+        network: String,
+        filter: String,
+    },
+    Unsubscribe {
+        channel: Origin,
+        id: String,
+    },
+    ListSubscriptions(Origin),
+    /// Send the user a direct message when funds of an address move.
+    Watch {
+        network: String,
+        target: Option<String>,
+    },
+    Unwatch(String),
+    ListWatches,
+    /// Show a referendum of the network the channel follows referenda of,
+    /// unless another one is given.
+    Referendum {
+        channel: Origin,
+        network: Option<String>,
+        index: u32,
+    },
+    /// Remind the user of referenda their wallet hasn't voted on before they
+    /// close.
+    VoteReminders {
+        network: String,
+        enabled: bool,
+    },
+    /// Show the status of a validator of the network the channel follows
+    /// staking events of, unless another one is given.
+    Validator {
+        channel: Origin,
+        network: Option<String>,
+        target: String,
+    },
+    Nominations {
+        channel: Origin,
+        network: Option<String>,
+        target: String,
+    },
+    /// Alert the user when a validator leaves the active set, gets chilled
+    /// or has payouts unclaimed for a number of eras.
+    MonitorValidator {
+        network: String,
+        target: String,
+        unclaimed_eras: Option<u32>,
+    },
+    UnmonitorValidator(String),
+    ListMonitoredValidators,
+    /// Hold watch messages back during hours like `22-7`, `None` turns them
+    /// off.
+    SetQuietHours(Option<String>),
+    ConfigureFaucet {
+        channel: Origin,
+        network: String,
+        setting: FaucetSetting,
+    },
+    FaucetStatus(Origin),
+    FaucetBalance(Origin),
+    RotateSeedKey,
+    AddNetwork {
+        name: String,
+        rpc_urls: Vec<String>,
+        explorer_url: Option<String>,
+    },
+    RemoveNetwork(String),
+    ListNetworks,
     _Unreachable(std::marker::PhantomData<T>),
 }
 
@@ -34,11 +178,120 @@ impl<T: Config> Dispatch for Call<T> {
             Call::RegisterFaucetChannel(channel) => {
                 Bot::<T>::register_faucet_channel(origin, channel)
             }
-            Call::ActivateFaucetChannel {
+            Call::RegisterWallet(address) => Bot::<T>::register_wallet(origin, address),
+            Call::MyWallet => Bot::<T>::my_wallet(origin),
+            Call::AddWallet {
+                label,
+                address,
+                network,
+            } => Bot::<T>::add_wallet(origin, label, address, network.as_deref()),
+            Call::RemoveWallet(label) => Bot::<T>::remove_wallet(origin, label),
+            Call::ListWallets => Bot::<T>::list_wallets(origin),
+            Call::SetDefaultWallet(label) => Bot::<T>::set_default_wallet(origin, label),
+            Call::StartWalletVerification(label) => {
+                Bot::<T>::start_wallet_verification(origin, label.as_deref())
+            }
+            Call::CompleteWalletVerification(signature) => {
+                Bot::<T>::complete_wallet_verification(origin, signature)
+            }
+            Call::LinkAccount => Bot::<T>::link_account(origin),
+            Call::ConfirmLink(code) => Bot::<T>::confirm_link(origin, code),
+            Call::ActivateFaucetChannel { channel, network } => {
+                Bot::<T>::activate_faucet_channel(origin, channel, network)
+            }
+            Call::ImportFaucetSeed {
                 channel,
-                rpc_url,
+                network,
                 wallet_seed,
-            } => Bot::<T>::activate_faucet_channel(origin, &channel, rpc_url, wallet_seed),
+            } => Bot::<T>::import_faucet_seed(origin, channel, network, wallet_seed),
+            Call::Drip {
+                channel,
+                network,
+                address,
+            } => Bot::<T>::drip(origin, channel, network, address.as_deref()),
+            Call::Balance { network, target } => {
+                Bot::<T>::balance(origin, network.as_deref(), target.as_deref())
+            }
+            Call::Tip {
+                to,
+                amount,
+                network,
+            } => Bot::<T>::tip(origin, to, amount, network),
+            Call::ClaimTips => Bot::<T>::claim_tips(origin),
+            Call::Deposit(network) => Bot::<T>::deposit(origin, network),
+            Call::LedgerBalance => Bot::<T>::ledger_balance(origin),
+            Call::Withdraw {
+                channel,
+                network,
+                amount,
+                target,
+            } => Bot::<T>::withdraw(origin, channel, network, amount, target.as_deref()),
+            Call::Reconcile => Bot::<T>::reconcile(origin),
+            Call::Rain {
+                channel,
+                network,
+                total,
+                window,
+            } => Bot::<T>::rain(origin, channel, network, total, *window),
+            Call::Airdrop {
+                channel,
+                network,
+                amount,
+                audience,
+            } => Bot::<T>::airdrop(origin, channel, network, amount, audience),
+            Call::Subscribe {
+                channel,
+                network,
+                filter,
+            } => Bot::<T>::subscribe(origin, channel, network, filter),
+            Call::Unsubscribe { channel, id } => Bot::<T>::unsubscribe(origin, channel, id),
+            Call::ListSubscriptions(channel) => Bot::<T>::list_subscriptions(channel),
+            Call::Watch { network, target } => Bot::<T>::watch(origin, network, target.as_deref()),
+            Call::Unwatch(id) => Bot::<T>::unwatch(origin, id),
+            Call::ListWatches => Bot::<T>::list_watches(origin),
+            Call::Referendum {
+                channel,
+                network,
+                index,
+            } => Bot::<T>::referendum(channel, network.as_deref(), *index),
+            Call::VoteReminders { network, enabled } => {
+                Bot::<T>::vote_reminders(origin, network, *enabled)
+            }
+            Call::Validator {
+                channel,
+                network,
+                target,
+            } => Bot::<T>::validator(origin, channel, network.as_deref(), target),
+            Call::Nominations {
+                channel,
+                network,
+                target,
+            } => Bot::<T>::nominations(origin, channel, network.as_deref(), target),
+            Call::MonitorValidator {
+                network,
+                target,
+                unclaimed_eras,
+            } => Bot::<T>::monitor_validator(origin, network, target, *unclaimed_eras),
+            Call::UnmonitorValidator(id) => Bot::<T>::unmonitor_validator(origin, id),
+            Call::ListMonitoredValidators => Bot::<T>::list_monitored_validators(origin),
+            Call::SetQuietHours(quiet_hours) => {
+                Bot::<T>::set_quiet_hours(origin, quiet_hours.as_deref())
+            }
+            Call::ConfigureFaucet {
+                channel,
+                network,
+                setting,
+            } => Bot::<T>::configure_faucet_channel(origin, channel, network, setting.clone()),
+            Call::FaucetStatus(channel) => Bot::<T>::faucet_status(channel),
+            Call::FaucetBalance(channel) => Bot::<T>::faucet_balance(origin, channel),
+            Call::RotateSeedKey => Bot::<T>::rotate_seed_key(origin),
+            Call::AddNetwork {
+                name,
+                rpc_urls,
+                explorer_url,
+            } => Bot::<T>::add_network(origin, name, rpc_urls, explorer_url.as_deref()),
+            Call::RemoveNetwork(name) => Bot::<T>::remove_network(origin, name),
+            Call::ListNetworks => Bot::<T>::list_networks(),
             _ => Err(DispatchError::Other(String::from("Unsupported call"))),
         }
     }
@@ -51,4 +304,34 @@ pub enum Response {
     ReplyDirect(String),
     Say(String),
     SayChan(Origin, String),
+    SayDirect(Origin, String),
+    /// Reply that's edited with every update from the handle.
+    ReplyTracked(String, UpdateHandle),
+    Multiple(Vec<Response>),
+}
+
+#[derive(Clone, Debug)]
+/// Handle to the updates of a message that changes after it was sent, like
+/// the status of a transaction.
+pub struct UpdateHandle(Arc<Mutex<Receiver<String>>>);
+
+impl UpdateHandle {
+    /// Create a handle and the sender to push updates to it.
+    pub fn new() -> (Sender<String>, Self) {
+        let (sender, receiver) = channel();
+        (sender, Self(Arc::new(Mutex::new(receiver))))
+    }
+
+    /// Wait for the next update of the message.
+    ///
+    /// Returns `None` once the message won't change anymore.
+    pub fn next_update(&self) -> Option<String> {
+        self.0.lock().ok()?.recv().ok()
+    }
+}
+
+impl PartialEq for UpdateHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
 }
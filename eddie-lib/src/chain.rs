@@ -0,0 +1,1343 @@
+use codec::{Compact, Decode, Input};
+use serde::{Deserialize, Serialize};
+use sp_core::{
+    crypto::{AccountId32, DeriveJunction, Ss58AddressFormat, Ss58Codec},
+    sr25519, Pair, H256,
+};
+use sp_runtime::MultiAddress;
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use substrate_api_client::{
+    ac_node_api::{error::DispatchError as ChainDispatchError, StaticEvent},
+    ac_primitives::{DefaultRuntimeConfig, ExtrinsicSigner},
+    compose_call, compose_extrinsic_offline,
+    rpc::TungsteniteRpcClient,
+    Api, ExtrinsicReport, GetAccountInformation, GetChainInfo, GetStorage, SubmitAndWatch,
+    SubscribeEvents, SystemApi, XtStatus,
+};
+use support::traits::dispatch::DispatchError;
+
+pub const MODULE: &str = "CHAIN";
+
+/// Number of attempts to connect to a node before giving up.
+const MAX_CONNECT_ATTEMPTS: u8 = 3;
+
+/// Time to wait between checks of the finalized head.
+const FINALITY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Time after which an included extrinsic that isn't finalized is given up on.
+const FINALITY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Type of the api used to talk to Substrate nodes.
+pub type ChainApi = Api<DefaultRuntimeConfig, TungsteniteRpcClient>;
+
+/// Type of balances on Substrate chains.
+pub type Balance = u128;
+
+/// Token symbol used when a chain doesn't define one.
+const DEFAULT_TOKEN_SYMBOL: &str = "UNIT";
+
+/// Token decimals used when a chain doesn't define them.
+const DEFAULT_TOKEN_DECIMALS: u8 = 12;
+
+/// SS58 prefix used when a chain doesn't define one.
+const DEFAULT_SS58_PREFIX: u16 = 42;
+
+fn default_ss58_prefix() -> u16 {
+    DEFAULT_SS58_PREFIX
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Properties identifying a chain and its native token.
+pub struct ChainInfo {
+    pub name: String,
+    pub genesis_hash: String,
+    pub token_symbol: String,
+    pub token_decimals: u8,
+    #[serde(default = "default_ss58_prefix")]
+    pub ss58_prefix: u16,
+}
+
+impl ChainInfo {
+    /// Format an amount of the chain's native token.
+    pub fn format_balance(&self, amount: Balance) -> String {
+        format_balance(amount, self.token_decimals, &self.token_symbol)
+    }
+
+    /// Parse an amount of the chain's native token.
+    pub fn parse_balance(&self, amount: &str) -> Result<Balance, ChainError> {
+        parse_balance(amount, self.token_decimals)
+    }
+
+    /// Encode an account id as a SS58 address of the chain.
+    pub fn address_of(&self, account: &AccountId32) -> String {
+        account.to_ss58check_with_version(Ss58AddressFormat::custom(self.ss58_prefix))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// Balances of an account.
+pub struct AccountBalance {
+    pub free: Balance,
+    pub reserved: Balance,
+    pub frozen: Balance,
+}
+
+#[derive(Clone, Debug)]
+/// All potential errors when interacting with a chain.
+pub enum ChainError {
+    /// The node couldn't be reached.
+    Connection(String),
+    /// The seed couldn't be turned into a key pair.
+    InvalidSeed,
+    /// The address isn't a valid SS58 or EVM address.
+    InvalidAddress,
+    /// The address was encoded for another network.
+    WrongNetwork(u16),
+    /// The amount isn't a positive number of tokens.
+    InvalidAmount,
+    /// The extrinsic couldn't be created or was rejected.
+    Extrinsic(String),
+    /// The extrinsic was included, but failed to dispatch.
+    Dispatch(String),
+    /// Following the events of the chain failed.
+    Subscription(String),
+}
+
+impl std::fmt::Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let err = match self {
+            Self::Connection(err) => format!("Couldn't connect to node: {}", err),
+            Self::InvalidSeed => String::from("Invalid wallet seed"),
+            Self::InvalidAddress => String::from("Invalid address"),
+            Self::WrongNetwork(prefix) => format!(
+                "The address belongs to another network, please use an address with SS58 prefix {}",
+                prefix
+            ),
+            Self::InvalidAmount => String::from("Invalid amount"),
+            Self::Extrinsic(err) => format!("Transaction failed: {}", err),
+            Self::Dispatch(err) => format!("Transaction failed with {}", err),
+            Self::Subscription(err) => format!("Event subscription failed: {}", err),
+        };
+
+        write!(f, "{}", err)
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+impl From<ChainError> for DispatchError {
+    fn from(value: ChainError) -> Self {
+        DispatchError::Module(String::from(MODULE), format!("{}", value))
+    }
+}
+
+#[derive(Decode)]
+/// `Balances::Transfer` event.
+struct TransferEvent {
+    from: AccountId32,
+    to: AccountId32,
+    amount: Balance,
+}
+
+impl StaticEvent for TransferEvent {
+    const PALLET: &'static str = "Balances";
+    const EVENT: &'static str = "Transfer";
+}
+
+#[derive(Decode)]
+/// `TransactionPayment::TransactionFeePaid` event.
+struct TransactionFeePaidEvent {
+    _who: AccountId32,
+    actual_fee: Balance,
+    tip: Balance,
+}
+
+impl StaticEvent for TransactionFeePaidEvent {
+    const PALLET: &'static str = "TransactionPayment";
+    const EVENT: &'static str = "TransactionFeePaid";
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An event emitted in a block.
+pub struct ChainEvent {
+    pub pallet: String,
+    pub variant: String,
+    /// Fields of the event as decoded with the runtime metadata.
+    pub fields: String,
+    /// Sender, recipient and amount of a `Balances::Transfer`.
+    pub transfer: Option<(AccountId32, AccountId32, Balance)>,
+    /// Account and amount of an event that changes the balance or locks of
+    /// a single account, see `ACCOUNT_EVENTS`.
+    pub account: Option<(AccountId32, Balance)>,
+    /// Index of the referendum of a `Referenda` event.
+    pub referendum: Option<u32>,
+}
+
+/// Events with an account and an amount as their fields, which are decoded
+/// to notify the owners of watched addresses.
+pub const ACCOUNT_EVENTS: [(&str, &str); 6] = [
+    ("Balances", "Slashed"),
+    ("Balances", "Locked"),
+    ("Balances", "Unlocked"),
+    ("Balances", "Frozen"),
+    ("Balances", "Thawed"),
+    ("Staking", "Slashed"),
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Block an extrinsic was included in.
+pub struct Inclusion {
+    /// Hash of the extrinsic.
+    pub hash: String,
+    pub block_hash: String,
+    pub block: u32,
+    /// Fee the signer paid, including the tip.
+    pub fee: Balance,
+}
+
+/// Connect to the node at the given rpc url.
+pub fn connect(rpc_url: &str) -> Result<ChainApi, ChainError> {
+    let client = TungsteniteRpcClient::new(rpc_url, MAX_CONNECT_ATTEMPTS)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?;
+    ChainApi::new(client).map_err(|err| ChainError::Connection(format!("{:?}", err)))
+}
+
+/// Connect to the first reachable node of the given rpc urls.
+pub fn connect_any(rpc_urls: &[String]) -> Result<ChainApi, ChainError> {
+    let mut last_err = ChainError::Connection("No rpc urls".into());
+    for rpc_url in rpc_urls {
+        match connect(rpc_url) {
+            Ok(api) => return Ok(api),
+            Err(err) => {
+                log::warn!("Couldn't connect to {}: {}", rpc_url, err);
+                last_err = err;
+            }
+        }
+    }
+    Err(last_err)
+}
+
+/// Get the name, genesis hash and native token of the connected chain.
+pub fn chain_info(api: &ChainApi) -> Result<ChainInfo, ChainError> {
+    let name = api
+        .get_system_chain()
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?;
+    let properties = api
+        .get_system_properties()
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?;
+
+    // Chains with multiple tokens list them in arrays, with the native token
+    // first.
+    let property = |key: &str| {
+        properties
+            .get(key)
+            .and_then(|value| match value.as_array() {
+                Some(values) => values.first().cloned(),
+                None => Some(value.clone()),
+            })
+    };
+    let token_symbol = property("tokenSymbol")
+        .and_then(|value| value.as_str().map(String::from))
+        .unwrap_or(DEFAULT_TOKEN_SYMBOL.into());
+    let token_decimals = property("tokenDecimals")
+        .and_then(|value| value.as_u64())
+        .and_then(|value| u8::try_from(value).ok())
+        .unwrap_or(DEFAULT_TOKEN_DECIMALS);
+    let ss58_prefix = properties
+        .get("ss58Format")
+        .and_then(|value| value.as_u64())
+        .and_then(|value| u16::try_from(value).ok())
+        .unwrap_or(DEFAULT_SS58_PREFIX);
+
+    Ok(ChainInfo {
+        name,
+        genesis_hash: genesis_hash(api),
+        token_symbol,
+        token_decimals,
+        ss58_prefix,
+    })
+}
+
+/// Get the genesis hash of the connected chain.
+pub fn genesis_hash(api: &ChainApi) -> String {
+    format!("{:?}", api.genesis_hash())
+}
+
+/// Format an amount of tokens with the given decimals and symbol.
+pub fn format_balance(amount: Balance, decimals: u8, symbol: &str) -> String {
+    // Any amount is a fraction of a token with more decimals than fit.
+    let (whole, fraction) = match 10u128.checked_pow(decimals as u32) {
+        Some(unit) => (amount / unit, amount % unit),
+        None => (0, amount),
+    };
+    let fraction = format!("{:0width$}", fraction, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => format!("{} {}", whole, symbol),
+        false => format!("{}.{} {}", whole, fraction, symbol),
+    }
+}
+
+/// Parse a positive amount of tokens with up to `decimals` decimals, like
+/// `1.5`, into the smallest unit of the token.
+pub fn parse_balance(amount: &str, decimals: u8) -> Result<Balance, ChainError> {
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty())
+        || !digits(whole)
+        || !digits(fraction)
+        || fraction.len() > decimals as usize
+    {
+        return Err(ChainError::InvalidAmount);
+    }
+
+    let unit = 10u128
+        .checked_pow(decimals as u32)
+        .ok_or(ChainError::InvalidAmount)?;
+    let whole: Balance = match whole.is_empty() {
+        true => 0,
+        false => whole.parse().map_err(|_| ChainError::InvalidAmount)?,
+    };
+    let fraction: Balance = format!("{:0<width$}", fraction, width = decimals as usize)
+        .parse()
+        .unwrap_or_default();
+    match whole
+        .checked_mul(unit)
+        .and_then(|whole| whole.checked_add(fraction))
+    {
+        Some(amount) if amount > 0 => Ok(amount),
+        _ => Err(ChainError::InvalidAmount),
+    }
+}
+
+/// Turn a seed phrase or secret uri into a sr25519 key pair.
+pub fn pair_from_seed(seed: &str) -> Result<sr25519::Pair, ChainError> {
+    sr25519::Pair::from_string(seed, None).map_err(|_| ChainError::InvalidSeed)
+}
+
+/// Derive a key pair from `pair` using hard derivation along `path`.
+pub fn derive_pair(pair: &sr25519::Pair, path: &[&str]) -> Result<sr25519::Pair, ChainError> {
+    let path = path.iter().map(DeriveJunction::hard);
+    pair.derive(path, None)
+        .map(|(pair, _)| pair)
+        .map_err(|_| ChainError::InvalidSeed)
+}
+
+/// Decode a SS58 address into an account id.
+pub fn parse_address(address: &str) -> Result<AccountId32, ChainError> {
+    AccountId32::from_ss58check_with_version(address.trim())
+        .map(|(account, _)| account)
+        .map_err(|_| ChainError::InvalidAddress)
+}
+
+/// Parse an EVM (H160) address like `0x5aAe…` into its EIP-55 checksummed
+/// form.
+///
+/// The checksum is only checked for mixed case addresses, all lower or upper
+/// case addresses have none.
+pub fn parse_evm_address(address: &str) -> Result<String, ChainError> {
+    let address = address.trim();
+    let hex = address
+        .strip_prefix("0x")
+        .filter(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or(ChainError::InvalidAddress)?;
+
+    let lower = hex.to_ascii_lowercase();
+    let hash = sp_core::hashing::keccak_256(lower.as_bytes());
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(
+            |(i, c)| match (hash[i / 2] >> (4 * (1 - i % 2))) & 0xf >= 8 {
+                true => c.to_ascii_uppercase(),
+                false => c,
+            },
+        )
+        .collect();
+
+    let mixed_case = hex != lower && hex != hex.to_ascii_uppercase();
+    if mixed_case && hex != checksummed {
+        return Err(ChainError::InvalidAddress);
+    }
+    Ok(format!("0x{}", checksummed))
+}
+
+/// Decode a SS58 address and check that it was encoded for a network.
+///
+/// Addresses with the generic substrate prefix are accepted on every network.
+pub fn parse_network_address(address: &str, prefix: u16) -> Result<AccountId32, ChainError> {
+    let (account, format) = AccountId32::from_ss58check_with_version(address.trim())
+        .map_err(|_| ChainError::InvalidAddress)?;
+    match u16::from(format) {
+        found if found == prefix || found == DEFAULT_SS58_PREFIX => Ok(account),
+        _ => Err(ChainError::WrongNetwork(prefix)),
+    }
+}
+
+/// Get the account id of a key pair.
+pub fn account_of(pair: &sr25519::Pair) -> AccountId32 {
+    pair.public().into()
+}
+
+/// Encode an account id as a SS58 address.
+pub fn to_address(account: &AccountId32) -> String {
+    account.to_ss58check()
+}
+
+/// Get the free balance of an account.
+pub fn free_balance(api: &ChainApi, account: &AccountId32) -> Result<Balance, ChainError> {
+    Ok(account_balance(api, account)?.free)
+}
+
+/// Get the balances of an account from `System::Account`.
+///
+/// Accounts that don't exist on the chain have no balance.
+pub fn account_balance(
+    api: &ChainApi,
+    account: &AccountId32,
+) -> Result<AccountBalance, ChainError> {
+    Ok(api
+        .get_account_data(account)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .map(|data| AccountBalance {
+            free: data.free,
+            reserved: data.reserved,
+            frozen: data.frozen,
+        })
+        .unwrap_or_default())
+}
+
+/// Set the key pair extrinsics are signed with.
+pub fn set_signer(api: &mut ChainApi, signer: sr25519::Pair) {
+    api.set_signer(ExtrinsicSigner::<DefaultRuntimeConfig>::new(signer));
+}
+
+/// Get the nonce of the next extrinsic of an account, counting the ones in
+/// the transaction pool.
+pub fn next_nonce(api: &ChainApi, account: &AccountId32) -> Result<u32, ChainError> {
+    api.get_account_next_index(account)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))
+}
+
+/// Transfer `amount` from the account of the signer to `dest` with the given
+/// nonce, keeping the signer's account alive.
+///
+/// Blocks until the extrinsic has been included in a block.
+pub fn transfer_keep_alive(
+    api: &ChainApi,
+    dest: AccountId32,
+    amount: Balance,
+    nonce: u32,
+) -> Result<Inclusion, ChainError> {
+    let signer = api
+        .signer()
+        .ok_or(ChainError::Extrinsic("No signer set".into()))?;
+    let call = compose_call!(
+        api.metadata(),
+        "Balances",
+        "transfer_keep_alive",
+        MultiAddress::<AccountId32, u32>::Id(dest),
+        Compact(amount)
+    )
+    .ok_or(ChainError::Extrinsic("Balances pallet not found".into()))?;
+    let xt = compose_extrinsic_offline!(signer, call, api.extrinsic_params(nonce));
+
+    let report = api
+        .submit_and_watch_extrinsic_until(xt, XtStatus::InBlock)
+        .map_err(extrinsic_error)?;
+    inclusion(api, &report)
+}
+
+/// Transfer several amounts from the account of the signer in a single
+/// `Utility::batch_all` with the given nonce, keeping the signer's account
+/// alive.
+///
+/// Blocks until the extrinsic has been included in a block. Returns the
+/// transfers that were made according to the events of the block.
+pub fn batch_transfer_keep_alive(
+    api: &ChainApi,
+    transfers: &[(AccountId32, Balance)],
+    nonce: u32,
+) -> Result<(Inclusion, Vec<(AccountId32, Balance)>), ChainError> {
+    let signer = api
+        .signer()
+        .ok_or(ChainError::Extrinsic("No signer set".into()))?;
+    let calls = transfers
+        .iter()
+        .map(|(dest, amount)| {
+            compose_call!(
+                api.metadata(),
+                "Balances",
+                "transfer_keep_alive",
+                MultiAddress::<AccountId32, u32>::Id(dest.clone()),
+                Compact(*amount)
+            )
+            .ok_or(ChainError::Extrinsic("Balances pallet not found".into()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let call = compose_call!(api.metadata(), "Utility", "batch_all", calls)
+        .ok_or(ChainError::Extrinsic("Utility pallet not found".into()))?;
+    let xt = compose_extrinsic_offline!(signer, call, api.extrinsic_params(nonce));
+
+    let report = api
+        .submit_and_watch_extrinsic_until(xt, XtStatus::InBlock)
+        .map_err(extrinsic_error)?;
+    let mut transferred = Vec::new();
+    for event in report.events.iter().flatten() {
+        if let Ok(Some(transfer)) = event.as_event::<TransferEvent>() {
+            transferred.push((transfer.to, transfer.amount));
+        }
+    }
+    Ok((inclusion(api, &report)?, transferred))
+}
+
+/// Transfer the whole balance of the signer's account to `dest` with the
+/// given nonce, letting the account be reaped.
+///
+/// Blocks until the extrinsic has been included in a block. Returns the
+/// amount that arrived at `dest` according to the events of the block, which
+/// is what's left after the fee.
+pub fn sweep(
+    api: &ChainApi,
+    dest: AccountId32,
+    nonce: u32,
+) -> Result<(Inclusion, Balance), ChainError> {
+    let signer = api
+        .signer()
+        .ok_or(ChainError::Extrinsic("No signer set".into()))?;
+    let call = compose_call!(
+        api.metadata(),
+        "Balances",
+        "transfer_all",
+        MultiAddress::<AccountId32, u32>::Id(dest),
+        false
+    )
+    .ok_or(ChainError::Extrinsic("Balances pallet not found".into()))?;
+    let xt = compose_extrinsic_offline!(signer, call, api.extrinsic_params(nonce));
+
+    let report = api
+        .submit_and_watch_extrinsic_until(xt, XtStatus::InBlock)
+        .map_err(extrinsic_error)?;
+    let swept = report
+        .events
+        .iter()
+        .flatten()
+        .filter_map(|event| event.as_event::<TransferEvent>().ok().flatten())
+        .map(|transfer| transfer.amount)
+        .sum();
+    Ok((inclusion(api, &report)?, swept))
+}
+
+/// Follow the events of new blocks.
+///
+/// Calls `on_block` with the hash and the events of every block until it
+/// returns `false`. Blocks until then, or until the subscription fails.
+pub fn watch_events(
+    api: &ChainApi,
+    mut on_block: impl FnMut(H256, Vec<ChainEvent>) -> bool,
+) -> Result<(), ChainError> {
+    let mut subscription = api
+        .subscribe_events()
+        .map_err(|err| ChainError::Subscription(format!("{:?}", err)))?;
+    while let Some(events) = subscription.next_events_from_metadata() {
+        let events = events.map_err(|err| ChainError::Subscription(format!("{:?}", err)))?;
+        let mut block = Vec::new();
+        for event in events.iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    log::warn!("Couldn't decode an event: {:?}", err);
+                    continue;
+                }
+            };
+            block.push(ChainEvent {
+                pallet: event.pallet_name().to_string(),
+                variant: event.variant_name().to_string(),
+                fields: event
+                    .field_values()
+                    .map(|fields| fields.to_string())
+                    .unwrap_or_default(),
+                transfer: event
+                    .as_event::<TransferEvent>()
+                    .ok()
+                    .flatten()
+                    .map(|transfer| (transfer.from, transfer.to, transfer.amount)),
+                account: ACCOUNT_EVENTS
+                    .contains(&(event.pallet_name(), event.variant_name()))
+                    .then(|| <(AccountId32, Balance)>::decode(&mut event.field_bytes()).ok())
+                    .flatten(),
+                // All events of the pallet start with the index.
+                referendum: (event.pallet_name() == "Referenda")
+                    .then(|| u32::decode(&mut event.field_bytes()).ok())
+                    .flatten(),
+            });
+        }
+        if !on_block(events.block_hash(), block) {
+            subscription.unsubscribe();
+            return Ok(());
+        }
+    }
+    Err(ChainError::Subscription(
+        "the node closed the subscription".into(),
+    ))
+}
+
+/// Time between blocks used when a chain doesn't define it.
+const DEFAULT_BLOCK_TIME: Duration = Duration::from_secs(6);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Decode)]
+/// Votes on a referendum.
+pub struct Tally {
+    pub ayes: Balance,
+    pub nays: Balance,
+    /// Aye votes without conviction, which count towards the turnout.
+    pub support: Balance,
+}
+
+impl Tally {
+    /// Share of the votes in favour, in percent.
+    pub fn approval(&self) -> f64 {
+        match self.ayes + self.nays {
+            0 => 0.0,
+            total => self.ayes as f64 * 100.0 / total as f64,
+        }
+    }
+
+    /// Support as a share of the issuance, in percent.
+    pub fn turnout(&self, issuance: Balance) -> f64 {
+        match issuance {
+            0 => 0.0,
+            issuance => self.support as f64 * 100.0 / issuance as f64,
+        }
+    }
+}
+
+/// Origin a referendum was submitted with.
+///
+/// Origins are specific to a runtime and can't be decoded without its
+/// metadata. The relay chain runtimes use unit variants for all of them,
+/// except for `frame_system::RawOrigin::Signed` which carries an account.
+struct ProposalOrigin;
+
+impl Decode for ProposalOrigin {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let (pallet, variant) = (input.read_byte()?, input.read_byte()?);
+        if pallet == 0 && variant == 1 {
+            AccountId32::decode(input)?;
+        }
+        Ok(Self)
+    }
+}
+
+#[derive(Decode)]
+/// Call a referendum dispatches, or its hash.
+enum BoundedCall {
+    Legacy { _hash: H256 },
+    Inline { _call: Vec<u8> },
+    Lookup { _hash: H256, _len: u32 },
+}
+
+#[derive(Decode)]
+/// Block a call is dispatched at.
+enum DispatchTime {
+    At { _block: u32 },
+    After { _blocks: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Decode)]
+/// Deposit placed for a referendum.
+pub struct Deposit {
+    pub who: AccountId32,
+    pub amount: Balance,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Decode)]
+/// Decision phase of a referendum.
+pub struct DecidingStatus {
+    /// Block the decision phase started at.
+    pub since: u32,
+    /// Block the referendum passes at if it keeps being approved.
+    pub confirming: Option<u32>,
+}
+
+#[derive(Decode)]
+/// `Referenda::ReferendumStatus` of a relay chain runtime.
+struct RawReferendumStatus {
+    track: u16,
+    _origin: ProposalOrigin,
+    _proposal: BoundedCall,
+    _enactment: DispatchTime,
+    submitted: u32,
+    _submission_deposit: Deposit,
+    decision_deposit: Option<Deposit>,
+    deciding: Option<DecidingStatus>,
+    tally: Tally,
+    _in_queue: bool,
+    _alarm: Option<(u32, (u32, u32))>,
+}
+
+#[derive(Decode)]
+/// `Referenda::ReferendumInfo` of a relay chain runtime.
+enum RawReferendumInfo {
+    Ongoing(RawReferendumStatus),
+    Approved {
+        block: u32,
+        _submission_deposit: Option<Deposit>,
+        _decision_deposit: Option<Deposit>,
+    },
+    Rejected {
+        block: u32,
+        _submission_deposit: Option<Deposit>,
+        _decision_deposit: Option<Deposit>,
+    },
+    Cancelled {
+        block: u32,
+        _submission_deposit: Option<Deposit>,
+        _decision_deposit: Option<Deposit>,
+    },
+    TimedOut {
+        block: u32,
+        _submission_deposit: Option<Deposit>,
+        _decision_deposit: Option<Deposit>,
+    },
+    Killed {
+        block: u32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// State of an OpenGov referendum.
+pub enum Referendum {
+    Ongoing {
+        track: u16,
+        /// Block it was submitted at.
+        submitted: u32,
+        decision_deposit: Option<Deposit>,
+        deciding: Option<DecidingStatus>,
+        tally: Tally,
+    },
+    /// Ended with the given outcome at a block.
+    Ended { outcome: &'static str, block: u32 },
+}
+
+impl From<RawReferendumInfo> for Referendum {
+    fn from(info: RawReferendumInfo) -> Self {
+        let ended = |outcome, block| Referendum::Ended { outcome, block };
+        match info {
+            RawReferendumInfo::Ongoing(status) => Referendum::Ongoing {
+                track: status.track,
+                submitted: status.submitted,
+                decision_deposit: status.decision_deposit,
+                deciding: status.deciding,
+                tally: status.tally,
+            },
+            RawReferendumInfo::Approved { block, .. } => ended("approved", block),
+            RawReferendumInfo::Rejected { block, .. } => ended("rejected", block),
+            RawReferendumInfo::Cancelled { block, .. } => ended("cancelled", block),
+            RawReferendumInfo::TimedOut { block, .. } => ended("timed out", block),
+            RawReferendumInfo::Killed { block } => ended("killed", block),
+        }
+    }
+}
+
+#[derive(Decode)]
+/// Threshold a referendum has to pass over the decision period.
+enum Curve {
+    LinearDecreasing {
+        _length: u32,
+        _floor: u32,
+        _ceil: u32,
+    },
+    SteppedDecreasing {
+        _begin: u32,
+        _end: u32,
+        _step: u32,
+        _period: u32,
+    },
+    Reciprocal {
+        _factor: i64,
+        _x_offset: i64,
+        _y_offset: i64,
+    },
+}
+
+#[derive(Decode)]
+/// `Referenda::TrackInfo` of a relay chain runtime.
+struct RawTrackInfo {
+    name: String,
+    _max_deciding: u32,
+    _decision_deposit: Balance,
+    prepare_period: u32,
+    decision_period: u32,
+    confirm_period: u32,
+    _min_enactment_period: u32,
+    _min_approval: Curve,
+    _min_support: Curve,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Periods of a referendum track, in blocks.
+pub struct Track {
+    pub id: u16,
+    pub name: String,
+    pub prepare_period: u32,
+    pub decision_period: u32,
+    pub confirm_period: u32,
+}
+
+#[derive(Decode)]
+struct Delegations {
+    _votes: Balance,
+    _capital: Balance,
+}
+
+#[derive(Decode)]
+struct PriorLock {
+    _until: u32,
+    _amount: Balance,
+}
+
+#[derive(Decode)]
+enum AccountVote {
+    Standard {
+        _vote: u8,
+        _balance: Balance,
+    },
+    Split {
+        _aye: Balance,
+        _nay: Balance,
+    },
+    SplitAbstain {
+        _aye: Balance,
+        _nay: Balance,
+        _abstain: Balance,
+    },
+}
+
+#[derive(Decode)]
+/// `ConvictionVoting::Voting` of an account on a track.
+enum Voting {
+    Casting {
+        votes: Vec<(u32, AccountVote)>,
+        _delegations: Delegations,
+        _prior: PriorLock,
+    },
+    Delegating {
+        _balance: Balance,
+        _target: AccountId32,
+        _conviction: u8,
+        _delegations: Delegations,
+        _prior: PriorLock,
+    },
+}
+
+/// Get the number of the best block.
+pub fn best_block(api: &ChainApi) -> Result<u32, ChainError> {
+    api.get_header(None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .map(|header| header.number)
+        .ok_or(ChainError::Connection("Block not found".into()))
+}
+
+/// Get the expected time between blocks.
+pub fn block_time(api: &ChainApi) -> Duration {
+    api.get_constant::<u64>("Babe", "ExpectedBlockTime")
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_BLOCK_TIME)
+}
+
+/// Get the issuance that can vote, which turnout is measured against.
+pub fn active_issuance(api: &ChainApi) -> Result<Balance, ChainError> {
+    let get = |item| {
+        api.get_storage::<Balance>("Balances", item, None)
+            .map(Option::unwrap_or_default)
+            .map_err(|err| ChainError::Connection(format!("{:?}", err)))
+    };
+    Ok(get("TotalIssuance")?.saturating_sub(get("InactiveIssuance")?))
+}
+
+/// Get the number of referenda submitted so far.
+pub fn referendum_count(api: &ChainApi) -> Result<u32, ChainError> {
+    Ok(api
+        .get_storage::<u32>("Referenda", "ReferendumCount", None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .unwrap_or_default())
+}
+
+/// Get a referendum, `None` if it doesn't exist.
+pub fn referendum(api: &ChainApi, index: u32) -> Result<Option<Referendum>, ChainError> {
+    Ok(api
+        .get_storage_map::<u32, RawReferendumInfo>("Referenda", "ReferendumInfoFor", index, None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .map(Referendum::from))
+}
+
+/// Get the tracks referenda are submitted on.
+pub fn referendum_tracks(api: &ChainApi) -> Result<Vec<Track>, ChainError> {
+    Ok(api
+        .get_constant::<Vec<(u16, RawTrackInfo)>>("Referenda", "Tracks")
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .into_iter()
+        .map(|(id, track)| Track {
+            id,
+            name: track.name,
+            prepare_period: track.prepare_period,
+            decision_period: track.decision_period,
+            confirm_period: track.confirm_period,
+        })
+        .collect())
+}
+
+/// Check whether an account voted on a referendum, delegating its votes on
+/// the track counts as voting.
+pub fn has_voted(
+    api: &ChainApi,
+    account: &AccountId32,
+    track: u16,
+    index: u32,
+) -> Result<bool, ChainError> {
+    let voting = api
+        .get_storage_double_map::<_, _, Voting>(
+            "ConvictionVoting",
+            "VotingFor",
+            account,
+            track,
+            None,
+        )
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?;
+    Ok(match voting {
+        Some(Voting::Casting { votes, .. }) => votes.iter().any(|(poll, _)| *poll == index),
+        Some(Voting::Delegating { .. }) => true,
+        None => false,
+    })
+}
+
+/// Number of eras payouts can be claimed for when a chain doesn't define it.
+const DEFAULT_HISTORY_DEPTH: u32 = 84;
+
+#[derive(Clone, Copy, Debug, PartialEq, Decode)]
+/// `Staking::ValidatorPrefs` of a validator.
+pub struct ValidatorPrefs {
+    /// Commission in parts per billion.
+    #[codec(compact)]
+    pub commission: u32,
+    /// Whether the validator accepts no new nominations.
+    pub blocked: bool,
+}
+
+#[derive(Decode)]
+struct UnlockChunk {
+    #[codec(compact)]
+    _value: Balance,
+    #[codec(compact)]
+    _era: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Bonded funds of a stash.
+pub struct StakingLedger {
+    pub total: Balance,
+    /// Funds that aren't being unbonded.
+    pub active: Balance,
+    /// Eras claimed before payouts were paged.
+    pub legacy_claimed_rewards: Vec<u32>,
+}
+
+#[derive(Decode)]
+/// `Staking::StakingLedger` of a relay chain runtime.
+struct RawStakingLedger {
+    _stash: AccountId32,
+    #[codec(compact)]
+    total: Balance,
+    #[codec(compact)]
+    active: Balance,
+    _unlocking: Vec<UnlockChunk>,
+    legacy_claimed_rewards: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Decode)]
+/// Stake backing a validator in an era.
+pub struct ExposureOverview {
+    #[codec(compact)]
+    pub total: Balance,
+    /// Stake of the validator itself.
+    #[codec(compact)]
+    pub own: Balance,
+    pub nominator_count: u32,
+    /// Number of pages the payout of the era is split into.
+    pub page_count: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Decode)]
+/// Points validators earned in an era, which their payouts are shared by.
+pub struct EraRewardPoints {
+    pub total: u32,
+    pub individual: std::collections::BTreeMap<AccountId32, u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Decode)]
+/// Validators a nominator backs.
+pub struct Nominations {
+    pub targets: Vec<AccountId32>,
+    /// Era the nominations were made in.
+    pub submitted_in: u32,
+    pub suppressed: bool,
+}
+
+#[derive(Decode)]
+struct ActiveEraInfo {
+    index: u32,
+    _start: Option<u64>,
+}
+
+/// Get the index of the active era.
+pub fn active_era(api: &ChainApi) -> Result<u32, ChainError> {
+    api.get_storage::<ActiveEraInfo>("Staking", "ActiveEra", None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .map(|era| era.index)
+        .ok_or(ChainError::Connection("No active era".into()))
+}
+
+/// Get the number of eras payouts can be claimed for.
+pub fn history_depth(api: &ChainApi) -> u32 {
+    api.get_constant::<u32>("Staking", "HistoryDepth")
+        .unwrap_or(DEFAULT_HISTORY_DEPTH)
+}
+
+/// Get the validators of the current session, which is the active set.
+pub fn session_validators(api: &ChainApi) -> Result<Vec<AccountId32>, ChainError> {
+    Ok(api
+        .get_storage::<Vec<AccountId32>>("Session", "Validators", None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .unwrap_or_default())
+}
+
+/// Get the preferences of a validator, `None` if it doesn't intend to
+/// validate, like after being chilled.
+pub fn validator_prefs(
+    api: &ChainApi,
+    stash: &AccountId32,
+) -> Result<Option<ValidatorPrefs>, ChainError> {
+    api.get_storage_map("Staking", "Validators", stash, None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))
+}
+
+/// Get the bonded funds of a stash, `None` if it isn't bonded.
+pub fn staking_ledger(
+    api: &ChainApi,
+    stash: &AccountId32,
+) -> Result<Option<StakingLedger>, ChainError> {
+    let Some(controller) = api
+        .get_storage_map::<_, AccountId32>("Staking", "Bonded", stash, None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+    else {
+        return Ok(None);
+    };
+    Ok(api
+        .get_storage_map::<_, RawStakingLedger>("Staking", "Ledger", controller, None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .map(|ledger| StakingLedger {
+            total: ledger.total,
+            active: ledger.active,
+            legacy_claimed_rewards: ledger.legacy_claimed_rewards,
+        }))
+}
+
+/// Get the stake backing a validator in an era, `None` if it wasn't active.
+pub fn era_exposure(
+    api: &ChainApi,
+    era: u32,
+    stash: &AccountId32,
+) -> Result<Option<ExposureOverview>, ChainError> {
+    api.get_storage_double_map("Staking", "ErasStakersOverview", era, stash, None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))
+}
+
+/// Get the points validators earned in an era.
+pub fn era_reward_points(api: &ChainApi, era: u32) -> Result<EraRewardPoints, ChainError> {
+    Ok(api
+        .get_storage_map("Staking", "ErasRewardPoints", era, None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .unwrap_or_default())
+}
+
+/// Get the nominations of a stash, `None` if it doesn't nominate.
+pub fn nominations(api: &ChainApi, stash: &AccountId32) -> Result<Option<Nominations>, ChainError> {
+    api.get_storage_map("Staking", "Nominators", stash, None)
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))
+}
+
+/// Get the eras in `eras` a validator was active in and hasn't claimed all
+/// payouts of.
+pub fn unclaimed_eras(
+    api: &ChainApi,
+    stash: &AccountId32,
+    eras: std::ops::Range<u32>,
+) -> Result<Vec<u32>, ChainError> {
+    let legacy_claimed = staking_ledger(api, stash)?
+        .map(|ledger| ledger.legacy_claimed_rewards)
+        .unwrap_or_default();
+    let mut unclaimed = Vec::new();
+    for era in eras {
+        if legacy_claimed.contains(&era) {
+            continue;
+        }
+        let Some(exposure) = era_exposure(api, era, stash)? else {
+            continue;
+        };
+        let claimed = api
+            .get_storage_double_map::<_, _, Vec<u32>>("Staking", "ClaimedRewards", era, stash, None)
+            .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+            .unwrap_or_default();
+        if (claimed.len() as u32) < exposure.page_count {
+            unclaimed.push(era);
+        }
+    }
+    Ok(unclaimed)
+}
+
+/// Get where a submitted extrinsic was included.
+fn inclusion(api: &ChainApi, report: &ExtrinsicReport<H256>) -> Result<Inclusion, ChainError> {
+    let block_hash = report.block_hash.ok_or(ChainError::Extrinsic(
+        "Block of the transaction unknown".into(),
+    ))?;
+
+    let fee = report
+        .events
+        .iter()
+        .flatten()
+        .filter_map(|event| event.as_event::<TransactionFeePaidEvent>().ok().flatten())
+        .map(|fee| fee.actual_fee + fee.tip)
+        .sum();
+
+    Ok(Inclusion {
+        hash: format!("{:?}", report.extrinsic_hash),
+        block_hash: format!("{:?}", block_hash),
+        block: block_number(api, block_hash)?,
+        fee,
+    })
+}
+
+/// Get the number of a block.
+fn block_number(api: &ChainApi, block_hash: H256) -> Result<u32, ChainError> {
+    api.get_header(Some(block_hash))
+        .map_err(|err| ChainError::Connection(format!("{:?}", err)))?
+        .map(|header| header.number)
+        .ok_or(ChainError::Connection("Block not found".into()))
+}
+
+/// Wait until the block an extrinsic was included in has been finalized.
+///
+/// Fails if another block was finalized at its height or finalization takes
+/// too long.
+pub fn wait_for_finality(api: &ChainApi, inclusion: &Inclusion) -> Result<(), ChainError> {
+    let block_hash = H256::from_str(&inclusion.block_hash)
+        .map_err(|_| ChainError::Extrinsic("Invalid block hash".into()))?;
+
+    let started = Instant::now();
+    loop {
+        let finalized_head = api
+            .get_finalized_head()
+            .map_err(|err| ChainError::Connection(format!("{:?}", err)))?;
+        if let Some(finalized_head) = finalized_head {
+            if block_number(api, finalized_head)? >= inclusion.block {
+                let canonical = api
+                    .get_block_hash(Some(inclusion.block))
+                    .map_err(|err| ChainError::Connection(format!("{:?}", err)))?;
+                return match canonical == Some(block_hash) {
+                    true => Ok(()),
+                    false => Err(ChainError::Extrinsic("The block was retracted".into())),
+                };
+            }
+        }
+
+        if started.elapsed() > FINALITY_TIMEOUT {
+            return Err(ChainError::Extrinsic(
+                "The block wasn't finalized in time".into(),
+            ));
+        }
+        std::thread::sleep(FINALITY_POLL_INTERVAL);
+    }
+}
+
+/// Turn a failed submission into an error, decoding module errors into the
+/// names of their pallet and error.
+fn extrinsic_error(err: substrate_api_client::Error) -> ChainError {
+    match err {
+        substrate_api_client::Error::FailedExtrinsic(failed) => match failed.dispatch_error() {
+            ChainDispatchError::Module(module) => {
+                ChainError::Dispatch(format!("{}::{}", module.pallet, module.error))
+            }
+            err => ChainError::Dispatch(format!("{:?}", err)),
+        },
+        err => ChainError::Extrinsic(format!("{:?}", err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_pair_matches_secret_uri() {
+        let master = pair_from_seed("//Alice").unwrap();
+        let derived = derive_pair(&master, &["faucet", "Telegram(-1001)"]).unwrap();
+        let expected = pair_from_seed("//Alice//faucet//Telegram(-1001)").unwrap();
+        assert_eq!(derived.public(), expected.public());
+    }
+
+    #[test]
+    fn referendum_decodes() {
+        use codec::Encode;
+
+        let who = account_of(&pair_from_seed("//Alice").unwrap());
+        let deposit = (who.clone(), 10u128);
+        let ongoing = (
+            0u8,
+            (2u16, [22u8, 1u8], (2u8, H256::zero(), 40u32), (1u8, 100u32)),
+            (1000u32, deposit.clone(), Some(deposit.clone())),
+            (Some((1100u32, Some(1200u32))), (3u128, 1u128, 2u128)),
+            (false, Some((1200u32, (0u32, 0u32)))),
+        )
+            .encode();
+        let referendum = Referendum::from(RawReferendumInfo::decode(&mut &ongoing[..]).unwrap());
+        assert_eq!(
+            referendum,
+            Referendum::Ongoing {
+                track: 2,
+                submitted: 1000,
+                decision_deposit: Some(Deposit { who, amount: 10 }),
+                deciding: Some(DecidingStatus {
+                    since: 1100,
+                    confirming: Some(1200)
+                }),
+                tally: Tally {
+                    ayes: 3,
+                    nays: 1,
+                    support: 2
+                },
+            }
+        );
+
+        let approved = (1u8, 1300u32, Some(deposit), None::<(AccountId32, u128)>).encode();
+        assert_eq!(
+            Referendum::from(RawReferendumInfo::decode(&mut &approved[..]).unwrap()),
+            Referendum::Ended {
+                outcome: "approved",
+                block: 1300
+            }
+        );
+        let killed = (5u8, 1400u32).encode();
+        assert_eq!(
+            Referendum::from(RawReferendumInfo::decode(&mut &killed[..]).unwrap()),
+            Referendum::Ended {
+                outcome: "killed",
+                block: 1400
+            }
+        );
+    }
+
+    #[test]
+    fn staking_ledger_decodes() {
+        use codec::Encode;
+
+        let stash = account_of(&pair_from_seed("//Alice").unwrap());
+        let encoded = (
+            stash,
+            Compact(30u128),
+            Compact(20u128),
+            vec![(Compact(10u128), Compact(5u32))],
+            vec![3u32, 4u32],
+        )
+            .encode();
+        let ledger = RawStakingLedger::decode(&mut &encoded[..]).unwrap();
+        assert_eq!((ledger.total, ledger.active), (30, 20));
+        assert_eq!(ledger.legacy_claimed_rewards, vec![3, 4]);
+    }
+
+    #[test]
+    fn format_balance_works() {
+        assert_eq!(format_balance(1_500_000_000_000, 12, "UNIT"), "1.5 UNIT");
+        assert_eq!(format_balance(2_000_000_000_000, 12, "UNIT"), "2 UNIT");
+        assert_eq!(format_balance(1, 10, "DOT"), "0.0000000001 DOT");
+        assert_eq!(format_balance(42, 0, "X"), "42 X");
+        assert_eq!(format_balance(0, 40, "X"), "0 X");
+        assert_eq!(
+            format_balance(5, 40, "X"),
+            format!("0.{}5 X", "0".repeat(39))
+        );
+    }
+
+    #[test]
+    fn parse_balance_works() {
+        assert_eq!(parse_balance("1.5", 12).unwrap(), 1_500_000_000_000);
+        assert_eq!(parse_balance("2", 12).unwrap(), 2_000_000_000_000);
+        assert_eq!(parse_balance(".0000000001", 10).unwrap(), 1);
+        assert_eq!(parse_balance("42", 0).unwrap(), 42);
+        for amount in ["", ".", "0", "0.0", "-1", "1.5.", "1,5", "0.0000000000001"] {
+            assert!(parse_balance(amount, 12).is_err(), "{}", amount);
+        }
+        assert!(parse_balance("1", 40).is_err());
+    }
+
+    #[test]
+    fn parse_evm_address_works() {
+        for address in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        ] {
+            assert_eq!(parse_evm_address(address).unwrap(), address);
+            assert_eq!(
+                parse_evm_address(&address.to_ascii_lowercase()).unwrap(),
+                address
+            );
+        }
+        for address in [
+            "0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA",
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg",
+        ] {
+            assert!(parse_evm_address(address).is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn parse_address_works() {
+        let alice = pair_from_seed("//Alice").unwrap();
+        let address = to_address(&account_of(&alice));
+        assert_eq!(address, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        assert_eq!(parse_address(&address).unwrap(), account_of(&alice));
+        assert!(parse_address("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ").is_err());
+
+        let polkadot = ChainInfo {
+            name: "Polkadot".into(),
+            genesis_hash: String::new(),
+            token_symbol: "DOT".into(),
+            token_decimals: 10,
+            ss58_prefix: 0,
+        };
+        let address = polkadot.address_of(&account_of(&alice));
+        assert_eq!(address, "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5");
+        assert_eq!(parse_address(&address).unwrap(), account_of(&alice));
+
+        // Network addresses and generic substrate addresses are accepted for a
+        // network, addresses of other networks aren't.
+        assert_eq!(
+            parse_network_address(&address, 0).unwrap(),
+            account_of(&alice)
+        );
+        assert_eq!(
+            parse_network_address(&to_address(&account_of(&alice)), 0).unwrap(),
+            account_of(&alice)
+        );
+        assert!(matches!(
+            parse_network_address(&address, 2),
+            Err(ChainError::WrongNetwork(2))
+        ));
+    }
+}
@@ -8,9 +8,23 @@ pub trait Config: BaseConfig {
     /// Type holding the bot's wallet seed.
     type WalletSeed: Get<String>;
 
+    /// Type holding the key faucet wallet seeds are encrypted with, 32 random
+    /// bytes hex encoded.
+    type SeedEncryptionKey: Get<String>;
+
+    /// Type holding the key faucet wallet seeds were encrypted with before the
+    /// current key, used for key rotation. Empty if there is none.
+    type PreviousSeedEncryptionKey: Get<String>;
+
     /// Type holding the rpc address.
     type SubstrateRPC: Get<String>;
 
     /// Type holding the path to the database file.
     type DBPath: Get<String>;
+
+    /// Type holding the amount paid out by faucets per drip.
+    type DripAmount: Get<u128>;
+
+    /// Type holding the number of seconds a user has to wait between drips.
+    type DripCooldown: Get<u64>;
 }
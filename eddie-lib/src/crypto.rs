@@ -0,0 +1,377 @@
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use sp_core::{crypto::AccountId32, ecdsa, ed25519, hashing::blake2_256, sr25519, Pair};
+use support::traits::dispatch::DispatchError;
+use zeroize::Zeroizing;
+
+pub const MODULE: &str = "CRYPTO";
+
+#[derive(Clone, Debug)]
+/// All potential errors when encrypting or decrypting secrets.
+pub enum CryptoError {
+    /// No encryption key has been configured.
+    MissingKey,
+    /// The encryption key isn't 32 hex encoded bytes.
+    InvalidKey,
+    /// The secret was encrypted with a key that isn't configured.
+    UnknownKey,
+    /// The secret couldn't be encrypted.
+    Encryption,
+    /// The secret couldn't be decrypted or was tampered with.
+    Decryption,
+    /// The signature isn't hex encoded or has an unknown length.
+    InvalidSignature,
+}
+
+impl std::fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let err = match self {
+            Self::MissingKey => "No seed encryption key configured",
+            Self::InvalidKey => "Seed encryption key has to be 32 hex encoded bytes",
+            Self::UnknownKey => "Seed was encrypted with an unknown key",
+            Self::Encryption => "Couldn't encrypt seed",
+            Self::Decryption => "Couldn't decrypt seed",
+            Self::InvalidSignature => "Invalid signature, expected a hex encoded signature",
+        };
+
+        write!(f, "{}", err)
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+impl From<CryptoError> for DispatchError {
+    fn from(value: CryptoError) -> Self {
+        DispatchError::Module(String::from(MODULE), format!("{}", value))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+/// A wallet seed as it's kept in the storage.
+pub enum StoredSeed {
+    /// Seed encrypted with XChaCha20-Poly1305, all values hex encoded.
+    Encrypted {
+        key_id: String,
+        nonce: String,
+        ciphertext: String,
+    },
+    /// Plaintext seed as stored by older versions of the bot.
+    Plain(String),
+}
+
+impl Default for StoredSeed {
+    fn default() -> Self {
+        Self::Plain(String::new())
+    }
+}
+
+impl StoredSeed {
+    /// Whether no seed has been stored.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Self::Plain(seed) if seed.is_empty())
+    }
+
+    /// Get the id of the key the seed was encrypted with, if any.
+    pub fn key_id(&self) -> Option<&str> {
+        match self {
+            Self::Encrypted { key_id, .. } => Some(key_id),
+            Self::Plain(_) => None,
+        }
+    }
+}
+
+/// Get the cipher key of a master key, which has to be 32 random bytes, hex
+/// encoded.
+///
+/// The key is used as is, so that it can't be brute-forced like a
+/// passphrase could.
+pub fn cipher_key(master_key: &str) -> Result<Zeroizing<[u8; 32]>, CryptoError> {
+    if master_key.is_empty() {
+        return Err(CryptoError::MissingKey);
+    }
+
+    let bytes = Zeroizing::new(
+        hex::decode(master_key.trim().trim_start_matches("0x"))
+            .map_err(|_| CryptoError::InvalidKey)?,
+    );
+    let mut key = Zeroizing::new([0; 32]);
+    if bytes.len() != key.len() {
+        return Err(CryptoError::InvalidKey);
+    }
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+/// Get the cipher key older versions of the bot derived from a passphrase.
+///
+/// It's only used to decrypt seeds that were encrypted before master keys
+/// had to be random bytes.
+fn legacy_cipher_key(master_key: &str) -> Zeroizing<[u8; 32]> {
+    Zeroizing::new(blake2_256(master_key.as_bytes()))
+}
+
+/// Get the public id of a cipher key, used to find the key to decrypt with.
+fn cipher_key_id(cipher_key: &[u8; 32]) -> String {
+    hex::encode(&blake2_256(cipher_key)[..4])
+}
+
+/// Get the public id of a master key, used to find the key to decrypt with.
+pub fn key_id(master_key: &str) -> Result<String, CryptoError> {
+    Ok(cipher_key_id(&*cipher_key(master_key)?))
+}
+
+/// Encrypt a seed with a master key.
+pub fn encrypt_seed(master_key: &str, seed: &str) -> Result<StoredSeed, CryptoError> {
+    encrypt_with(&*cipher_key(master_key)?, seed)
+}
+
+/// Encrypt a seed with a cipher key.
+fn encrypt_with(cipher_key: &[u8; 32], seed: &str) -> Result<StoredSeed, CryptoError> {
+    let cipher = XChaCha20Poly1305::new(cipher_key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, seed.as_bytes())
+        .map_err(|_| CryptoError::Encryption)?;
+
+    Ok(StoredSeed::Encrypted {
+        key_id: cipher_key_id(cipher_key),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })
+}
+
+/// Decrypt a seed with whichever of the given master keys it was encrypted
+/// with.
+///
+/// Plaintext seeds are returned as is, and seeds encrypted by older versions
+/// of the bot are decrypted with the passphrase they were encrypted with.
+pub fn decrypt_seed(
+    master_keys: &[&str],
+    stored: &StoredSeed,
+) -> Result<Zeroizing<String>, CryptoError> {
+    let (key_id, nonce, ciphertext) = match stored {
+        StoredSeed::Plain(seed) => return Ok(Zeroizing::new(seed.clone())),
+        StoredSeed::Encrypted {
+            key_id,
+            nonce,
+            ciphertext,
+        } => (key_id, nonce, ciphertext),
+    };
+
+    let cipher_key = master_keys
+        .iter()
+        .filter(|master_key| !master_key.is_empty())
+        .flat_map(|master_key| {
+            [
+                cipher_key(master_key).ok(),
+                Some(legacy_cipher_key(master_key)),
+            ]
+        })
+        .flatten()
+        .find(|cipher_key| cipher_key_id(cipher_key) == *key_id)
+        .ok_or(CryptoError::UnknownKey)?;
+
+    let nonce = hex::decode(nonce).map_err(|_| CryptoError::Decryption)?;
+    if nonce.len() != 24 {
+        return Err(CryptoError::Decryption);
+    }
+    let ciphertext = hex::decode(ciphertext).map_err(|_| CryptoError::Decryption)?;
+
+    let cipher = XChaCha20Poly1305::new(cipher_key.as_ref().into());
+    let seed = Zeroizing::new(
+        cipher
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| CryptoError::Decryption)?,
+    );
+
+    String::from_utf8(seed.to_vec())
+        .map(Zeroizing::new)
+        .map_err(|_| CryptoError::Decryption)
+}
+
+/// Generate a random hex encoded value of `len` bytes.
+pub fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0; len];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Check whether a hex encoded signature over `message` was made by the key
+/// of an account.
+///
+/// Sr25519, ed25519 and ecdsa signatures are supported, with or without the
+/// type prefix of a `MultiSignature`. Wallets like polkadot-js wrap messages
+/// in `<Bytes>` tags before signing them, so both forms are accepted.
+pub fn verify_signature(
+    account: &AccountId32,
+    message: &str,
+    signature: &str,
+) -> Result<bool, CryptoError> {
+    let signature = hex::decode(signature.trim().trim_start_matches("0x"))
+        .map_err(|_| CryptoError::InvalidSignature)?;
+    let wrapped = format!("<Bytes>{}</Bytes>", message);
+    let messages = [message.as_bytes(), wrapped.as_bytes()];
+
+    let public: &[u8; 32] = account.as_ref();
+    let schnorr = |signature: &[u8]| {
+        let sr25519 = sr25519::Signature::try_from(signature).ok();
+        let ed25519 = ed25519::Signature::try_from(signature).ok();
+        messages.iter().any(|message| {
+            sr25519.as_ref().is_some_and(|signature| {
+                sr25519::Pair::verify(signature, message, &sr25519::Public::from_raw(*public))
+            }) || ed25519.as_ref().is_some_and(|signature| {
+                ed25519::Pair::verify(signature, message, &ed25519::Public::from_raw(*public))
+            })
+        })
+    };
+    // Ecdsa accounts are the hash of the public key, which is recovered from
+    // the signature.
+    let ecdsa = |signature: &[u8]| {
+        let Ok(signature) = ecdsa::Signature::try_from(signature) else {
+            return false;
+        };
+        messages.iter().any(|message| {
+            signature
+                .recover(message)
+                .is_some_and(|signer| &blake2_256(signer.as_ref()) == public)
+        })
+    };
+
+    match (signature.len(), signature.first()) {
+        (64, _) => Ok(schnorr(&signature)),
+        (65, Some(0 | 1)) => Ok(ecdsa(&signature) || schnorr(&signature[1..])),
+        (65, _) => Ok(ecdsa(&signature)),
+        (66, Some(2)) => Ok(ecdsa(&signature[1..])),
+        _ => Err(CryptoError::InvalidSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kv::Json;
+
+    const MASTER_KEY: &str = "0x0102030405060708091011121314151617181920212223242526272829303132";
+    const OTHER_KEY: &str = "3231302928272625242322212019181716151413121110090807060504030201";
+
+    #[test]
+    fn seed_encryption_works() {
+        let stored = encrypt_seed(MASTER_KEY, "//Alice").unwrap();
+        assert_eq!(stored.key_id(), Some(key_id(MASTER_KEY).unwrap().as_str()));
+        assert!(!stored.is_empty());
+
+        assert_eq!(
+            decrypt_seed(&[OTHER_KEY, MASTER_KEY], &stored)
+                .unwrap()
+                .as_str(),
+            "//Alice"
+        );
+        assert!(matches!(
+            decrypt_seed(&[OTHER_KEY], &stored),
+            Err(CryptoError::UnknownKey)
+        ));
+        assert!(matches!(
+            encrypt_seed("", "//Alice"),
+            Err(CryptoError::MissingKey)
+        ));
+
+        // Master keys have to be 32 random bytes rather than a passphrase.
+        for key in ["master key", "0x0102", &format!("{}00", OTHER_KEY)] {
+            assert!(matches!(
+                encrypt_seed(key, "//Alice"),
+                Err(CryptoError::InvalidKey)
+            ));
+        }
+
+        // Tampering with the ciphertext is detected.
+        let StoredSeed::Encrypted {
+            key_id,
+            nonce,
+            mut ciphertext,
+        } = stored
+        else {
+            unreachable!()
+        };
+        ciphertext.replace_range(
+            0..2,
+            if &ciphertext[0..2] == "00" {
+                "01"
+            } else {
+                "00"
+            },
+        );
+        let tampered = StoredSeed::Encrypted {
+            key_id,
+            nonce,
+            ciphertext,
+        };
+        assert!(matches!(
+            decrypt_seed(&[MASTER_KEY], &tampered),
+            Err(CryptoError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn plaintext_seeds_can_be_read() {
+        let Json(stored) =
+            <Json<StoredSeed> as kv::Value>::from_raw_value(kv::Raw::from(b"\"//Alice\"")).unwrap();
+        assert_eq!(stored, StoredSeed::Plain("//Alice".into()));
+        assert_eq!(
+            decrypt_seed(&[MASTER_KEY], &stored).unwrap().as_str(),
+            "//Alice"
+        );
+        assert!(StoredSeed::default().is_empty());
+    }
+
+    #[test]
+    fn passphrase_encrypted_seeds_can_be_read() {
+        let stored = encrypt_with(&legacy_cipher_key("master key"), "//Alice").unwrap();
+        assert_eq!(
+            decrypt_seed(&[MASTER_KEY, "master key"], &stored)
+                .unwrap()
+                .as_str(),
+            "//Alice"
+        );
+        assert!(matches!(
+            decrypt_seed(&[MASTER_KEY], &stored),
+            Err(CryptoError::UnknownKey)
+        ));
+    }
+
+    #[test]
+    fn signature_verification_works() {
+        let message = "Eddie wallet verification 0123";
+        let wrapped = format!("<Bytes>{}</Bytes>", message);
+
+        let sr25519 = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let account = AccountId32::from(sr25519.public());
+        let signature = hex::encode(sr25519.sign(wrapped.as_bytes()));
+        assert!(verify_signature(&account, message, &signature).unwrap());
+        assert!(!verify_signature(&account, "other message", &signature).unwrap());
+
+        // Signatures with a type prefix.
+        let signature = format!("0x01{}", hex::encode(sr25519.sign(message.as_bytes())));
+        assert!(verify_signature(&account, message, &signature).unwrap());
+
+        let ed25519 = ed25519::Pair::from_string("//Alice", None).unwrap();
+        let signature = hex::encode(ed25519.sign(message.as_bytes()));
+        assert!(
+            verify_signature(&AccountId32::from(ed25519.public()), message, &signature).unwrap()
+        );
+        assert!(!verify_signature(&account, message, &signature).unwrap());
+
+        let ecdsa = ecdsa::Pair::from_string("//Alice", None).unwrap();
+        let account = AccountId32::from(blake2_256(ecdsa.public().as_ref()));
+        let signature = hex::encode(ecdsa.sign(wrapped.as_bytes()));
+        assert!(verify_signature(&account, message, &signature).unwrap());
+
+        assert!(matches!(
+            verify_signature(&account, message, "0x1234"),
+            Err(CryptoError::InvalidSignature)
+        ));
+    }
+}
@@ -1,12 +1,55 @@
-use crate::{bot::Bot, call::Response, config::Config, origin::Origin, storage::Storage};
-use support::traits::{dispatch::DispatchError, Get};
+use crate::{
+    bot::Bot,
+    call::{Response, UpdateHandle},
+    chain,
+    config::Config,
+    crypto::{self, StoredSeed},
+    jobs::job_status_message,
+    notifications::notify,
+    origin::Origin,
+    storage::{
+        storage::{
+            user_account, ChallengeData, DripRecordData, FaucetData, FaucetSetting, JobData,
+            LedgerEntryData, LinkCodeData, NetworkData, StorageError, WalletData,
+        },
+        Storage,
+    },
+};
+use sp_core::sr25519;
+use std::collections::BTreeMap;
+use support::traits::{dispatch::DispatchError, Get, Origin as _};
+use zeroize::Zeroizing;
 
 /// Type to make function definitions a bit cleaner.
 type DispatchResult<T> = support::traits::dispatch::DispatchResult<Option<T>>;
 
+/// Number of seconds a wallet verification can be completed in.
+const CHALLENGE_TTL: u64 = 10 * 60;
+
+/// Number of seconds a link code can be redeemed in.
+const LINK_CODE_TTL: u64 = 10 * 60;
+
 /// The version as defined in Cargo.toml.
 const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 
+/// Get the current unix timestamp in seconds.
+pub(crate) fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Format a number of seconds as a human readable duration.
+pub(crate) fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", seconds),
+        (0, _) => format!("{}m {}s", minutes, seconds),
+        _ => format!("{}h {}m", hours, minutes),
+    }
+}
+
 impl<T: Config> Bot<T> {
     #[deprecated(since = "0.1.0", note = "use info() instead")]
     pub fn version() -> DispatchResult<Response> {
@@ -63,7 +106,7 @@ impl<T: Config> Bot<T> {
             .map_err(|err| DispatchError::Other(err.to_string()))?;
 
         Ok(Some(Response::ReplyDirect(format!(
-            "The channel has been registered as a faucet.\n\nNow please activate the faucet by replying here with the following command:\n/activate_faucet {} <rpc url> <wallet seed>",
+            "The channel has been registered as a faucet.\n\nNow please activate the faucet by replying here with the following command:\n/activate_faucet {} <network>",
             channel.inner(),
         ))))
     }
@@ -71,8 +114,7 @@ impl<T: Config> Bot<T> {
     pub fn activate_faucet_channel(
         who: Origin,
         channel: &Origin,
-        rpc_url: &String,
-        wallet_seed: &String,
+        network: &str,
     ) -> DispatchResult<Response> {
         // Get the storage.
         let storage = Storage::new(&T::DBPath::get().to_owned())
@@ -86,15 +128,1643 @@ impl<T: Config> Bot<T> {
             ));
         }
 
+        let faucet_channel = storage
+            .get_faucet_channel(channel)
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "This channel isn't a faucet".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        let network = Self::get_network(&storage, network)?;
+        let wallet_seed = faucet_channel
+            .networks
+            .get(&network.name)
+            .map(|faucet| faucet.wallet_seed.clone())
+            .unwrap_or_default();
+
+        // Make sure the node can be reached and is still on the registered
+        // chain.
+        let api = chain::connect_any(&network.rpc_urls)?;
+        let chain_info = chain::chain_info(&api)?;
+        if chain_info.genesis_hash != network.chain.genesis_hash {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!(
+                    "The nodes of {} are no longer on {}. Please register the network again",
+                    network.name, network.chain.name,
+                ),
+            ));
+        }
+        let account = chain::account_of(&Self::faucet_pair(channel, &network.name, &wallet_seed)?);
+        let balance = chain::free_balance(&api, &account)?;
+
         // Register the channel as a faucet in the storage.
         storage
-            .activate_faucet_channel(channel, rpc_url, wallet_seed)
+            .activate_faucet_channel(channel, &network.name, chain_info.clone())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let address = chain_info.address_of(&account);
+        Ok(Some(Response::Multiple(vec![
+            Response::SayChan(
+                channel.clone(),
+                format!(
+                    "Faucet has been activated for this channel on {}!\n\nPlease fund the faucet by sending {} to {}",
+                    network.name, chain_info.token_symbol, address,
+                ),
+            ),
+            Response::Reply(format!(
+                "The faucet has been activated.\n\nChain: {}\nGenesis hash: {}\nToken: {} ({} decimals)\nAddress: {}\nFree balance: {}",
+                chain_info.name,
+                chain_info.genesis_hash,
+                chain_info.token_symbol,
+                chain_info.token_decimals,
+                address,
+                chain_info.format_balance(balance),
+            )),
+        ])))
+    }
+
+    pub fn import_faucet_seed(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        wallet_seed: &str,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        // Reject seeds that can't be used for signing.
+        let account = chain::account_of(&chain::pair_from_seed(wallet_seed)?);
+
+        let faucet = Self::get_faucet(&storage, channel, network)?;
+        let network = Self::get_network(&storage, network)?;
+
+        // Only keep the seed encrypted in the storage.
+        let wallet_seed = crypto::encrypt_seed(&T::SeedEncryptionKey::get(), wallet_seed)?;
+        storage
+            .set_faucet_seed(channel, &network.name, wallet_seed)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let api = chain::connect_any(&network.rpc_urls)?;
+        Ok(Some(Response::Reply(format!(
+            "The seed has been imported. The faucet now pays out from {}\n\nFree balance: {}",
+            faucet.chain.address_of(&account),
+            faucet
+                .chain
+                .format_balance(chain::free_balance(&api, &account)?),
+        ))))
+    }
+
+    pub fn register_wallet(who: Origin, address: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Wallets are stored with the generic prefix, so that the same
+        // account is recognized whatever network its address was encoded for.
+        let pub_addr = chain::to_address(&chain::parse_address(address)?);
+        storage
+            .set_user_wallet(who, pub_addr.clone())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        Ok(Some(Response::Reply(format!(
+            "Your wallet {} has been registered.",
+            pub_addr
+        ))))
+    }
+
+    pub fn my_wallet(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Make sure there is a wallet before reading its status.
+        Self::registered_wallet(&storage, &who)?;
+        let wallet = storage
+            .get_user_wallet_data(who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        Ok(Some(Response::Reply(format!(
+            "Your registered wallet is {} ({}).",
+            wallet.address,
+            match wallet.verified {
+                true => "verified",
+                false => "not verified",
+            }
+        ))))
+    }
+
+    pub fn add_wallet(
+        who: Origin,
+        label: &str,
+        address: &str,
+        network: Option<&str>,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let label = label.trim();
+        if label.is_empty()
+            || label.contains(char::is_whitespace)
+            || chain::parse_address(label).is_ok()
+            || chain::parse_evm_address(label).is_ok()
+        {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "A wallet label must be a single word that isn't an address".into(),
+            ));
+        }
+
+        // EVM accounts have H160 addresses. SS58 addresses of a network must
+        // use its prefix or the generic one.
+        let network = network
+            .map(|network| Self::get_network(&storage, network))
+            .transpose()?;
+        let address = match (address.trim().starts_with("0x"), &network) {
+            (true, _) => chain::parse_evm_address(address)?,
+            (false, Some(network)) => chain::to_address(&chain::parse_network_address(
+                address,
+                network.chain.ss58_prefix,
+            )?),
+            (false, None) => chain::to_address(&chain::parse_address(address)?),
+        };
+        let wallet = WalletData::new(label, address, network.map(|network| network.name));
+        storage
+            .add_user_wallet(&who, wallet.clone())
+            .map_err(|err| match err {
+                StorageError::NotUnique => DispatchError::Module(
+                    crate::MODULE.into(),
+                    format!("You already have a wallet labelled {}", wallet.label),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        Ok(Some(Response::Reply(format!(
+            "Your wallet {} has been added as {}.",
+            wallet.address, wallet.label
+        ))))
+    }
+
+    pub fn remove_wallet(who: Origin, label: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let wallet = Self::labelled_wallet(&storage, &who, label)?;
+        storage
+            .remove_user_wallet(&who, &wallet.label)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        Ok(Some(Response::Reply(format!(
+            "Your wallet {} has been removed.",
+            wallet.label
+        ))))
+    }
+
+    pub fn list_wallets(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let wallets = storage
+            .get_user_wallets(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if wallets.wallets.is_empty() {
+            return Ok(Some(Response::Reply(
+                "You haven't registered a wallet yet.".into(),
+            )));
+        }
+
+        let lines: Vec<String> = wallets
+            .wallets
+            .iter()
+            .map(|wallet| {
+                let mut line = format!("{}: {}", wallet.label, wallet.address);
+                if let Some(network) = &wallet.network {
+                    line.push_str(&format!(" on {}", network));
+                }
+                line.push_str(match wallet.verified {
+                    true => " (verified)",
+                    false => " (not verified)",
+                });
+                if wallet.default {
+                    line.push_str(" [default]");
+                }
+                line
+            })
+            .collect();
+        Ok(Some(Response::Reply(format!(
+            "Your wallets:\n{}",
+            lines.join("\n")
+        ))))
+    }
+
+    pub fn set_default_wallet(who: Origin, label: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let wallet = Self::labelled_wallet(&storage, &who, label)?;
+        storage
+            .set_default_user_wallet(&who, &wallet.label)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        Ok(Some(Response::Reply(format!(
+            "Your wallet {} is now your default wallet.",
+            wallet.label
+        ))))
+    }
+
+    pub fn start_wallet_verification(who: Origin, label: Option<&str>) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let address = match label {
+            Some(label) => Self::labelled_wallet(&storage, &who, label)?.address,
+            None => Self::registered_wallet(&storage, &who)?,
+        };
+        let challenge = ChallengeData {
+            message: format!(
+                "{} wallet verification of {}: {}",
+                T::Name::get(),
+                address,
+                crypto::random_hex(16)
+            ),
+            address,
+            expires: now() + CHALLENGE_TTL,
+        };
+        storage
+            .set_challenge(&who, challenge.clone())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        Ok(Some(Response::Reply(format!(
+            "Please sign the following message with your wallet, for instance with the sign and verify tool of polkadot-js, and send the signature with /complete_wallet_verification within {}:\n\n{}",
+            format_duration(CHALLENGE_TTL),
+            challenge.message,
+        ))))
+    }
+
+    pub fn complete_wallet_verification(who: Origin, signature: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let challenge = storage
+            .get_challenge(&who, now())
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "There is no pending verification. Please start a new one".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        let account = chain::parse_address(&challenge.address)?;
+        if !crypto::verify_signature(&account, &challenge.message, signature)? {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "The signature doesn't match the message and your wallet".into(),
+            ));
+        }
+
+        storage
+            .verify_user_wallet(who.clone(), &challenge.address)
+            .map_err(|err| {
+                match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "Your wallet changed since the verification was started. Please start a new one"
+                        .into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            }
+            })?;
+        storage
+            .remove_challenge(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        Ok(Some(Response::Reply(format!(
+            "Your wallet {} has been verified.",
+            challenge.address
+        ))))
+    }
+
+    pub fn link_account(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let code = crypto::random_hex(4).to_uppercase();
+        storage
+            .set_link_code(
+                &code,
+                LinkCodeData {
+                    origin: who.to_string(),
+                    expires: now() + LINK_CODE_TTL,
+                },
+            )
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // The code is only sent privately, as anyone redeeming it gets
+        // linked to the account.
+        Ok(Some(Response::ReplyDirect(format!(
+            "Send /confirm_link {} to {} on the other platform within {} to link your accounts.",
+            code,
+            T::Name::get(),
+            format_duration(LINK_CODE_TTL),
+        ))))
+    }
+
+    pub fn confirm_link(who: Origin, code: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let origin = storage
+            .take_link_code(&code.trim().to_uppercase(), now())
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "Unknown or expired link code".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+        if origin.network() == who.network() {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Please confirm the link with your account on the other platform".into(),
+            ));
+        }
+
+        storage
+            .link_origins(&origin, &who, &crypto::random_hex(8))
+            .map_err(|err| match err {
+                StorageError::NotUnique => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "Your accounts are already linked".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        log::info!("Linked {} to {}", who, origin);
+        Ok(Some(Response::Reply(format!(
+            "Your account has been linked to {}.",
+            origin
+        ))))
+    }
+
+    pub fn balance(
+        who: Origin,
+        network: Option<&str>,
+        target: Option<&str>,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = network
+            .map(|name| Self::get_network(&storage, name))
+            .transpose()?;
+
+        // Accounts to look up by network, with the name they're shown as.
+        let mut lookups: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        let user = match target {
+            Some(target) => Origin::try_from(target.trim()).map_err(|_| target),
+            None => Ok(who.clone()),
+        };
+        match user {
+            // The wallets of the caller or of a mentioned user.
+            Ok(user) => {
+                let wallets = storage
+                    .get_user_wallets(&user)
+                    .map_err(|err| DispatchError::Other(err.to_string()))?;
+                if wallets.wallets.is_empty() {
+                    return Err(DispatchError::Module(
+                        crate::MODULE.into(),
+                        match user == who {
+                            true => "You haven't registered a wallet yet".into(),
+                            false => format!("{} hasn't registered a wallet yet", user),
+                        },
+                    ));
+                }
+
+                match &network {
+                    Some(network) => {
+                        let wallet = wallets.for_network(&network.name).ok_or_else(|| {
+                            DispatchError::Module(
+                                crate::MODULE.into(),
+                                match user == who {
+                                    true => format!("You have no wallet for {}", network.name),
+                                    false => format!("{} has no wallet for {}", user, network.name),
+                                },
+                            )
+                        })?;
+                        lookups
+                            .entry(network.name.clone())
+                            .or_default()
+                            .push((wallet.label.clone(), wallet.address.clone()));
+                    }
+                    None => {
+                        for wallet in wallets.wallets {
+                            if let Some(name) = wallet.network {
+                                lookups
+                                    .entry(name)
+                                    .or_default()
+                                    .push((wallet.label, wallet.address));
+                            }
+                        }
+                    }
+                }
+            }
+            // A wallet label of the caller or an address.
+            Err(target) => {
+                let wallet = storage
+                    .get_user_wallets(&who)
+                    .map_err(|err| DispatchError::Other(err.to_string()))?
+                    .get(target)
+                    .cloned();
+                let name = network
+                    .as_ref()
+                    .map(|network| network.name.clone())
+                    .or(wallet.as_ref().and_then(|wallet| wallet.network.clone()));
+                if let Some(name) = name {
+                    let lookup = match wallet {
+                        Some(wallet) => (wallet.label, wallet.address),
+                        None => (target.trim().to_string(), target.trim().to_string()),
+                    };
+                    lookups.entry(name).or_default().push(lookup);
+                }
+            }
+        }
+        if lookups.is_empty() {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "The wallet isn't registered on a network. Please name the network to look the balance up on".into(),
+            ));
+        }
+
+        // Failures are reported per network, so that an unreachable node
+        // doesn't hide the other balances.
+        let mut lines = Vec::new();
+        for (name, accounts) in lookups {
+            let network = match Self::get_network(&storage, &name) {
+                Ok(network) => network,
+                Err(err) => {
+                    lines.push(format!("{}: {}", name, err));
+                    continue;
+                }
+            };
+            let api = match chain::connect_any(&network.rpc_urls) {
+                Ok(api) => api,
+                Err(err) => {
+                    lines.push(format!("{}: {}", network.name, err));
+                    continue;
+                }
+            };
+            for (shown_as, address) in accounts {
+                let balance = chain::parse_network_address(&address, network.chain.ss58_prefix)
+                    .map_err(DispatchError::from)
+                    .and_then(|account| {
+                        chain::account_balance(&api, &account).map_err(DispatchError::from)
+                    });
+                lines.push(match balance {
+                    Ok(balance) => format!(
+                        "{} on {}: free {}, reserved {}, frozen {}",
+                        shown_as,
+                        network.name,
+                        network.chain.format_balance(balance.free),
+                        network.chain.format_balance(balance.reserved),
+                        network.chain.format_balance(balance.frozen),
+                    ),
+                    Err(err) => format!("{} on {}: {}", shown_as, network.name, err),
+                });
+            }
+        }
+
+        Ok(Some(Response::Reply(lines.join("\n"))))
+    }
+
+    pub fn drip(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        address: Option<&str>,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Get the faucet of the channel on the network.
+        let faucet = Self::get_faucet(&storage, channel, network)?;
+        let network = Self::get_network(&storage, network)?;
+        let chain_info = &faucet.chain;
+
+        // Send to one of the caller's registered wallets when no address was
+        // given.
+        let address = Self::resolve_address(&storage, &who, address, Some(&network.name))?;
+        let dest = chain::parse_network_address(&address, chain_info.ss58_prefix)?;
+        let dest_address = chain::to_address(&dest);
+
+        // Collect the origins of the caller and of everybody who registered the
+        // caller's or the destination's address, so that the cooldown can't be
+        // bypassed by switching between Discord and Telegram.
+        let mut origins = vec![who.clone()];
+        let mut pub_addrs = vec![dest_address.clone()];
+        if let Ok(wallets) = storage.get_user_wallets(&who) {
+            pub_addrs.extend(wallets.wallets.into_iter().map(|wallet| wallet.address));
+        }
+        for pub_addr in pub_addrs {
+            for origin in storage
+                .get_pub_address_origins(pub_addr)
+                .map_err(|err| DispatchError::Other(err.to_string()))?
+            {
+                if !origins.contains(&origin) {
+                    origins.push(origin);
+                }
+            }
+        }
+
+        let policy = &faucet.policy;
+        let amount = policy.amount.unwrap_or(T::DripAmount::get());
+        let now = now();
+
+        // Reject if the cooldown hasn't passed yet, the user reached the
+        // maximum number of drips for today or the drip would exceed the
+        // faucet's daily budget. The drip is reserved in the same step, so
+        // that concurrent requests can't overspend the limits while the
+        // payout is prepared.
+        let drips = storage
+            .reserve_drip(
+                channel,
+                &network.name,
+                &origins,
+                &dest_address,
+                now,
+                |drips, (_, paid)| {
+                    if let Some(drips) = drips {
+                        let next_drip = drips
+                            .last_drip
+                            .saturating_add(policy.cooldown.unwrap_or(T::DripCooldown::get()));
+                        if now < next_drip {
+                            return Err(DispatchError::Module(
+                                crate::MODULE.into(),
+                                format!(
+                                    "You've already received tokens from this faucet. Please try again in {}",
+                                    format_duration(next_drip - now),
+                                ),
+                            ));
+                        }
+
+                        if let Some(max_drips) = policy.max_drips_per_day {
+                            if drips.drips >= max_drips {
+                                return Err(DispatchError::Module(
+                                    crate::MODULE.into(),
+                                    format!(
+                                        "You've reached the maximum of {} drips per day. Please try again tomorrow",
+                                        max_drips,
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+
+                    if let Some(daily_budget) = policy.daily_budget {
+                        if paid.saturating_add(amount) > daily_budget {
+                            return Err(DispatchError::Module(
+                                crate::MODULE.into(),
+                                "The faucet's budget for today has been used up. Please try again tomorrow"
+                                    .into(),
+                            ));
+                        }
+                    }
+                    Ok(amount)
+                },
+            )
+            .map_err(|err| DispatchError::Other(err.to_string()))??;
+
+        log::info!("{} requested a drip to {} in {}", who, address, channel);
+
+        // The idempotency key includes the previous drip, so that a drip that
+        // was undone is requested again under the same key.
+        let last_drip = drips.map(|drips| drips.last_drip).unwrap_or_default();
+        let mut job = JobData::new(
+            format!(
+                "drip/{}/{}/{}/{}",
+                channel, network.name, dest_address, last_drip
+            ),
+            channel.to_string(),
+            network.name.clone(),
+            chain_info.address_of(&dest),
+            amount,
+            now,
+        );
+        let record = DripRecordData {
+            origins: origins.iter().map(Origin::to_string).collect(),
+            address: dest_address.clone(),
+            previous: last_drip,
+        };
+        job.drip = Some(record.clone());
+
+        // The reservation is given back when the payout can't be queued.
+        let handle = match Self::queue_drip(&storage, channel, &network, &faucet, &job) {
+            Ok(handle) => handle,
+            Err(err) => {
+                if let Err(undo_err) =
+                    storage.undo_drip(channel, &network.name, &record, amount, now)
+                {
+                    log::error!("Couldn't undo the drip of {}: {}", job.id, undo_err);
+                }
+                return Err(err);
+            }
+        };
+
+        Self::run_jobs()?;
+
+        let pending = job_status_message(&job, &network);
+        Ok(Some(Response::ReplyTracked(pending, handle)))
+    }
+
+    /// Queue the payout of a drip, after checking that the faucet can pay it.
+    fn queue_drip(
+        storage: &Storage,
+        channel: &Origin,
+        network: &NetworkData,
+        faucet: &FaucetData,
+        job: &JobData,
+    ) -> Result<UpdateHandle, DispatchError> {
+        let signer = Self::faucet_pair(channel, &network.name, &faucet.wallet_seed)?;
+        let api = chain::connect_any(&network.rpc_urls)?;
+
+        // Reject if the node is no longer on the chain the faucet was activated
+        // for.
+        if chain::genesis_hash(&api) != faucet.chain.genesis_hash {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!(
+                    "The faucet's node is no longer on {}. Please ask an admin to reactivate the faucet",
+                    faucet.chain.name,
+                ),
+            ));
+        }
+
+        // Reject if the faucet wallet can't pay the drip while keeping its
+        // minimum balance.
+        let balance = chain::free_balance(&api, &chain::account_of(&signer))?;
+        if balance
+            < job
+                .amount
+                .saturating_add(faucet.policy.min_balance.unwrap_or_default())
+        {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "The faucet is running low on funds. Please try again later".into(),
+            ));
+        }
+
+        // A failed drip is undone, after which it can be requested again
+        // under the same key.
+        match Self::enqueue_job(storage, job) {
+            Err(StorageError::NotUnique) => Self::requeue_job(storage, job),
+            result => result,
+        }
+        .map_err(|err| match err {
+            StorageError::NotUnique => DispatchError::Module(
+                crate::MODULE.into(),
+                "Your previous request is already being processed".into(),
+            ),
+            err => DispatchError::Other(err.to_string()),
+        })
+    }
+
+    pub fn tip(who: Origin, to: &Origin, amount: &str, network: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::get_network(&storage, network)?;
+        let amount = network.chain.parse_balance(amount)?;
+        let (identity, recipient) = storage
+            .get_identity(&who)
+            .and_then(|identity| Ok((identity, storage.get_identity(to)?)))
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if identity == recipient {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "You can't tip yourself".into(),
+            ));
+        }
+        // The recipient is notified on all of their linked accounts.
+        let recipients = storage
+            .get_identity_origins(&recipient)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Tips move between balances of the ledger, so they arrive instantly.
+        let now = now();
+        storage
+            .post_ledger_entry(&LedgerEntryData {
+                id: format!("tip/{}/{}/{}", who, now, crypto::random_hex(4)),
+                network: network.name.clone(),
+                debit: user_account(&identity),
+                credit: user_account(&recipient),
+                amount,
+                created: now,
+            })
+            .map_err(|err| match err {
+                StorageError::InsufficientFunds => DispatchError::Module(
+                    crate::MODULE.into(),
+                    format!(
+                        "Your balance on {} is too low for this tip. Send /deposit to top it up",
+                        network.name
+                    ),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        let tip = network.chain.format_balance(amount);
+        for recipient in recipients {
+            notify(Response::SayDirect(
+                recipient,
+                format!(
+                    "{} tipped you {} on {}. Send /ledger_balance to see your balance or /withdraw to send it to your wallet.",
+                    who, tip, network.name
+                ),
+            ));
+        }
+        Ok(Some(Response::Reply(format!(
+            "You tipped {} {} on {}.",
+            to, tip, network.name
+        ))))
+    }
+
+    /// Claim the tips that were held for a user before tips moved to the
+    /// ledger. They're added to the user's balance if the tipper's balance
+    /// covers them.
+    pub fn claim_tips(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let tips = storage
+            .get_pending_tips(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if tips.is_empty() {
+            return Ok(Some(Response::Reply("You have no tips to claim.".into())));
+        }
+        let recipient = storage
+            .get_identity(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // A tip stays pending until it's credited, so a claim that fails can
+        // be tried again.
+        let mut lines = Vec::new();
+        for tip in tips {
+            let claimed = Self::get_network(&storage, &tip.network).and_then(|network| {
+                let tipper = Origin::try_from(tip.from.clone())
+                    .map_err(|err| DispatchError::Other(err.into()))?;
+                let tipper_identity = storage
+                    .get_identity(&tipper)
+                    .map_err(|err| DispatchError::Other(err.to_string()))?;
+                let amount = network.chain.format_balance(tip.amount);
+
+                // The entry has the id of the tip, so it's only credited once.
+                match storage.post_ledger_entry(&LedgerEntryData {
+                    id: tip.id.clone(),
+                    network: tip.network.clone(),
+                    debit: user_account(&tipper_identity),
+                    credit: user_account(&recipient),
+                    amount: tip.amount,
+                    created: now(),
+                }) {
+                    Ok(()) | Err(StorageError::NotUnique) => {}
+                    Err(StorageError::InsufficientFunds) => {
+                        return Ok(format!(
+                            "The tip of {} from {} can't be claimed yet, their balance is too low.",
+                            amount, tip.from
+                        ));
+                    }
+                    Err(err) => return Err(DispatchError::Other(err.to_string())),
+                }
+                match storage.take_pending_tip(&tip.id) {
+                    Ok(_) | Err(StorageError::NotFound) => {}
+                    Err(err) => log::warn!("Couldn't remove claimed tip {}: {}", tip.id, err),
+                }
+                Ok(format!(
+                    "Claimed the tip of {} from {} on {}.",
+                    amount, tip.from, tip.network
+                ))
+            });
+            lines.push(claimed.unwrap_or_else(|err| {
+                log::warn!("Couldn't claim tip {}: {:?}", tip.id, err);
+                format!(
+                    "The tip from {} on {} can't be claimed right now, please try again later.",
+                    tip.from, tip.network
+                )
+            }));
+        }
+
+        Ok(Some(Response::Reply(lines.join("\n"))))
+    }
+
+    pub fn configure_faucet_channel(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        setting: FaucetSetting,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        // Update the policy of the faucet.
+        Self::get_faucet(&storage, channel, network)?;
+        let network = Self::get_network(&storage, network)?;
+        let policy = storage
+            .configure_faucet_channel(channel, &network.name, setting)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let limit = |v: Option<String>| v.unwrap_or("unlimited".into());
+        Ok(Some(Response::Reply(format!(
+            "The faucet on {} has been configured.\n\namount: {}\ncooldown: {}\nmax_drips_per_day: {}\ndaily_budget: {}\nmin_balance: {}\nalert_balance: {}\nbatch_window: {}s\nbatch_size: {}",
+            network.name,
+            policy.amount.unwrap_or(T::DripAmount::get()),
+            format_duration(policy.cooldown.unwrap_or(T::DripCooldown::get())),
+            limit(policy.max_drips_per_day.map(|v| v.to_string())),
+            limit(policy.daily_budget.map(|v| v.to_string())),
+            policy.min_balance.unwrap_or_default(),
+            limit(policy.alert_balance.map(|v| v.to_string())),
+            policy.batch_window.unwrap_or_default(),
+            policy
+                .batch_size()
+                .map(|v| v.to_string())
+                .unwrap_or("off".into()),
+        ))))
+    }
+
+    pub fn faucet_status(channel: &Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let faucet_channel = storage
+            .get_faucet_channel(channel)
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "This channel isn't a faucet".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+        if faucet_channel.networks.is_empty() {
+            return Ok(Some(Response::Reply(
+                "The faucet of this channel hasn't been activated yet.".into(),
+            )));
+        }
+
+        let now = now();
+        let mut sections = Vec::new();
+        for (name, faucet) in faucet_channel.networks {
+            let chain_info = &faucet.chain;
+            let account =
+                chain::account_of(&Self::faucet_pair(channel, &name, &faucet.wallet_seed)?);
+            let (drips, paid) = storage
+                .get_faucet_usage(channel, &name, now)
+                .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+            // A network that can't be reached shouldn't hide the status of
+            // the others.
+            let balance = Self::get_network(&storage, &name)
+                .and_then(|network| Ok(chain::connect_any(&network.rpc_urls)?))
+                .and_then(|api| Ok(chain::free_balance(&api, &account)?))
+                .map(|balance| chain_info.format_balance(balance))
+                .unwrap_or_else(|err| {
+                    log::warn!("Couldn't get the faucet balance on {}: {:?}", name, err);
+                    String::from("unavailable")
+                });
+
+            let policy = &faucet.policy;
+            let budget = match policy.daily_budget {
+                Some(budget) => format!(" of {}", chain_info.format_balance(budget)),
+                None => String::new(),
+            };
+            sections.push(format!(
+                "{} ({})\nAddress: {}\nBalance: {}\nDrip: {} every {}\nToday: {} drip(s), {}{} paid out",
+                name,
+                chain_info.name,
+                chain_info.address_of(&account),
+                balance,
+                chain_info.format_balance(policy.amount.unwrap_or(T::DripAmount::get())),
+                format_duration(policy.cooldown.unwrap_or(T::DripCooldown::get())),
+                drips,
+                chain_info.format_balance(paid),
+                budget,
+            ));
+        }
+
+        Ok(Some(Response::Reply(sections.join("\n\n"))))
+    }
+
+    pub fn faucet_balance(who: Origin, channel: &Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        let faucet_channel = storage
+            .get_faucet_channel(channel)
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "This channel isn't a faucet".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+        if faucet_channel.networks.is_empty() {
+            return Ok(Some(Response::Reply(
+                "The faucet of this channel hasn't been activated yet.".into(),
+            )));
+        }
+
+        let mut lines = Vec::new();
+        for (name, faucet) in faucet_channel.networks {
+            let balance = Self::get_network(&storage, &name).and_then(|network| {
+                let account =
+                    chain::account_of(&Self::faucet_pair(channel, &name, &faucet.wallet_seed)?);
+                let api = chain::connect_any(&network.rpc_urls)?;
+                Ok(chain::free_balance(&api, &account)?)
+            });
+            lines.push(match balance {
+                Ok(balance) => format!(
+                    "{}: {} (about {} drip(s) left)",
+                    name,
+                    faucet.chain.format_balance(balance),
+                    faucet.policy.drips_left(balance, T::DripAmount::get()),
+                ),
+                Err(err) => {
+                    log::warn!("Couldn't get the faucet balance on {}: {:?}", name, err);
+                    format!("{}: unavailable", name)
+                }
+            });
+        }
+
+        Ok(Some(Response::Reply(lines.join("\n"))))
+    }
+
+    /// Check the balances of all faucets with an alert balance.
+    ///
+    /// Returns alerts for the faucet channels and the admins of faucets whose
+    /// balance dropped below their alert balance since the last check.
+    pub fn check_faucet_balances() -> Result<Vec<Response>, DispatchError> {
+        // Get the storage, it's released while talking to the nodes.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let faucets = storage
+            .get_faucets()
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let networks = storage
+            .get_networks()
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let admins = storage
+            .get_admins()
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        drop(storage);
+
+        let mut responses = Vec::new();
+        let mut changes = Vec::new();
+        for (channel, name, faucet) in faucets {
+            let Some(alert_balance) = faucet.policy.alert_balance else {
+                continue;
+            };
+            let Some(network) = networks.iter().find(|network| network.name == name) else {
+                continue;
+            };
+
+            let account = match Self::faucet_pair(&channel, &name, &faucet.wallet_seed) {
+                Ok(signer) => chain::account_of(&signer),
+                Err(err) => {
+                    log::warn!(
+                        "Couldn't get the faucet wallet of {} on {}: {:?}",
+                        channel,
+                        name,
+                        err
+                    );
+                    continue;
+                }
+            };
+            let balance = match chain::connect_any(&network.rpc_urls)
+                .and_then(|api| chain::free_balance(&api, &account))
+            {
+                Ok(balance) => balance,
+                Err(err) => {
+                    log::warn!(
+                        "Couldn't check the faucet balance of {} on {}: {}",
+                        channel,
+                        name,
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            // Only alert once until the balance has recovered.
+            let low_balance = balance < alert_balance;
+            if low_balance == faucet.low_balance {
+                continue;
+            }
+            changes.push((channel.clone(), name.clone(), low_balance));
+            if !low_balance {
+                continue;
+            }
+
+            let chain_info = &faucet.chain;
+            let msg = format!(
+                "The faucet on {} is running low on funds: {} left, enough for about {} drip(s).\n\nPlease fund the faucet by sending {} to {}",
+                name,
+                chain_info.format_balance(balance),
+                faucet.policy.drips_left(balance, T::DripAmount::get()),
+                chain_info.token_symbol,
+                chain_info.address_of(&account),
+            );
+            responses.push(Response::SayChan(channel.clone(), msg.clone()));
+            for admin in &admins {
+                responses.push(Response::SayDirect(
+                    admin.clone(),
+                    format!("Faucet channel {}: {}", channel, msg),
+                ));
+            }
+        }
+
+        // Remember which faucets have been alerted about.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        for (channel, name, low_balance) in changes {
+            if let Err(err) = storage.set_faucet_low_balance(&channel, &name, low_balance) {
+                log::warn!(
+                    "Couldn't update the faucet of {} on {}: {}",
+                    channel,
+                    name,
+                    err
+                );
+            }
+        }
+
+        Ok(responses)
+    }
+
+    /// Check whether a network is registered under a name.
+    pub fn is_network(name: &str) -> Result<bool, DispatchError> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        match storage.get_network(name) {
+            Ok(_) => Ok(true),
+            Err(StorageError::NotFound) => Ok(false),
+            Err(err) => Err(DispatchError::Other(err.to_string())),
+        }
+    }
+
+    /// Get the names of the networks the faucet of a channel pays out on.
+    ///
+    /// Returns an empty list if the channel isn't a faucet.
+    pub fn faucet_networks(channel: &Origin) -> Result<Vec<String>, DispatchError> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        match storage.get_faucet_channel(channel) {
+            Ok(faucet_channel) => Ok(faucet_channel.networks.into_keys().collect()),
+            Err(StorageError::NotFound) => Ok(Vec::new()),
+            Err(err) => Err(DispatchError::Other(err.to_string())),
+        }
+    }
+
+    pub fn add_network(
+        who: Origin,
+        name: &str,
+        rpc_urls: &[String],
+        explorer_url: Option<&str>,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        // Network names are used as command arguments, so keep them simple.
+        let name = name.trim().to_lowercase();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Network names may only contain letters, digits, dashes and underscores".into(),
+            ));
+        }
+        if let Some(explorer_url) = explorer_url {
+            if !explorer_url.contains("{hash}") {
+                return Err(DispatchError::Module(
+                    crate::MODULE.into(),
+                    "The explorer url must contain {hash}".into(),
+                ));
+            }
+        }
+
+        // Find out which chain the nodes are on. All endpoints must be on the
+        // same chain, otherwise failing over would switch chains.
+        let rpc_urls: Vec<String> = rpc_urls
+            .iter()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+        let mut chain_info: Option<chain::ChainInfo> = None;
+        for rpc_url in &rpc_urls {
+            let info = chain::chain_info(&chain::connect(rpc_url)?)?;
+            match &chain_info {
+                Some(first) if first.genesis_hash != info.genesis_hash => {
+                    return Err(DispatchError::Module(
+                        crate::MODULE.into(),
+                        format!("{} isn't on {}", rpc_url, first.name),
+                    ));
+                }
+                Some(_) => {}
+                None => chain_info = Some(info),
+            }
+        }
+        let Some(chain_info) = chain_info else {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "At least one rpc url is required".into(),
+            ));
+        };
+
+        let network = NetworkData {
+            name: name.clone(),
+            rpc_urls,
+            chain: chain_info.clone(),
+            explorer_url: explorer_url.map(String::from),
+        };
+        storage.add_network(network).map_err(|err| match err {
+            StorageError::NotUnique => DispatchError::Module(
+                crate::MODULE.into(),
+                format!("A network named {} already exists", name),
+            ),
+            err => DispatchError::Other(err.to_string()),
+        })?;
+
+        let mut reply = format!(
+            "The network {} has been added.\n\nChain: {}\nGenesis hash: {}\nToken: {} ({} decimals)\nSS58 prefix: {}",
+            name,
+            chain_info.name,
+            chain_info.genesis_hash,
+            chain_info.token_symbol,
+            chain_info.token_decimals,
+            chain_info.ss58_prefix,
+        );
+        // Faucets of older versions that used one of the endpoints pay out on
+        // the network from now on.
+        match Self::bind_unbound_faucets() {
+            Ok(0) => {}
+            Ok(bound) => reply.push_str(&format!(
+                "\n\n{} faucet(s) of older versions of the bot now pay out on it.",
+                bound
+            )),
+            Err(err) => log::error!("Couldn't bind faucets to {}: {}", name, err),
+        }
+
+        Ok(Some(Response::Reply(reply)))
+    }
+
+    pub fn remove_network(who: Origin, name: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        // Reject if a faucet still pays out on the network.
+        let network = Self::get_network(&storage, name)?;
+        let in_use = storage
+            .get_faucets()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .into_iter()
+            .any(|(_, name, _)| name == network.name);
+        if in_use {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!("The network {} is still used by a faucet", network.name),
+            ));
+        }
+
+        storage
+            .remove_network(&network.name)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Nobody can be reminded to vote on the network or alerted about its
+        // validators anymore.
+        let mut lines = vec![format!("The network {} has been removed.", network.name)];
+        let reminders = storage
+            .remove_network_vote_reminders(&network.name)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if reminders > 0 {
+            lines.push(format!(
+                "{} user(s) won't be reminded to vote on it anymore.",
+                reminders
+            ));
+        }
+        let alerts = storage
+            .remove_network_validator_alerts(&network.name)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if alerts > 0 {
+            lines.push(format!(
+                "{} validator alert(s) on it have been removed.",
+                alerts
+            ));
+        }
+
+        Ok(Some(Response::Reply(lines.join("\n"))))
+    }
+
+    pub fn list_networks() -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
             .map_err(|err| DispatchError::Other(err.to_string()))?;
 
-        Ok(Some(Response::SayChan(
-            channel.clone(),
-            "Faucet has been activated for this channel!".into(),
-        )))
+        let networks = storage
+            .get_networks()
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if networks.is_empty() {
+            return Ok(Some(Response::Reply(
+                "No networks have been added yet.".into(),
+            )));
+        }
+
+        let lines: Vec<String> = networks
+            .iter()
+            .map(|network| {
+                format!(
+                    "{}: {} ({})",
+                    network.name, network.chain.name, network.chain.token_symbol,
+                )
+            })
+            .collect();
+        Ok(Some(Response::Reply(format!(
+            "Available networks:\n\n{}",
+            lines.join("\n"),
+        ))))
+    }
+
+    pub fn rotate_seed_key(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+        drop(storage);
+
+        let count = Self::reencrypt_faucet_seeds(false)?;
+
+        Ok(Some(Response::Reply(format!(
+            "{} faucet seed(s) have been encrypted with the current key.",
+            count,
+        ))))
+    }
+
+    /// Bind the faucets stored by older versions of the bot, before faucets
+    /// paid out on registered networks, to the network they paid out on.
+    ///
+    /// Faucets that can't be bound keep their seed and policy until an admin
+    /// registers their network with the RPC url they used. Returns the number
+    /// of bound faucets.
+    pub fn bind_unbound_faucets() -> Result<usize, DispatchError> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let networks = storage
+            .get_networks()
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let bound = storage
+            .bind_unbound_faucets(&networks)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        for (channel, _, faucet) in storage
+            .get_unbound_faucets()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            log::warn!(
+                "The faucet of {} on {} isn't bound to a network, register a network with its RPC url to bind it",
+                channel,
+                faucet
+                    .network
+                    .or(faucet.rpc_url)
+                    .unwrap_or_else(|| "an unknown network".into()),
+            );
+        }
+
+        Ok(bound)
+    }
+
+    /// Rebuild the index of the users of wallet addresses.
+    ///
+    /// Returns the number of indexed addresses.
+    pub fn rebuild_wallet_index() -> Result<usize, DispatchError> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        storage
+            .rebuild_wallet_index()
+            .map_err(|err| DispatchError::Other(err.to_string()))
+    }
+
+    /// Encrypt all faucet seeds that were stored in plaintext by older
+    /// versions of the bot.
+    ///
+    /// Returns the number of migrated seeds.
+    pub fn encrypt_plaintext_seeds() -> Result<usize, DispatchError> {
+        Self::reencrypt_faucet_seeds(true)
+    }
+
+    /// Encrypt faucet seeds with the current key.
+    ///
+    /// Seeds encrypted with the previous key are only re-encrypted when
+    /// `plaintext_only` is false.
+    fn reencrypt_faucet_seeds(plaintext_only: bool) -> Result<usize, DispatchError> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let key = T::SeedEncryptionKey::get();
+        let key_id = crypto::key_id(&key)?;
+
+        let up_to_date = |seed: &StoredSeed| match seed.key_id() {
+            None => seed.is_empty(),
+            Some(id) => plaintext_only || id == key_id,
+        };
+
+        let mut count = 0;
+        for (channel, network, faucet) in storage
+            .get_faucets()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            if up_to_date(&faucet.wallet_seed) {
+                continue;
+            }
+
+            let seed = Self::decrypt_seed(&faucet.wallet_seed)?;
+            storage
+                .set_faucet_seed(&channel, &network, crypto::encrypt_seed(&key, &seed)?)
+                .map_err(|err| DispatchError::Other(err.to_string()))?;
+            count += 1;
+        }
+
+        // Faucets of older versions are encrypted too while they wait to be
+        // bound to a network.
+        for (channel, index, faucet) in storage
+            .get_unbound_faucets()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            if up_to_date(&faucet.wallet_seed) {
+                continue;
+            }
+
+            let seed = Self::decrypt_seed(&faucet.wallet_seed)?;
+            storage
+                .set_unbound_faucet_seed(&channel, index, crypto::encrypt_seed(&key, &seed)?)
+                .map_err(|err| DispatchError::Other(err.to_string()))?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Get the wallet registered by a user.
+    pub(crate) fn registered_wallet(
+        storage: &Storage,
+        who: &Origin,
+    ) -> Result<String, DispatchError> {
+        storage.get_user_wallet(who.clone()).map_err(|err| match err {
+            StorageError::NotFound => DispatchError::Module(
+                crate::MODULE.into(),
+                "You haven't registered a wallet yet. Please provide an address or register your wallet first".into(),
+            ),
+            err => DispatchError::Other(err.to_string()),
+        })
+    }
+
+    /// Get a wallet of a user by label.
+    pub(crate) fn labelled_wallet(
+        storage: &Storage,
+        who: &Origin,
+        label: &str,
+    ) -> Result<WalletData, DispatchError> {
+        storage
+            .get_user_wallets(who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .get(label)
+            .cloned()
+            .ok_or(DispatchError::Module(
+                crate::MODULE.into(),
+                format!("You don't have a wallet labelled {}", label.trim()),
+            ))
+    }
+
+    /// Resolve the address a command refers to.
+    ///
+    /// `target` is either the label of one of the user's wallets or an
+    /// address, which is returned as is. Without a target, the user's wallet
+    /// for `network` is preferred over the default one.
+    pub(crate) fn resolve_address(
+        storage: &Storage,
+        who: &Origin,
+        target: Option<&str>,
+        network: Option<&str>,
+    ) -> Result<String, DispatchError> {
+        let wallets = storage
+            .get_user_wallets(who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if let Some(target) = target {
+            return Ok(match wallets.get(target) {
+                Some(wallet) => wallet.address.clone(),
+                None => target.trim().to_string(),
+            });
+        }
+
+        match network.and_then(|network| wallets.for_network(network)) {
+            Some(wallet) => Ok(wallet.address.clone()),
+            None => Self::registered_wallet(storage, who),
+        }
+    }
+
+    /// Get a registered network by name.
+    pub(crate) fn get_network(storage: &Storage, name: &str) -> Result<NetworkData, DispatchError> {
+        storage.get_network(name).map_err(|err| match err {
+            StorageError::NotFound => {
+                DispatchError::Module(crate::MODULE.into(), format!("Unknown network {}", name))
+            }
+            err => DispatchError::Other(err.to_string()),
+        })
+    }
+
+    /// Get the network a command about a pallet refers to.
+    ///
+    /// Without a network, it's the one the channel follows the events of the
+    /// pallet on, or the only one registered.
+    pub(crate) fn channel_network(
+        storage: &Storage,
+        channel: &Origin,
+        network: Option<&str>,
+        pallet: &str,
+    ) -> Result<NetworkData, DispatchError> {
+        if let Some(network) = network {
+            return Self::get_network(storage, network);
+        }
+
+        let mut networks: Vec<String> = storage
+            .get_channel_subscriptions(channel)
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .into_iter()
+            .filter(|subscription| subscription.filter.pallet.eq_ignore_ascii_case(pallet))
+            .map(|subscription| subscription.network)
+            .collect();
+        networks.sort();
+        networks.dedup();
+        if let [network] = networks.as_slice() {
+            return Self::get_network(storage, network);
+        }
+
+        match storage
+            .get_networks()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .as_slice()
+        {
+            [network] => Ok(network.clone()),
+            _ => Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Please name the network".into(),
+            )),
+        }
+    }
+
+    /// Get the faucet of a channel on a network.
+    pub(crate) fn get_faucet(
+        storage: &Storage,
+        channel: &Origin,
+        network: &str,
+    ) -> Result<FaucetData, DispatchError> {
+        let mut faucet_channel = storage
+            .get_faucet_channel(channel)
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "This channel isn't a faucet".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        let network = network.trim().to_lowercase();
+        match faucet_channel.networks.remove(&network) {
+            Some(faucet) => Ok(faucet),
+            None if faucet_channel.networks.is_empty() => Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "The faucet of this channel hasn't been activated yet".into(),
+            )),
+            None => Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!(
+                    "This faucet doesn't pay out on {}. Available networks: {}",
+                    network,
+                    faucet_channel
+                        .networks
+                        .into_keys()
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+            )),
+        }
+    }
+
+    /// Get the key pair a faucet pays out with.
+    ///
+    /// Unless a seed was imported, the key pair is derived from the bot's wallet
+    /// seed, which is equivalent to the
+    /// `<wallet seed>//faucet//<channel>//<network>` secret uri. Every network
+    /// gets its own account, so a chain that replays transactions of another
+    /// can't drain the faucet.
+    pub(crate) fn faucet_pair(
+        channel: &Origin,
+        network: &str,
+        wallet_seed: &StoredSeed,
+    ) -> Result<sr25519::Pair, DispatchError> {
+        if wallet_seed.is_empty() {
+            let master = chain::pair_from_seed(&T::WalletSeed::get())?;
+            Ok(chain::derive_pair(
+                &master,
+                &["faucet", &channel.to_string(), network],
+            )?)
+        } else {
+            Ok(chain::pair_from_seed(&Self::decrypt_seed(wallet_seed)?)?)
+        }
+    }
+
+    /// Decrypt a stored seed with the current or the previous key.
+    fn decrypt_seed(stored: &StoredSeed) -> Result<Zeroizing<String>, DispatchError> {
+        let keys = [
+            T::SeedEncryptionKey::get(),
+            T::PreviousSeedEncryptionKey::get(),
+        ];
+        Ok(crypto::decrypt_seed(
+            &[keys[0].as_str(), keys[1].as_str()],
+            stored,
+        )?)
     }
 
     pub fn set_admin(who: Origin, admin: &Origin, remove: bool) -> DispatchResult<Response> {
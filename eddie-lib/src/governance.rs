@@ -0,0 +1,362 @@
+use crate::{
+    bot::Bot,
+    call::Response,
+    chain::{self, ChainApi, ChainError, Referendum, Track},
+    config::Config,
+    functions::format_duration,
+    origin::Origin,
+    storage::{
+        storage::{NetworkData, StorageError, VoteReminderData},
+        Storage,
+    },
+};
+use std::{collections::BTreeMap, time::Duration};
+use support::traits::{dispatch::DispatchError, Get};
+
+/// Type to make function definitions a bit cleaner.
+type DispatchResult<T> = support::traits::dispatch::DispatchResult<Option<T>>;
+
+/// How long before a referendum closes users are reminded to vote.
+const REMINDER_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of the latest referenda checked for reminders, older ones are
+/// assumed to be over.
+const MAX_REFERENDA_SCAN: u32 = 100;
+
+/// Get the block a deciding referendum closes at.
+fn closes_at(deciding: &chain::DecidingStatus, track: &Track) -> u32 {
+    deciding
+        .confirming
+        .unwrap_or(deciding.since + track.decision_period)
+}
+
+/// Get the time until a block.
+fn time_until(block: u32, now: u32, block_time: Duration) -> String {
+    format_duration(block.saturating_sub(now) as u64 * block_time.as_secs())
+}
+
+/// Turn the name of an event like `DecisionDepositPlaced` into words.
+fn describe_event(variant: &str) -> String {
+    let mut words = String::new();
+    for c in variant.chars() {
+        if c.is_uppercase() && !words.is_empty() {
+            words.push(' ');
+        }
+        words.extend(c.to_lowercase());
+    }
+    words
+}
+
+/// Describe a referendum with its tally and the time left, starting with
+/// what happened to it if anything.
+pub(crate) fn referendum_message(
+    api: &ChainApi,
+    network: &NetworkData,
+    index: u32,
+    event: Option<&str>,
+) -> Result<String, ChainError> {
+    let mut lines = vec![match event {
+        Some(event) => format!(
+            "[{}] Referendum #{}: {}",
+            network.name,
+            index,
+            describe_event(event)
+        ),
+        None => format!("[{}] Referendum #{}", network.name, index),
+    }];
+
+    let (track, submitted, decision_deposit, deciding, tally) = match chain::referendum(api, index)?
+    {
+        None => {
+            lines.push("It doesn't exist.".into());
+            return Ok(lines.join("\n"));
+        }
+        Some(Referendum::Ended { outcome, block }) => {
+            lines.push(format!("It was {} at block {}.", outcome, block));
+            return Ok(lines.join("\n"));
+        }
+        Some(Referendum::Ongoing {
+            track,
+            submitted,
+            decision_deposit,
+            deciding,
+            tally,
+        }) => (track, submitted, decision_deposit, deciding, tally),
+    };
+
+    let tracks = chain::referendum_tracks(api)?;
+    let now = chain::best_block(api)?;
+    let block_time = chain::block_time(api);
+    let Some(track) = tracks.iter().find(|t| t.id == track) else {
+        lines.push(format!("Track: {}", track));
+        return Ok(lines.join("\n"));
+    };
+    lines.push(format!("Track: {}", track.name));
+
+    lines.push(match (&decision_deposit, &deciding) {
+        (None, _) => format!(
+            "Status: waiting for a decision deposit, submitted at block {}",
+            submitted
+        ),
+        (Some(_), None) => format!(
+            "Status: preparing, deciding can start in {}",
+            time_until(submitted + track.prepare_period, now, block_time)
+        ),
+        (Some(_), Some(deciding)) if deciding.confirming.is_some() => format!(
+            "Status: confirming, passes in {}",
+            time_until(closes_at(deciding, track), now, block_time)
+        ),
+        (Some(_), Some(deciding)) => format!(
+            "Status: deciding, {} left to vote",
+            time_until(closes_at(deciding, track), now, block_time)
+        ),
+    });
+    if let Some(deposit) = &decision_deposit {
+        lines.push(format!(
+            "Decision deposit: {} by {}",
+            network.chain.format_balance(deposit.amount),
+            network.chain.address_of(&deposit.who)
+        ));
+    }
+
+    let issuance = chain::active_issuance(api)?;
+    lines.push(format!(
+        "Aye: {} ({:.2}%), Nay: {} ({:.2}%)",
+        network.chain.format_balance(tally.ayes),
+        tally.approval(),
+        network.chain.format_balance(tally.nays),
+        100.0 - tally.approval(),
+    ));
+    lines.push(format!(
+        "Support: {} ({:.2}% of the issuance)",
+        network.chain.format_balance(tally.support),
+        tally.turnout(issuance)
+    ));
+    Ok(lines.join("\n"))
+}
+
+impl<T: Config> Bot<T> {
+    pub fn referendum(
+        channel: &Origin,
+        network: Option<&str>,
+        index: u32,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::channel_network(&storage, channel, network, "Referenda")?;
+        let api = chain::connect_any(&network.rpc_urls)?;
+        Ok(Some(Response::Reply(referendum_message(
+            &api, &network, index, None,
+        )?)))
+    }
+
+    pub fn vote_reminders(who: Origin, network: &str, enabled: bool) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::get_network(&storage, network)?;
+        let reminder = VoteReminderData {
+            origin: who.to_string(),
+            network: network.name.clone(),
+            reminded: Vec::new(),
+        };
+        if !enabled {
+            storage
+                .remove_vote_reminder(&reminder)
+                .map_err(|err| match err {
+                    StorageError::NotFound => DispatchError::Module(
+                        crate::MODULE.into(),
+                        format!("You don't get reminders on {}", network.name),
+                    ),
+                    err => DispatchError::Other(err.to_string()),
+                })?;
+            return Ok(Some(Response::Reply(format!(
+                "You won't be reminded to vote on {} anymore.",
+                network.name
+            ))));
+        }
+
+        // Votes are checked for the wallet that's used on the network.
+        let wallet = storage
+            .get_user_wallets(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .for_network(&network.name)
+            .map(|wallet| wallet.address.clone())
+            .ok_or(DispatchError::Module(
+                crate::MODULE.into(),
+                "Please register a wallet first".into(),
+            ))?;
+        storage
+            .add_vote_reminder(&reminder)
+            .map_err(|err| match err {
+                StorageError::NotUnique => DispatchError::Module(
+                    crate::MODULE.into(),
+                    format!("You already get reminders on {}", network.name),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        Ok(Some(Response::Reply(format!(
+            "You'll get a message when a referendum on {} that {} hasn't voted on closes within {}.",
+            network.name,
+            wallet,
+            format_duration(REMINDER_WINDOW.as_secs())
+        ))))
+    }
+
+    /// Remind users of the referenda closing soon that their wallets haven't
+    /// voted on.
+    ///
+    /// Runs in the background. Users are reminded once per referendum.
+    pub fn remind_voters() -> Result<Vec<Response>, DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let mut reminders: BTreeMap<String, Vec<VoteReminderData>> = BTreeMap::new();
+        for reminder in storage
+            .get_vote_reminders()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            reminders
+                .entry(reminder.network.clone())
+                .or_default()
+                .push(reminder);
+        }
+
+        let mut responses = Vec::new();
+        for (name, reminders) in reminders {
+            let network = match Self::get_network(&storage, &name) {
+                Ok(network) => network,
+                Err(err) => {
+                    log::warn!("Couldn't check the referenda on {}: {}", name, err);
+                    continue;
+                }
+            };
+            let api = match chain::connect_any(&network.rpc_urls) {
+                Ok(api) => api,
+                Err(err) => {
+                    log::warn!("Couldn't check the referenda on {}: {}", name, err);
+                    continue;
+                }
+            };
+            match Self::remind_network_voters(&storage, &api, &network, reminders) {
+                Ok(messages) => responses.extend(messages),
+                Err(err) => log::warn!("Couldn't remind the voters on {}: {}", name, err),
+            }
+        }
+        Ok(responses)
+    }
+
+    /// Remind the users of a network of the referenda closing soon.
+    fn remind_network_voters(
+        storage: &Storage,
+        api: &ChainApi,
+        network: &NetworkData,
+        reminders: Vec<VoteReminderData>,
+    ) -> Result<Vec<Response>, DispatchError> {
+        let now = chain::best_block(api)?;
+        let block_time = chain::block_time(api);
+        let window = (REMINDER_WINDOW.as_secs() / block_time.as_secs().max(1)) as u32;
+        let tracks = chain::referendum_tracks(api)?;
+
+        // The referenda in their decision phase that close within the window.
+        // One that can't be read doesn't keep the others from being checked.
+        let count = chain::referendum_count(api)?;
+        let mut closing = Vec::new();
+        for index in count.saturating_sub(MAX_REFERENDA_SCAN)..count {
+            let referendum = match chain::referendum(api, index) {
+                Ok(referendum) => referendum,
+                Err(err) => {
+                    log::warn!(
+                        "Couldn't read referendum #{} on {}: {}",
+                        index,
+                        network.name,
+                        err
+                    );
+                    continue;
+                }
+            };
+            let Some(Referendum::Ongoing {
+                track,
+                deciding: Some(deciding),
+                ..
+            }) = referendum
+            else {
+                continue;
+            };
+            let Some(track) = tracks.iter().find(|t| t.id == track) else {
+                continue;
+            };
+            let closes = closes_at(&deciding, track);
+            if closes > now && closes - now <= window {
+                closing.push((index, track.id, closes));
+            }
+        }
+
+        let mut responses = Vec::new();
+        for mut reminder in reminders {
+            let Ok(origin) = Origin::try_from(reminder.origin.clone()) else {
+                continue;
+            };
+            let wallet = match storage.get_user_wallets(&origin) {
+                Ok(wallets) => wallets
+                    .for_network(&network.name)
+                    .map(|wallet| wallet.address.clone()),
+                Err(err) => {
+                    log::warn!("Couldn't get the wallets of {}: {}", origin, err);
+                    continue;
+                }
+            };
+            let Some(account) = wallet.and_then(|address| chain::parse_address(&address).ok())
+            else {
+                continue;
+            };
+
+            // Referenda that closed don't need to be remembered.
+            reminder
+                .reminded
+                .retain(|index| closing.iter().any(|(i, _, _)| i == index));
+            let mut messages = Vec::new();
+            for (index, track, closes) in &closing {
+                if reminder.reminded.contains(index) {
+                    continue;
+                }
+                match chain::has_voted(api, &account, *track, *index) {
+                    Ok(false) => {}
+                    Ok(true) => continue,
+                    Err(err) => {
+                        log::warn!(
+                            "Couldn't check the vote of {} on referendum #{} on {}: {}",
+                            origin,
+                            index,
+                            network.name,
+                            err
+                        );
+                        continue;
+                    }
+                }
+                reminder.reminded.push(*index);
+                messages.push(Response::SayDirect(
+                    origin.clone(),
+                    format!(
+                        "Referendum #{} on {} closes in {} and {} hasn't voted on it yet.",
+                        index,
+                        network.name,
+                        time_until(*closes, now, block_time),
+                        network.chain.address_of(&account)
+                    ),
+                ));
+            }
+
+            // The reminders are only remembered together with the messages
+            // that are returned for them.
+            match storage.update_vote_reminder(&reminder) {
+                Ok(()) => responses.extend(messages),
+                Err(err) => log::warn!("Couldn't update the reminders of {}: {}", origin, err),
+            }
+        }
+        Ok(responses)
+    }
+}
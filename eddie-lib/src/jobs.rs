@@ -0,0 +1,628 @@
+use crate::{
+    bot::Bot,
+    call::UpdateHandle,
+    chain,
+    config::Config,
+    functions::now,
+    origin::Origin,
+    storage::{
+        storage::{
+            FaucetPolicy, JobData, JobState, LedgerEntryData, NetworkData, StorageError,
+            HOT_WALLET_ACCOUNT,
+        },
+        Storage,
+    },
+};
+use sp_core::sr25519;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{mpsc::Sender, Mutex, MutexGuard, OnceLock},
+    time::Duration,
+};
+use support::traits::{dispatch::DispatchError, Get};
+
+/// Number of times a job is submitted before it's given up on.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Seconds to wait before the first retry, doubled on every further attempt.
+const RETRY_DELAY: u64 = 10;
+
+/// Maximum number of seconds to wait between retries.
+const MAX_RETRY_DELAY: u64 = 10 * 60;
+
+/// Outcome of a round of a worker.
+enum Round {
+    /// No jobs are due.
+    Idle,
+    /// More jobs may be due right away.
+    Again,
+    /// A batch is being collected until the given unix timestamp.
+    Collecting(u64),
+}
+
+/// Senders of the replies tracking a job, by job id.
+static TRACKERS: OnceLock<Mutex<HashMap<String, Sender<String>>>> = OnceLock::new();
+
+/// Signer accounts with a running worker and jobs waiting for finality, with
+/// whether new jobs were queued for them since their last round.
+static WORKERS: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+
+/// Lock one of the registries above, which stay usable when a thread
+/// panicked while holding them.
+fn lock<V: Default>(registry: &'static OnceLock<Mutex<V>>) -> MutexGuard<'static, V> {
+    registry
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+/// Get the number of seconds to wait before retrying a job.
+fn retry_delay(attempts: u32) -> u64 {
+    RETRY_DELAY
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Format the status of a payout job.
+pub(crate) fn job_status_message(job: &JobData, network: &NetworkData) -> String {
+    let mut msg = format!(
+        "Sending {} to {}.\n\nStatus: ",
+        network.chain.format_balance(job.amount),
+        job.dest
+    );
+    match (job.state, &job.error) {
+        (JobState::Pending, None) => msg.push_str("queued"),
+        (JobState::Pending, Some(err)) => msg.push_str(&format!(
+            "retrying (attempt {} of {} failed: {})",
+            job.attempts, MAX_ATTEMPTS, err
+        )),
+        (JobState::Submitted, _) => msg.push_str("in block"),
+        (JobState::Finalized, _) => msg.push_str("finalized"),
+        (JobState::Failed, err) => {
+            msg.push_str(&format!("failed ({})", err.as_deref().unwrap_or("unknown")))
+        }
+    }
+    if let Some(block) = job.block {
+        msg.push_str(&format!("\nBlock: #{}", block));
+    }
+    if let Some(hash) = &job.hash {
+        msg.push_str(&format!("\nExtrinsic hash: {}", hash));
+        if let Some(link) = network.explorer_link(hash) {
+            msg.push_str(&format!("\n{}", link));
+        }
+    }
+    msg
+}
+
+impl<T: Config> Bot<T> {
+    /// Queue a payout and get a handle to follow its status.
+    ///
+    /// Fails with `StorageError::NotUnique` when a job with the same
+    /// idempotency key was queued before.
+    pub(crate) fn enqueue_job(
+        storage: &Storage,
+        job: &JobData,
+    ) -> Result<UpdateHandle, StorageError> {
+        storage.enqueue_job(job)?;
+        Ok(Self::track_job(job))
+    }
+
+    /// Queue a payout under the idempotency key of a failed one and get a
+    /// handle to follow its status.
+    ///
+    /// Fails with `StorageError::NotUnique` unless the job with the same key
+    /// failed.
+    pub(crate) fn requeue_job(
+        storage: &Storage,
+        job: &JobData,
+    ) -> Result<UpdateHandle, StorageError> {
+        storage.requeue_job(job)?;
+        Ok(Self::track_job(job))
+    }
+
+    /// Get a handle to follow the status of a queued job.
+    fn track_job(job: &JobData) -> UpdateHandle {
+        let (updates, handle) = UpdateHandle::new();
+        lock(&TRACKERS).insert(job.id.clone(), updates);
+        handle
+    }
+
+    /// Start workers for all signer accounts with pending jobs and watch the
+    /// jobs waiting for finality.
+    ///
+    /// Runs in the background, so it can be called whenever a job was queued.
+    /// It's also called periodically to retry failed submissions and to resume
+    /// the queue after a restart.
+    pub fn run_jobs() -> Result<(), DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let jobs = storage
+            .get_open_jobs()
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        for job in jobs {
+            let key = match job.state {
+                JobState::Pending => match Self::signer_key(&storage, &job) {
+                    Ok(key) => key,
+                    Err(err) => {
+                        Self::reject_job(&storage, job, err.to_string());
+                        continue;
+                    }
+                },
+                _ => job.id.clone(),
+            };
+
+            match lock(&WORKERS).entry(key.clone()) {
+                // Let the running worker know it has more to do.
+                Entry::Occupied(mut worker) => *worker.get_mut() = true,
+                Entry::Vacant(worker) => {
+                    worker.insert(false);
+                    match job.state {
+                        JobState::Pending => std::thread::spawn(move || Self::work(key)),
+                        _ => std::thread::spawn(move || Self::watch_finality(job)),
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the key of the account that signs a job.
+    ///
+    /// Faucets with the same wallet share an account, so they're handled by
+    /// the same worker to keep their nonces apart.
+    fn signer_key(storage: &Storage, job: &JobData) -> Result<String, DispatchError> {
+        let (signer, _) = Self::job_signer(storage, job)?;
+        Ok(format!(
+            "{}/{}",
+            job.network,
+            chain::to_address(&chain::account_of(&signer))
+        ))
+    }
+
+    /// Get the key pair that signs a job, with the policy of its faucet.
+    ///
+    /// Withdrawals are paid from the hot wallet. Sweeps, and tips queued
+    /// before tips moved to the ledger, are paid from the deposit address of
+    /// the tipper. None of them is batched.
+    fn job_signer(
+        storage: &Storage,
+        job: &JobData,
+    ) -> Result<(sr25519::Pair, Option<FaucetPolicy>), DispatchError> {
+        if job.withdrawal.is_some() {
+            return Ok((Self::hot_wallet_pair()?, None));
+        }
+        if let Some(tipper) = &job.tipper {
+            let tipper =
+                Origin::try_from(tipper.clone()).map_err(|err| DispatchError::Other(err.into()))?;
+            return Ok((Self::deposit_pair(&tipper)?, None));
+        }
+
+        let channel = Origin::try_from(job.channel.clone())
+            .map_err(|err| DispatchError::Other(err.into()))?;
+        let faucet = Self::get_faucet(storage, &channel, &job.network)?;
+        let signer = Self::faucet_pair(&channel, &job.network, &faucet.wallet_seed)?;
+        Ok((signer, Some(faucet.policy)))
+    }
+
+    /// Submit the pending jobs of a signer account, until none are due
+    /// anymore.
+    ///
+    /// This is the only place extrinsics of the account are signed, so the
+    /// nonce can be kept locally and only has to be synced with the chain
+    /// when a round starts.
+    fn work(key: String) {
+        loop {
+            let result = Self::work_round(&key);
+            let mut workers = lock(&WORKERS);
+            match result {
+                Ok(Round::Again) => continue,
+                Ok(Round::Collecting(until)) => {
+                    drop(workers);
+                    Self::wait_for_jobs(&key, until);
+                }
+                // Jobs may have been queued while the round was running.
+                Ok(Round::Idle) if workers.get(&key) == Some(&true) => {
+                    workers.insert(key.clone(), false);
+                }
+                Ok(Round::Idle) => {
+                    workers.remove(&key);
+                    return;
+                }
+                Err(err) => {
+                    log::error!("Job worker of {} stopped: {}", key, err);
+                    workers.remove(&key);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Sleep until the given unix timestamp, or until jobs are queued for the
+    /// account of a worker.
+    fn wait_for_jobs(key: &str, until: u64) {
+        while now() < until {
+            if let Some(queued) = lock(&WORKERS).get_mut(key) {
+                if *queued {
+                    *queued = false;
+                    return;
+                }
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+    }
+
+    /// Submit the jobs of a signer account that are due.
+    fn work_round(key: &str) -> Result<Round, DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let now = now();
+        let mut jobs: Vec<JobData> = storage
+            .get_open_jobs()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .into_iter()
+            .filter(|job| job.state == JobState::Pending && job.next_attempt <= now)
+            .filter(|job| Self::signer_key(&storage, job).ok().as_deref() == Some(key))
+            .collect();
+        let Some(first) = jobs.first() else {
+            return Ok(Round::Idle);
+        };
+
+        let (signer, policy) = Self::job_signer(&storage, first)?;
+        let account = chain::account_of(&signer);
+        let network = Self::get_network(&storage, &first.network)?;
+
+        // Faucets that batch their payouts wait until the batch is full or its
+        // window has passed. Faucets sharing a wallet follow the settings of
+        // the faucet with the oldest job.
+        let batch_size = policy.as_ref().and_then(FaucetPolicy::batch_size);
+        if let (Some(batch_size), Some(policy)) = (batch_size, &policy) {
+            let closes = first.created + policy.batch_window.unwrap_or_default();
+            if jobs.len() < batch_size && now < closes {
+                return Ok(Round::Collecting(closes));
+            }
+            jobs.truncate(batch_size);
+        }
+
+        let connected = chain::connect_any(&network.rpc_urls).and_then(|mut api| {
+            chain::set_signer(&mut api, signer);
+            let nonce = chain::next_nonce(&api, &account)?;
+            Ok((api, nonce))
+        });
+        let (api, mut nonce) = match connected {
+            Ok(connected) => connected,
+            Err(err) => {
+                for job in jobs {
+                    Self::retry_job(&storage, job, &network, err.to_string());
+                }
+                return Ok(Round::Idle);
+            }
+        };
+
+        // A nonce that was used since it was assigned means the job may have
+        // been paid out before the bot stopped. Paying again could pay twice.
+        let (stale, jobs): (Vec<_>, Vec<_>) = jobs
+            .into_iter()
+            .partition(|job| job.nonce.is_some_and(|assigned| assigned < nonce));
+        for job in stale {
+            Self::fail_job(
+                &storage,
+                job,
+                "the transaction may have gone through before a restart, please check your balance"
+                    .into(),
+            );
+        }
+
+        // Jobs that ran out of attempts can be given up on now that their
+        // nonce is known to be unused.
+        let (exhausted, jobs): (Vec<_>, Vec<_>) = jobs
+            .into_iter()
+            .partition(|job| job.attempts >= MAX_ATTEMPTS);
+        for mut job in exhausted {
+            let err = job.error.take().unwrap_or_default();
+            Self::reject_job(&storage, job, err);
+        }
+
+        if batch_size.is_some() {
+            Self::submit_batch(&storage, &api, jobs, &mut nonce, &network);
+        } else {
+            for job in jobs {
+                if !Self::submit_job(&storage, &api, job, &mut nonce, &network) {
+                    break;
+                }
+            }
+        }
+        Ok(Round::Again)
+    }
+
+    /// Submit a single payout.
+    ///
+    /// Returns `false` when the job has to be retried, after which the nonce
+    /// has to be synced with the chain again.
+    fn submit_job(
+        storage: &Storage,
+        api: &chain::ChainApi,
+        mut job: JobData,
+        nonce: &mut u32,
+        network: &NetworkData,
+    ) -> bool {
+        let dest = match chain::parse_address(&job.dest) {
+            Ok(dest) => dest,
+            Err(err) => {
+                Self::reject_job(storage, job, err.to_string());
+                return true;
+            }
+        };
+
+        // The nonce is stored before submitting so that a restart can't lead
+        // to a second payout.
+        job.nonce = Some(*nonce);
+        Self::save_job(storage, &job, network);
+
+        let submitted = match job.sweep {
+            true => chain::sweep(api, dest, *nonce).map(|(inclusion, swept)| {
+                job.amount = swept;
+                inclusion
+            }),
+            false => chain::transfer_keep_alive(api, dest, job.amount, *nonce),
+        };
+        match submitted {
+            Ok(inclusion) => {
+                *nonce += 1;
+                Self::submitted(storage, job, inclusion, network);
+                true
+            }
+            // The extrinsic was included, but the transfer failed.
+            Err(chain::ChainError::Dispatch(err)) => {
+                *nonce += 1;
+                Self::reject_job(storage, job, err);
+                true
+            }
+            Err(err) => {
+                Self::retry_job(storage, job, network, err.to_string());
+                false
+            }
+        }
+    }
+
+    /// Submit several payouts in a single `Utility::batch_all`.
+    ///
+    /// When the batch fails as a whole, for instance because one of the
+    /// transfers can't be made, its payouts are submitted one by one instead
+    /// so that the others still get paid.
+    fn submit_batch(
+        storage: &Storage,
+        api: &chain::ChainApi,
+        jobs: Vec<JobData>,
+        nonce: &mut u32,
+        network: &NetworkData,
+    ) {
+        let mut batch = Vec::new();
+        for job in jobs {
+            match chain::parse_address(&job.dest) {
+                Ok(dest) => batch.push((job, dest)),
+                Err(err) => Self::fail_job(storage, job, err.to_string()),
+            }
+        }
+        if batch.len() < 2 {
+            for (job, _) in batch {
+                Self::submit_job(storage, api, job, nonce, network);
+            }
+            return;
+        }
+
+        // The nonce is stored before submitting so that a restart can't lead
+        // to a second payout.
+        for (job, _) in &mut batch {
+            job.nonce = Some(*nonce);
+            Self::save_job(storage, job, network);
+        }
+
+        let transfers: Vec<_> = batch
+            .iter()
+            .map(|(job, dest)| (dest.clone(), job.amount))
+            .collect();
+        match chain::batch_transfer_keep_alive(api, &transfers, *nonce) {
+            Ok((inclusion, mut transferred)) => {
+                *nonce += 1;
+                for (job, dest) in batch {
+                    match transferred
+                        .iter()
+                        .position(|transfer| *transfer == (dest.clone(), job.amount))
+                    {
+                        Some(index) => {
+                            transferred.remove(index);
+                            Self::submitted(storage, job, inclusion.clone(), network);
+                        }
+                        None => Self::fail_job(
+                            storage,
+                            job,
+                            "the transfer is missing from the events of the batch".into(),
+                        ),
+                    }
+                }
+            }
+            // The batch was included, but reverted. None of its transfers
+            // were made, so they can safely be sent again.
+            Err(chain::ChainError::Dispatch(err)) => {
+                *nonce += 1;
+                log::warn!(
+                    "Batch of {} payouts failed ({}), sending them one by one",
+                    batch.len(),
+                    err
+                );
+                let mut jobs = batch.into_iter().map(|(mut job, _)| {
+                    job.nonce = None;
+                    job
+                });
+                for job in jobs.by_ref() {
+                    if !Self::submit_job(storage, api, job, nonce, network) {
+                        break;
+                    }
+                }
+                for job in jobs {
+                    Self::save_job(storage, &job, network);
+                }
+            }
+            Err(err) => {
+                for (job, _) in batch {
+                    Self::retry_job(storage, job, network, err.to_string());
+                }
+            }
+        }
+    }
+
+    /// Mark a job as included in a block and wait for its finality in the
+    /// background.
+    fn submitted(
+        storage: &Storage,
+        mut job: JobData,
+        inclusion: chain::Inclusion,
+        network: &NetworkData,
+    ) {
+        if job.withdrawal.is_some() {
+            Self::charge_withdrawal_fee(storage, &job, inclusion.fee);
+        }
+        job.state = JobState::Submitted;
+        job.error = None;
+        job.hash = Some(inclusion.hash);
+        job.block_hash = Some(inclusion.block_hash);
+        job.block = Some(inclusion.block);
+        Self::save_job(storage, &job, network);
+
+        lock(&WORKERS).insert(job.id.clone(), false);
+        std::thread::spawn(move || Self::watch_finality(job));
+    }
+
+    /// Wait until the block of a submitted job is finalized.
+    fn watch_finality(mut job: JobData) {
+        let result = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))
+            .and_then(|storage| {
+                let network = Self::get_network(&storage, &job.network)?;
+                let inclusion = chain::Inclusion {
+                    hash: job.hash.clone().unwrap_or_default(),
+                    block_hash: job.block_hash.clone().unwrap_or_default(),
+                    block: job.block.unwrap_or_default(),
+                    ..Default::default()
+                };
+                match chain::connect_any(&network.rpc_urls)
+                    .and_then(|api| chain::wait_for_finality(&api, &inclusion))
+                {
+                    Ok(()) => {
+                        job.state = JobState::Finalized;
+                        if job.sweep {
+                            Self::credit_sweep(&storage, &job, &network);
+                        }
+                    }
+                    Err(err) => {
+                        log::warn!("Job {} wasn't finalized: {}", job.id, err);
+                        job.state = JobState::Failed;
+                        job.error = Some(err.to_string());
+                    }
+                }
+                Self::save_job(&storage, &job, &network);
+                Ok(())
+            });
+        if let Err(err) = result {
+            log::error!("Couldn't watch job {}: {}", job.id, err);
+        }
+        lock(&WORKERS).remove(&job.id);
+    }
+
+    /// Schedule a job for another attempt, or give up on it after too many
+    /// attempts.
+    ///
+    /// A job that was signed before is only given up on once its nonce is
+    /// known to be unused, by the next round of its worker.
+    fn retry_job(storage: &Storage, mut job: JobData, network: &NetworkData, err: String) {
+        job.attempts += 1;
+        log::warn!("Attempt {} of job {} failed: {}", job.attempts, job.id, err);
+        if job.attempts >= MAX_ATTEMPTS && job.nonce.is_none() {
+            Self::reject_job(storage, job, err);
+            return;
+        }
+        job.next_attempt = now() + retry_delay(job.attempts);
+        job.error = Some(err);
+        Self::save_job(storage, &job, network);
+    }
+
+    /// Mark a job as failed.
+    fn fail_job(storage: &Storage, mut job: JobData, err: String) {
+        log::warn!("Job {} failed: {}", job.id, err);
+        job.state = JobState::Failed;
+        job.error = Some(err);
+        match Self::get_network(storage, &job.network) {
+            Ok(network) => Self::save_job(storage, &job, &network),
+            Err(_) => {
+                if let Err(err) = storage.update_job(&job) {
+                    log::error!("Couldn't store job {}: {}", job.id, err);
+                }
+                lock(&TRACKERS).remove(&job.id);
+            }
+        }
+    }
+
+    /// Mark a job as failed when it's certain nothing was paid out.
+    ///
+    /// The amount of a withdrawal is given back to the user's ledger account
+    /// and the record of a drip is undone.
+    fn reject_job(storage: &Storage, job: JobData, err: String) {
+        if let Some(record) = &job.drip {
+            let undone = Origin::try_from(job.channel.clone())
+                .map_err(|err| err.to_string())
+                .and_then(|channel| {
+                    storage
+                        .undo_drip(&channel, &job.network, record, job.amount, job.created)
+                        .map_err(|err| err.to_string())
+                });
+            if let Err(err) = undone {
+                log::error!("Couldn't undo the drip of job {}: {}", job.id, err);
+            }
+        }
+        if let Some(account) = &job.withdrawal {
+            let refund = LedgerEntryData {
+                id: format!("refund/{}", job.id),
+                network: job.network.clone(),
+                debit: HOT_WALLET_ACCOUNT.into(),
+                credit: account.clone(),
+                amount: job.amount,
+                created: now(),
+            };
+            match storage.post_ledger_entry(&refund) {
+                Ok(()) | Err(StorageError::NotUnique) => {}
+                Err(err) => log::error!("Couldn't refund job {}: {}", job.id, err),
+            }
+        }
+        Self::fail_job(storage, job, err);
+    }
+
+    /// Store the new state of a job and update the reply tracking it.
+    fn save_job(storage: &Storage, job: &JobData, network: &NetworkData) {
+        if let Err(err) = storage.update_job(job) {
+            log::error!("Couldn't store job {}: {}", job.id, err);
+        }
+
+        let mut trackers = lock(&TRACKERS);
+        if let Some(updates) = trackers.get(&job.id) {
+            let _ = updates.send(job_status_message(job, network));
+        }
+        // The reply won't change anymore.
+        if matches!(job.state, JobState::Finalized | JobState::Failed) {
+            trackers.remove(&job.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::retry_delay;
+
+    #[test]
+    fn retry_delay_backs_off() {
+        assert_eq!(retry_delay(1), 10);
+        assert_eq!(retry_delay(2), 20);
+        assert_eq!(retry_delay(4), 80);
+        assert_eq!(retry_delay(30), 600);
+    }
+}
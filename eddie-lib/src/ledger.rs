@@ -0,0 +1,440 @@
+use crate::{
+    bot::Bot,
+    call::Response,
+    chain,
+    config::Config,
+    crypto,
+    functions::now,
+    jobs::job_status_message,
+    notifications::notify,
+    origin::Origin,
+    storage::{
+        storage::{
+            user_account, DepositAccountData, JobData, LedgerEntryData, NetworkData, StorageError,
+            FEE_ACCOUNT, HOT_WALLET_ACCOUNT,
+        },
+        Storage,
+    },
+};
+use sp_core::{crypto::AccountId32, sr25519};
+use std::collections::BTreeMap;
+use support::traits::{dispatch::DispatchError, Get};
+
+/// Type to make function definitions a bit cleaner.
+type DispatchResult<T> = support::traits::dispatch::DispatchResult<Option<T>>;
+
+impl<T: Config> Bot<T> {
+    pub fn deposit(who: Origin, network: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::get_network(&storage, network)?;
+        let account = chain::account_of(&Self::deposit_pair(&who)?);
+        storage
+            .add_deposit_account(&DepositAccountData {
+                origin: who.to_string(),
+                network: network.name.clone(),
+                unswept: 0,
+            })
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let balance = Self::user_balance(&storage, &who, &network.name)?;
+
+        Ok(Some(Response::Reply(format!(
+            "Your deposit address on {} is {}, tokens sent to it are added to your balance within a few minutes.\n\nBalance: {}",
+            network.name,
+            network.chain.address_of(&account),
+            network.chain.format_balance(balance),
+        ))))
+    }
+
+    pub fn ledger_balance(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let identity = storage
+            .get_identity(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let balances = storage
+            .get_ledger_balances(&user_account(&identity))
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let mut lines = Vec::new();
+        for (network, balance) in balances {
+            if balance.balance() == 0 {
+                continue;
+            }
+            let amount = match storage.get_network(&network) {
+                Ok(network) => network.chain.format_balance(balance.balance()),
+                Err(_) => balance.balance().to_string(),
+            };
+            lines.push(format!("{}: {}", network, amount));
+        }
+        if lines.is_empty() {
+            return Ok(Some(Response::Reply(
+                "Your balance is empty. Send /deposit to get your deposit address.".into(),
+            )));
+        }
+        Ok(Some(Response::Reply(format!(
+            "Your balance:\n{}",
+            lines.join("\n")
+        ))))
+    }
+
+    pub fn withdraw(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        amount: &str,
+        target: Option<&str>,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::get_network(&storage, network)?;
+        let amount = network.chain.parse_balance(amount)?;
+        let address = Self::resolve_address(&storage, &who, target, Some(&network.name))?;
+        let dest = chain::parse_network_address(&address, network.chain.ss58_prefix)?;
+        let account = user_account(
+            &storage
+                .get_identity(&who)
+                .map_err(|err| DispatchError::Other(err.to_string()))?,
+        );
+
+        // The amount leaves the user's balance before the job is queued, and
+        // is given back if the transfer can't be made. The fee is charged
+        // once it's known.
+        let now = now();
+        let id = format!("withdraw/{}/{}/{}", who, now, crypto::random_hex(4));
+        storage
+            .post_ledger_entry(&LedgerEntryData {
+                id: id.clone(),
+                network: network.name.clone(),
+                debit: account.clone(),
+                credit: HOT_WALLET_ACCOUNT.into(),
+                amount,
+                created: now,
+            })
+            .map_err(|err| match err {
+                StorageError::InsufficientFunds => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "Your balance is too low for this withdrawal".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        let mut job = JobData::new(
+            id,
+            channel.to_string(),
+            network.name.clone(),
+            chain::to_address(&dest),
+            amount,
+            now,
+        );
+        job.withdrawal = Some(account);
+        let handle = Self::enqueue_job(&storage, &job)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        Self::run_jobs()?;
+
+        Ok(Some(Response::ReplyTracked(
+            job_status_message(&job, &network),
+            handle,
+        )))
+    }
+
+    pub fn reconcile(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        let networks = storage
+            .get_networks()
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let mut unswept: BTreeMap<String, u128> = BTreeMap::new();
+        for account in storage
+            .get_deposit_accounts()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            *unswept.entry(account.network).or_default() += account.unswept;
+        }
+        let hot_wallet = chain::account_of(&Self::hot_wallet_pair()?);
+
+        let mut lines = Vec::new();
+        for network in networks {
+            let owed = storage
+                .get_ledger_total(&network.name)
+                .map_err(|err| DispatchError::Other(err.to_string()))?;
+            let unswept = unswept.get(&network.name).copied().unwrap_or_default();
+            let fees = storage
+                .get_ledger_balance(&network.name, FEE_ACCOUNT)
+                .map_err(|err| DispatchError::Other(err.to_string()))?
+                .debits;
+            let held = match chain::connect_any(&network.rpc_urls)
+                .and_then(|api| chain::free_balance(&api, &hot_wallet))
+            {
+                Ok(balance) => balance + unswept,
+                Err(err) => {
+                    lines.push(format!(
+                        "{}: couldn't check the hot wallet: {}",
+                        network.name, err
+                    ));
+                    continue;
+                }
+            };
+
+            let chain_info = &network.chain;
+            let difference = match held >= owed {
+                true => format!("surplus of {}", chain_info.format_balance(held - owed)),
+                false => format!("DEFICIT of {}", chain_info.format_balance(owed - held)),
+            };
+            let mut line = format!(
+                "{}: users hold {}, the hot wallet {} has {} and {} is waiting to be swept, {}",
+                network.name,
+                chain_info.format_balance(owed),
+                chain_info.address_of(&hot_wallet),
+                chain_info.format_balance(held - unswept),
+                chain_info.format_balance(unswept),
+                difference,
+            );
+            if fees > 0 {
+                line.push_str(&format!(
+                    " ({} of withdrawal fees weren't covered by users)",
+                    chain_info.format_balance(fees)
+                ));
+            }
+            lines.push(line);
+        }
+
+        Ok(Some(Response::Reply(format!(
+            "Ledger reconciliation:\n{}",
+            lines.join("\n")
+        ))))
+    }
+
+    /// Move new deposits to the hot wallet, after which they're credited to
+    /// the balances of their users.
+    ///
+    /// Runs in the background. The sweeps are queued as jobs signed by the
+    /// deposit addresses, so they can't clash with the tips that were queued
+    /// for them before tips moved to the ledger.
+    pub fn watch_deposits() -> Result<(), DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let mut accounts: BTreeMap<String, Vec<DepositAccountData>> = BTreeMap::new();
+        for account in storage
+            .get_deposit_accounts()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            accounts
+                .entry(account.network.clone())
+                .or_default()
+                .push(account);
+        }
+        // Deposit addresses with a sweep on the way are left alone.
+        let sweeping: Vec<(String, String)> = storage
+            .get_open_jobs()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .into_iter()
+            .filter(|job| job.sweep)
+            .filter_map(|job| Some((job.network, job.tipper?)))
+            .collect();
+        let hot_wallet = chain::account_of(&Self::hot_wallet_pair()?);
+
+        let mut queued = false;
+        for (name, accounts) in accounts {
+            let network = match Self::get_network(&storage, &name) {
+                Ok(network) => network,
+                Err(err) => {
+                    log::warn!("Couldn't check the deposits on {}: {}", name, err);
+                    continue;
+                }
+            };
+            let api = match chain::connect_any(&network.rpc_urls) {
+                Ok(api) => api,
+                Err(err) => {
+                    log::warn!("Couldn't check the deposits on {}: {}", name, err);
+                    continue;
+                }
+            };
+            for account in accounts {
+                if sweeping.contains(&(name.clone(), account.origin.clone())) {
+                    continue;
+                }
+                match Self::queue_sweep(&storage, &api, &network, &hot_wallet, account) {
+                    Ok(sweep) => queued |= sweep,
+                    Err(err) => log::warn!("Couldn't check a deposit on {}: {}", name, err),
+                }
+            }
+        }
+
+        if queued {
+            Self::run_jobs()?;
+        }
+        Ok(())
+    }
+
+    /// Queue a sweep of a deposit address that holds funds.
+    ///
+    /// Returns whether a sweep was queued.
+    fn queue_sweep(
+        storage: &Storage,
+        api: &chain::ChainApi,
+        network: &NetworkData,
+        hot_wallet: &AccountId32,
+        mut account: DepositAccountData,
+    ) -> Result<bool, DispatchError> {
+        let origin = Origin::try_from(account.origin.clone())
+            .map_err(|err| DispatchError::Other(err.into()))?;
+        let address = chain::account_of(&Self::deposit_pair(&origin)?);
+        let balance = chain::free_balance(api, &address)?;
+        if balance == 0 {
+            // Funds credited before they were swept have left.
+            if account.unswept > 0 {
+                account.unswept = 0;
+                storage
+                    .update_deposit_account(&account)
+                    .map_err(|err| DispatchError::Other(err.to_string()))?;
+            }
+            return Ok(false);
+        }
+
+        // The key changes with every deposit and every sweep, so a sweep that
+        // failed isn't tried again until more funds arrive.
+        let nonce = chain::next_nonce(api, &address)?;
+        let mut job = JobData::new(
+            format!("sweep/{}/{}/{}/{}", network.name, origin, nonce, balance),
+            account.origin.clone(),
+            network.name.clone(),
+            chain::to_address(hot_wallet),
+            balance,
+            now(),
+        );
+        job.tipper = Some(account.origin);
+        job.sweep = true;
+        match storage.enqueue_job(&job) {
+            Ok(()) => Ok(true),
+            Err(StorageError::NotUnique) => Ok(false),
+            Err(err) => Err(DispatchError::Other(err.to_string())),
+        }
+    }
+
+    /// Credit what a final sweep moved to the hot wallet to the user of the
+    /// deposit address.
+    pub(crate) fn credit_sweep(storage: &Storage, job: &JobData, network: &NetworkData) {
+        let result = Origin::try_from(job.tipper.clone().unwrap_or_default())
+            .map_err(|err| DispatchError::Other(err.into()))
+            .and_then(|origin| {
+                let mut account = storage
+                    .get_deposit_account(&job.network, &origin)
+                    .map_err(|err| DispatchError::Other(err.to_string()))?;
+                // Deposits credited before they were swept only add what
+                // arrived on top.
+                let amount = job.amount.saturating_sub(account.unswept);
+                account.unswept = 0;
+                if amount == 0 {
+                    storage
+                        .update_deposit_account(&account)
+                        .map_err(|err| DispatchError::Other(err.to_string()))?;
+                    return Ok(());
+                }
+
+                let identity = storage
+                    .get_identity(&origin)
+                    .map_err(|err| DispatchError::Other(err.to_string()))?;
+                let entry = LedgerEntryData {
+                    id: format!("deposit/{}", job.id),
+                    network: job.network.clone(),
+                    debit: HOT_WALLET_ACCOUNT.into(),
+                    credit: user_account(&identity),
+                    amount,
+                    created: now(),
+                };
+                match storage.credit_deposit(&account, &entry) {
+                    Ok(()) => notify(Response::SayDirect(
+                        origin,
+                        format!(
+                            "Your deposit of {} on {} has arrived.",
+                            network.chain.format_balance(amount),
+                            network.name
+                        ),
+                    )),
+                    Err(StorageError::NotUnique) => {}
+                    Err(err) => return Err(DispatchError::Other(err.to_string())),
+                }
+                Ok(())
+            });
+        if let Err(err) = result {
+            log::error!("Couldn't credit sweep {}: {}", job.id, err);
+        }
+    }
+
+    /// Charge the fee of a withdrawal to the user, or to the fee account if
+    /// their balance doesn't cover it.
+    pub(crate) fn charge_withdrawal_fee(storage: &Storage, job: &JobData, fee: u128) {
+        let Some(account) = &job.withdrawal else {
+            return;
+        };
+        if fee == 0 {
+            return;
+        }
+        let mut entry = LedgerEntryData {
+            id: format!("fee/{}", job.id),
+            network: job.network.clone(),
+            debit: account.clone(),
+            credit: HOT_WALLET_ACCOUNT.into(),
+            amount: fee,
+            created: now(),
+        };
+        let mut result = storage.post_ledger_entry(&entry);
+        if let Err(StorageError::InsufficientFunds) = result {
+            entry.debit = FEE_ACCOUNT.into();
+            result = storage.post_ledger_entry(&entry);
+        }
+        match result {
+            Ok(()) | Err(StorageError::NotUnique) => {}
+            Err(err) => log::error!("Couldn't charge the fee of job {}: {}", job.id, err),
+        }
+    }
+
+    /// Get the ledger balance of a user on a network.
+    pub(crate) fn user_balance(
+        storage: &Storage,
+        who: &Origin,
+        network: &str,
+    ) -> Result<u128, DispatchError> {
+        let identity = storage
+            .get_identity(who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        Ok(storage
+            .get_ledger_balance(network, &user_account(&identity))
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .balance())
+    }
+
+    /// Get the key pair of a user's deposit address, derived from the bot's
+    /// wallet seed.
+    ///
+    /// It's the account users funded to tip before tips moved to the
+    /// ledger, so those funds are credited once the address is watched.
+    pub(crate) fn deposit_pair(who: &Origin) -> Result<sr25519::Pair, DispatchError> {
+        let master = chain::pair_from_seed(&T::WalletSeed::get())?;
+        Ok(chain::derive_pair(&master, &["tip", &who.to_string()])?)
+    }
+
+    /// Get the key pair of the hot wallet, which holds the deposits of all
+    /// users and pays out their withdrawals.
+    pub(crate) fn hot_wallet_pair() -> Result<sr25519::Pair, DispatchError> {
+        let master = chain::pair_from_seed(&T::WalletSeed::get())?;
+        Ok(chain::derive_pair(&master, &["hot_wallet"])?)
+    }
+}
@@ -1,9 +1,19 @@
+pub mod airdrop;
 pub mod bot;
 pub mod call;
+pub mod chain;
 pub mod config;
+pub mod crypto;
 pub mod functions;
+pub mod governance;
+pub mod jobs;
+pub mod ledger;
+pub mod notifications;
 pub mod origin;
+pub mod staking;
 pub mod storage;
+pub mod subscriptions;
+pub mod watches;
 
 pub use bot::Bot;
 pub use bot::MODULE;
@@ -15,12 +25,25 @@ mod tests {
     use crate::{call::Response, origin::Origin};
 
     use super::*;
-    use support::{param, traits::Dispatch};
+    use sp_core::Pair;
+    use support::{
+        param,
+        traits::{
+            get::{ConstU128, ConstU64},
+            Dispatch,
+        },
+    };
 
     struct Test;
 
     param!(Name, &'static str, "Eddie");
-    param!(WalletSeed, &'static str, "\\Alice");
+    param!(WalletSeed, &'static str, "//Alice");
+    param!(
+        SeedEncryptionKey,
+        &'static str,
+        "0x0102030405060708091011121314151617181920212223242526272829303132"
+    );
+    param!(PreviousSeedEncryptionKey, &'static str, "");
     param!(SubstrateRPC, &'static str, "ws://localhost:9944");
     param!(DBPath, &'static str, "/tmp/general_bot_test");
 
@@ -29,8 +52,12 @@ mod tests {
     impl Config for Test {
         type Name = Name;
         type WalletSeed = WalletSeed;
+        type SeedEncryptionKey = SeedEncryptionKey;
+        type PreviousSeedEncryptionKey = PreviousSeedEncryptionKey;
         type SubstrateRPC = SubstrateRPC;
         type DBPath = DBPath;
+        type DripAmount = ConstU128<1_000_000_000_000>;
+        type DripCooldown = ConstU64<{ 24 * 60 * 60 }>;
     }
 
     #[test]
@@ -42,4 +69,220 @@ mod tests {
             Some(Response::Reply("Eddie version 0.1.0\nCopyright (c) 2024, Arjan van Eersel\n\nMore information: https://github.com/arjanvaneersel/eddie-bot".into())),
         )
     }
+
+    #[test]
+    fn register_wallet_works() {
+        let who = Origin::Discord("5678".into());
+
+        assert!(Call::<Test>::RegisterWallet(
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ".into()
+        )
+        .dispatch(who.clone())
+        .is_err());
+
+        // Addresses are stored with the generic prefix.
+        Call::<Test>::RegisterWallet("15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5".into())
+            .dispatch(who.clone())
+            .unwrap();
+        assert_eq!(
+            Call::<Test>::MyWallet.dispatch(who).unwrap(),
+            Some(Response::Reply(
+                "Your registered wallet is 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY (not verified)."
+                    .into()
+            ))
+        );
+        assert!(Call::<Test>::MyWallet
+            .dispatch(Origin::Discord("8765".into()))
+            .is_err());
+    }
+
+    #[test]
+    fn labelled_wallets_work() {
+        let who = Origin::Telegram("6789".into());
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let bob = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        storage::Storage::new(DBPath::get())
+            .unwrap()
+            .user_wallets
+            .remove(&who.to_string())
+            .unwrap();
+
+        let add = |label: &str, address: &str| Call::<Test>::AddWallet {
+            label: label.into(),
+            address: address.into(),
+            network: None,
+        };
+        add("main", alice).dispatch(who.clone()).unwrap();
+        add("bob", bob).dispatch(who.clone()).unwrap();
+        assert!(add("Main", bob).dispatch(who.clone()).is_err());
+        assert!(add(bob, alice).dispatch(who.clone()).is_err());
+        assert!(add("other", "invalid").dispatch(who.clone()).is_err());
+
+        Call::<Test>::SetDefaultWallet("bob".into())
+            .dispatch(who.clone())
+            .unwrap();
+        assert_eq!(
+            Call::<Test>::ListWallets.dispatch(who.clone()).unwrap(),
+            Some(Response::Reply(format!(
+                "Your wallets:\nmain: {} (not verified)\nbob: {} (not verified) [default]",
+                alice, bob
+            )))
+        );
+
+        // Labels resolve to their wallet, anything else is taken as an
+        // address.
+        let storage = storage::Storage::new(DBPath::get()).unwrap();
+        let resolve = |target| Bot::<Test>::resolve_address(&storage, &who, target, None);
+        assert_eq!(resolve(Some("MAIN")).unwrap(), alice);
+        assert_eq!(resolve(Some("5Gother")).unwrap(), "5Gother");
+        assert_eq!(resolve(None).unwrap(), bob);
+
+        Call::<Test>::RemoveWallet("bob".into())
+            .dispatch(who.clone())
+            .unwrap();
+        assert!(Call::<Test>::RemoveWallet("bob".into())
+            .dispatch(who.clone())
+            .is_err());
+        assert_eq!(resolve(None).unwrap(), alice);
+    }
+
+    #[test]
+    fn balance_needs_a_network() {
+        let who = Origin::Telegram("7890".into());
+        storage::Storage::new(DBPath::get())
+            .unwrap()
+            .user_wallets
+            .remove(&who.to_string())
+            .unwrap();
+
+        let balance = |target: Option<&str>| Call::<Test>::Balance {
+            network: None,
+            target: target.map(String::from),
+        };
+        assert!(balance(None).dispatch(who.clone()).is_err());
+        Call::<Test>::RegisterWallet("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY".into())
+            .dispatch(who.clone())
+            .unwrap();
+
+        // Neither the wallet nor the address is registered on a network.
+        assert!(balance(None).dispatch(who.clone()).is_err());
+        assert!(balance(Some("default")).dispatch(who.clone()).is_err());
+        assert!(balance(Some("Telegram(7891)")).dispatch(who).is_err());
+    }
+
+    #[test]
+    fn rain_reaches_active_members() {
+        let channel = Origin::Discord("rain".into());
+        let member = Origin::Discord("2468".into());
+        Bot::<Test>::record_activity(&channel, &member);
+        Bot::<Test>::record_activity(&channel, &member);
+        assert_eq!(Bot::<Test>::active_members(&channel, 5), vec![member]);
+        assert!(Bot::<Test>::active_members(&Origin::Discord("other".into()), 5).is_empty());
+
+        let rain = |window| Call::<Test>::Rain {
+            channel: channel.clone(),
+            network: "rococo".into(),
+            total: "10".into(),
+            window,
+        };
+        // The window is checked before anything else.
+        for window in [0, airdrop::MAX_RAIN_WINDOW + 1] {
+            assert!(matches!(
+                rain(window).dispatch(Origin::Discord("1357".into())),
+                Err(support::traits::dispatch::DispatchError::Module(_, msg)) if msg.contains("window")
+            ));
+        }
+        // Only admins can make it rain.
+        assert!(matches!(
+            rain(5).dispatch(Origin::Discord("1357".into())),
+            Err(support::traits::dispatch::DispatchError::Module(_, msg)) if msg.contains("admin")
+        ));
+    }
+
+    #[test]
+    fn wallet_verification_works() {
+        let who = Origin::Telegram("5678".into());
+        let alice = chain::pair_from_seed("//Alice").unwrap();
+        storage::Storage::new(DBPath::get())
+            .unwrap()
+            .user_wallets
+            .remove(&who.to_string())
+            .unwrap();
+
+        assert!(Call::<Test>::StartWalletVerification(None)
+            .dispatch(who.clone())
+            .is_err());
+        Call::<Test>::RegisterWallet(chain::to_address(&chain::account_of(&alice)))
+            .dispatch(who.clone())
+            .unwrap();
+
+        let Ok(Some(Response::Reply(reply))) =
+            Call::<Test>::StartWalletVerification(None).dispatch(who.clone())
+        else {
+            panic!("Expected a reply");
+        };
+        let message = reply.lines().last().unwrap();
+
+        // A signature of another message is rejected.
+        let signature = hex::encode(alice.sign(b"other message"));
+        assert!(Call::<Test>::CompleteWalletVerification(signature)
+            .dispatch(who.clone())
+            .is_err());
+
+        let signature = hex::encode(alice.sign(format!("<Bytes>{}</Bytes>", message).as_bytes()));
+        Call::<Test>::CompleteWalletVerification(signature)
+            .dispatch(who.clone())
+            .unwrap();
+        assert_eq!(
+            Call::<Test>::MyWallet.dispatch(who).unwrap(),
+            Some(Response::Reply(
+                "Your registered wallet is 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY (verified).".into()
+            ))
+        );
+    }
+
+    #[test]
+    #[ignore = "requires a local dev node at ws://localhost:9944"]
+    fn drip_works() {
+        let channel = Origin::Telegram("-1001".into());
+        let bob = String::from("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+
+        let chain_info = chain::chain_info(&chain::connect(SubstrateRPC::get()).unwrap()).unwrap();
+        let storage = storage::Storage::new(DBPath::get()).unwrap();
+        let _ = storage.add_network(storage::storage::NetworkData {
+            name: "local".into(),
+            rpc_urls: vec![SubstrateRPC::get().into()],
+            chain: chain_info.clone(),
+            explorer_url: None,
+        });
+        let _ = storage.register_faucet_channel(&channel);
+        storage
+            .activate_faucet_channel(&channel, "local", chain_info)
+            .unwrap();
+        storage
+            .set_faucet_seed(
+                &channel,
+                "local",
+                crypto::encrypt_seed(SeedEncryptionKey::get(), "//Alice").unwrap(),
+            )
+            .unwrap();
+        drop(storage);
+
+        let result = Call::<Test>::Drip {
+            channel,
+            network: "local".into(),
+            address: Some(bob),
+        }
+        .dispatch(Origin::Telegram("1234".into()));
+        let Ok(Some(Response::ReplyTracked(msg, handle))) = result else {
+            panic!("Unexpected response: {:?}", result);
+        };
+        assert!(msg.contains("queued"));
+
+        let mut last = msg;
+        while let Some(update) = handle.next_update() {
+            last = update;
+        }
+        assert!(last.contains("finalized") && last.contains("0x"));
+    }
 }
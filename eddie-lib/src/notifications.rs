@@ -0,0 +1,36 @@
+use crate::{bot::Bot, call::Response, config::Config};
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Mutex, OnceLock,
+};
+
+/// Messages to users that don't answer their own command, like the DM to
+/// the recipient of a tip. They're delivered by the binary through the
+/// transport of their origin, so they can reach users on either platform.
+static OUTBOX: OnceLock<(Sender<Response>, Mutex<Receiver<Response>>)> = OnceLock::new();
+
+fn outbox() -> &'static (Sender<Response>, Mutex<Receiver<Response>>) {
+    OUTBOX.get_or_init(|| {
+        let (sender, receiver) = channel();
+        (sender, Mutex::new(receiver))
+    })
+}
+
+/// Queue a `SayChan` or `SayDirect` response for delivery.
+pub(crate) fn notify(response: Response) {
+    if outbox().0.send(response).is_err() {
+        log::error!("Couldn't queue a notification");
+    }
+}
+
+impl<T: Config> Bot<T> {
+    /// Wait for the next notification to deliver.
+    pub fn next_notification() -> Option<Response> {
+        outbox()
+            .1
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .recv()
+            .ok()
+    }
+}
@@ -40,8 +40,8 @@ impl support::traits::Origin for Origin {
 }
 
 fn parse_origin(input: &str) -> Option<Origin> {
-    let discord_regex = Regex::new(r#"Discord\((\w+)\)"#).unwrap();
-    let telegram_regex = Regex::new(r#"Telegram\((\w+)\)"#).unwrap();
+    let discord_regex = Regex::new(r#"Discord\((-?\w+)\)"#).unwrap();
+    let telegram_regex = Regex::new(r#"Telegram\((-?\w+)\)"#).unwrap();
 
     if let Some(captures) = discord_regex.captures(input) {
         let id = captures.get(1).unwrap().as_str().to_string();
@@ -105,6 +105,11 @@ mod tests {
             Origin::Telegram("4321".into())
         );
 
+        assert_eq!(
+            Origin::try_from("Telegram(-1004321)").unwrap(),
+            Origin::Telegram("-1004321".into())
+        );
+
         assert!(Origin::try_from("Blah(4321)").is_err());
     }
 }
@@ -0,0 +1,399 @@
+use crate::{
+    bot::Bot,
+    call::Response,
+    chain::{self, ChainApi},
+    config::Config,
+    crypto,
+    origin::Origin,
+    storage::{
+        storage::{NetworkData, StorageError, ValidatorAlertData},
+        Storage,
+    },
+};
+use sp_core::crypto::AccountId32;
+use std::collections::BTreeMap;
+use support::traits::{dispatch::DispatchError, Get};
+
+/// Type to make function definitions a bit cleaner.
+type DispatchResult<T> = support::traits::dispatch::DispatchResult<Option<T>>;
+
+/// Number of eras after which unclaimed payouts are reported by default.
+pub const DEFAULT_UNCLAIMED_ERAS: u32 = 7;
+
+/// Format a commission in parts per billion as a percentage.
+fn format_commission(commission: u32) -> String {
+    format!("{:.2}%", commission as f64 / 10_000_000.0)
+}
+
+impl<T: Config> Bot<T> {
+    pub fn validator(
+        who: Origin,
+        channel: &Origin,
+        network: Option<&str>,
+        target: &str,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::channel_network(&storage, channel, network, "Staking")?;
+        let stash = Self::staking_account(&storage, &who, &network, target)?;
+        let api = chain::connect_any(&network.rpc_urls)?;
+        let chain_info = &network.chain;
+
+        let era = chain::active_era(&api)?;
+        let prefs = chain::validator_prefs(&api, &stash)?;
+        let active = chain::session_validators(&api)?.contains(&stash);
+        let exposure = chain::era_exposure(&api, era, &stash)?;
+        if prefs.is_none() && exposure.is_none() {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!(
+                    "{} isn't a validator on {}",
+                    chain_info.address_of(&stash),
+                    network.name
+                ),
+            ));
+        }
+
+        let mut lines = vec![format!(
+            "[{}] Validator {}",
+            network.name,
+            chain_info.address_of(&stash)
+        )];
+        lines.push(match (active, &prefs) {
+            (true, Some(_)) => format!("Status: active in era {}", era),
+            (true, None) => format!("Status: active in era {}, but chilled", era),
+            (false, Some(_)) => "Status: waiting".into(),
+            (false, None) => "Status: chilled".into(),
+        });
+        if let Some(prefs) = prefs {
+            lines.push(format!(
+                "Commission: {}{}",
+                format_commission(prefs.commission),
+                if prefs.blocked {
+                    ", not accepting nominations"
+                } else {
+                    ""
+                }
+            ));
+        }
+        if let Some(ledger) = chain::staking_ledger(&api, &stash)? {
+            lines.push(format!(
+                "Self-stake: {} bonded",
+                chain_info.format_balance(ledger.active)
+            ));
+        }
+        match exposure {
+            Some(exposure) => {
+                let points = chain::era_reward_points(&api, era)?;
+                lines.push(format!(
+                    "Nominators: {} backing {} of {} in total",
+                    exposure.nominator_count,
+                    chain_info.format_balance(exposure.total - exposure.own),
+                    chain_info.format_balance(exposure.total)
+                ));
+                lines.push(format!(
+                    "Era points: {} of {} in era {}",
+                    points.individual.get(&stash).copied().unwrap_or_default(),
+                    points.total,
+                    era
+                ));
+            }
+            None => lines.push(format!("Not backed in era {}", era)),
+        }
+
+        Ok(Some(Response::Reply(lines.join("\n"))))
+    }
+
+    pub fn nominations(
+        who: Origin,
+        channel: &Origin,
+        network: Option<&str>,
+        target: &str,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::channel_network(&storage, channel, network, "Staking")?;
+        let stash = Self::staking_account(&storage, &who, &network, target)?;
+        let api = chain::connect_any(&network.rpc_urls)?;
+        let chain_info = &network.chain;
+
+        let Some(nominations) = chain::nominations(&api, &stash)? else {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                format!(
+                    "{} doesn't nominate on {}",
+                    chain_info.address_of(&stash),
+                    network.name
+                ),
+            ));
+        };
+        let active_set = chain::session_validators(&api)?;
+        let era = chain::active_era(&api)?;
+
+        let mut active = 0;
+        let mut lines = Vec::new();
+        for target in &nominations.targets {
+            let status = match (
+                active_set.contains(target),
+                chain::validator_prefs(&api, target)?.is_some(),
+            ) {
+                (true, _) => {
+                    active += 1;
+                    "active"
+                }
+                (false, true) => "waiting",
+                (false, false) => "not validating",
+            };
+            lines.push(format!("{}: {}", chain_info.address_of(target), status));
+        }
+
+        Ok(Some(Response::Reply(format!(
+            "[{}] Nominations of {}, made in era {}:\n{}\n\n{} of {} targets are active in era {}.",
+            network.name,
+            chain_info.address_of(&stash),
+            nominations.submitted_in,
+            lines.join("\n"),
+            active,
+            nominations.targets.len(),
+            era
+        ))))
+    }
+
+    pub fn monitor_validator(
+        who: Origin,
+        network: &str,
+        target: &str,
+        unclaimed_eras: Option<u32>,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::get_network(&storage, network)?;
+        let stash = Self::staking_account(&storage, &who, &network, target)?;
+        let unclaimed_eras = unclaimed_eras.unwrap_or(DEFAULT_UNCLAIMED_ERAS);
+        if unclaimed_eras == 0 {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "The number of eras has to be at least 1".into(),
+            ));
+        }
+
+        let alert = ValidatorAlertData {
+            id: crypto::random_hex(4),
+            origin: who.to_string(),
+            network: network.name.clone(),
+            stash: chain::to_address(&stash),
+            unclaimed_eras,
+            active: None,
+            validating: None,
+            unclaimed_reported: None,
+        };
+        storage
+            .add_validator_alert(&alert)
+            .map_err(|err| match err {
+                StorageError::NotUnique => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "You already monitor this validator".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        Ok(Some(Response::Reply(format!(
+            "You'll get a message when {} leaves the active set on {}, gets chilled or has payouts unclaimed for {} eras. Stop with id {}.",
+            network.chain.address_of(&stash),
+            network.name,
+            unclaimed_eras,
+            alert.id
+        ))))
+    }
+
+    pub fn unmonitor_validator(who: Origin, id: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        storage
+            .remove_validator_alert(&who, id.trim())
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    format!("You don't monitor a validator with id {}", id),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+        Ok(Some(Response::Reply(format!("Stopped monitoring {}.", id))))
+    }
+
+    pub fn list_monitored_validators(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let alerts = storage
+            .get_validator_alerts(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if alerts.is_empty() {
+            return Ok(Some(Response::Reply(
+                "You don't monitor any validator.".into(),
+            )));
+        }
+
+        let lines: Vec<String> = alerts
+            .iter()
+            .map(|alert| {
+                format!(
+                    "{}: {} on {}, payouts unclaimed for {} eras",
+                    alert.id, alert.stash, alert.network, alert.unclaimed_eras
+                )
+            })
+            .collect();
+        Ok(Some(Response::Reply(format!(
+            "Validators you monitor:\n{}",
+            lines.join("\n")
+        ))))
+    }
+
+    /// Alert users about the validators they monitor that left the active
+    /// set, got chilled or have old unclaimed payouts.
+    ///
+    /// Runs in the background. Each change is reported once.
+    pub fn check_validators() -> Result<Vec<Response>, DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let mut alerts: BTreeMap<String, Vec<ValidatorAlertData>> = BTreeMap::new();
+        for alert in storage
+            .get_all_validator_alerts()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            alerts.entry(alert.network.clone()).or_default().push(alert);
+        }
+
+        let mut responses = Vec::new();
+        for (name, alerts) in alerts {
+            let network = match Self::get_network(&storage, &name) {
+                Ok(network) => network,
+                Err(err) => {
+                    log::warn!("Couldn't check the validators on {}: {}", name, err);
+                    continue;
+                }
+            };
+            let api = match chain::connect_any(&network.rpc_urls) {
+                Ok(api) => api,
+                Err(err) => {
+                    log::warn!("Couldn't check the validators on {}: {}", name, err);
+                    continue;
+                }
+            };
+            let active_set = match chain::session_validators(&api) {
+                Ok(active_set) => active_set,
+                Err(err) => {
+                    log::warn!("Couldn't get the active set of {}: {}", name, err);
+                    continue;
+                }
+            };
+            for alert in alerts {
+                match Self::check_validator(&storage, &api, &network, &active_set, alert) {
+                    Ok(messages) => responses.extend(messages),
+                    Err(err) => log::warn!("Couldn't check a validator on {}: {}", name, err),
+                }
+            }
+        }
+        Ok(responses)
+    }
+
+    /// Compare a validator with what was known about it, and alert its
+    /// owner about the changes.
+    fn check_validator(
+        storage: &Storage,
+        api: &ChainApi,
+        network: &NetworkData,
+        active_set: &[AccountId32],
+        mut alert: ValidatorAlertData,
+    ) -> Result<Vec<Response>, DispatchError> {
+        let origin = Origin::try_from(alert.origin.clone())
+            .map_err(|err| DispatchError::Other(err.into()))?;
+        let stash = chain::parse_address(&alert.stash)?;
+        let name = network.chain.address_of(&stash);
+
+        let mut messages = Vec::new();
+        let active = active_set.contains(&stash);
+        if alert.active == Some(true) && !active {
+            messages.push(format!(
+                "Your validator {} dropped out of the active set on {}.",
+                name, network.name
+            ));
+        }
+        let validating = chain::validator_prefs(api, &stash)?.is_some();
+        if alert.validating == Some(true) && !validating {
+            messages.push(format!(
+                "Your validator {} was chilled on {} and stopped validating.",
+                name, network.name
+            ));
+        }
+
+        // Payouts can only be claimed for the last eras, so only those are
+        // checked, up to the one that's old enough to report.
+        let era = chain::active_era(api)?;
+        let oldest = era.saturating_sub(chain::history_depth(api));
+        let newest = era.saturating_sub(alert.unclaimed_eras);
+        let unclaimed = chain::unclaimed_eras(api, &stash, oldest..newest + 1)?;
+        match unclaimed.first() {
+            Some(first)
+                if alert
+                    .unclaimed_reported
+                    .is_none_or(|reported| reported < *first) =>
+            {
+                messages.push(format!(
+                    "Your validator {} has unclaimed payouts on {} for era(s) {}, they expire after era {}.",
+                    name,
+                    network.name,
+                    unclaimed
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    first + chain::history_depth(api)
+                ));
+                alert.unclaimed_reported = Some(*first);
+            }
+            Some(_) => {}
+            None => alert.unclaimed_reported = None,
+        }
+
+        alert.active = Some(active);
+        alert.validating = Some(validating);
+        storage
+            .update_validator_alert(&alert)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let mut responses = Vec::new();
+        for msg in messages {
+            if let Some(response) = Self::direct_message(storage, &origin, msg)
+                .map_err(|err| DispatchError::Other(err.to_string()))?
+            {
+                responses.push(response);
+            }
+        }
+        Ok(responses)
+    }
+
+    /// Get the account a staking command refers to, by wallet label or
+    /// address.
+    fn staking_account(
+        storage: &Storage,
+        who: &Origin,
+        network: &NetworkData,
+        target: &str,
+    ) -> Result<AccountId32, DispatchError> {
+        let address = Self::resolve_address(storage, who, Some(target), Some(&network.name))?;
+        Ok(chain::parse_network_address(
+            &address,
+            network.chain.ss58_prefix,
+        )?)
+    }
+}
@@ -3,12 +3,15 @@ use crate::origin::Origin;
 
 impl<'a> Storage<'a> {
     /// Set a user's admin status
+    ///
+    /// The status applies to all origins linked to the user's identity.
     pub fn set_admin(&self, origin: Origin, remove: bool) -> Result<(), StorageError> {
+        let identity = self.get_identity(&origin)?;
         if !remove {
             // Store the admin
-            self.admins.set(&origin.to_string(), &Nil)?;
+            self.admins.set(&identity, &Nil)?;
         } else {
-            self.admins.remove(&origin.to_string())?;
+            self.admins.remove(&identity)?;
         }
 
         Ok(())
@@ -16,12 +19,24 @@ impl<'a> Storage<'a> {
 
     /// Get the public address from a user origin.
     pub fn is_admin(&self, origin: Origin) -> bool {
-        match self.admins.get(&origin.to_string()) {
+        let Ok(identity) = self.get_identity(&origin) else {
+            return false;
+        };
+        match self.admins.get(&identity) {
             Err(_) => false,
             Ok(v) => v.is_some(),
         }
     }
 
+    /// Get the origins of all admins.
+    pub fn get_admins(&self) -> Result<Vec<Origin>, StorageError> {
+        let mut admins = Vec::new();
+        for item in self.admins.iter() {
+            admins.extend(self.get_identity_origins(&item?.key::<String>()?)?);
+        }
+        Ok(admins)
+    }
+
     /// Get whether there are admins appointed.
     pub fn has_admins(&self) -> bool {
         self.admins.iter().count() > 0
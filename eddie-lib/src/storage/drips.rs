@@ -0,0 +1,234 @@
+use kv::Json;
+
+use super::storage::{DripData, DripRecordData, Storage, StorageError};
+use crate::origin::Origin;
+
+/// Get the drips key of the totals of the faucet of a channel on a network.
+fn faucet_key(channel: &Origin, network: &str) -> String {
+    format!("{}/{}", channel, network)
+}
+
+/// Get the drips key of a beneficiary of the faucet of a channel on a
+/// network.
+fn drip_key(channel: &Origin, network: &str, beneficiary: &str) -> String {
+    format!("{}/{}", faucet_key(channel, network), beneficiary)
+}
+
+/// Add the drips of a beneficiary on `day` to the drips of the others.
+///
+/// Linked accounts and addresses each keep their own drips, so the highest
+/// of them is what counts.
+fn combine(combined: &mut Option<DripData>, data: &DripData, day: u64) {
+    let (drips, amount) = data.usage_on(day);
+    let combined = combined.get_or_insert(DripData {
+        day,
+        ..Default::default()
+    });
+    combined.last_drip = combined.last_drip.max(data.last_drip);
+    combined.drips = combined.drips.max(drips);
+    combined.amount = combined.amount.max(amount);
+}
+
+impl<'a> Storage<'a> {
+    /// Get the drips keys of all beneficiaries of a drip.
+    ///
+    /// Origins are resolved to their identity, so that linked accounts share
+    /// their drips.
+    fn drip_keys(
+        &self,
+        channel: &Origin,
+        network: &str,
+        origins: &[Origin],
+        pub_addr: &str,
+    ) -> Result<Vec<String>, StorageError> {
+        let mut keys = Vec::new();
+        for origin in origins {
+            let key = drip_key(channel, network, &self.get_identity(origin)?);
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys.push(drip_key(channel, network, pub_addr));
+        Ok(keys)
+    }
+
+    /// Record a drip of the faucet of a channel on a network to the given
+    /// origins and address.
+    ///
+    /// The drip is also added to the totals of the faucet.
+    pub fn record_drip(
+        &self,
+        channel: &Origin,
+        network: &str,
+        origins: &[Origin],
+        pub_addr: &str,
+        amount: u128,
+        timestamp: u64,
+    ) -> Result<(), StorageError> {
+        self.reserve_drip(channel, network, origins, pub_addr, timestamp, |_, _| {
+            Ok::<_, StorageError>(amount)
+        })??;
+        Ok(())
+    }
+
+    /// Record a drip of the faucet of a channel on a network, if `check`
+    /// accepts it.
+    ///
+    /// `check` gets the combined drips of the beneficiaries and the number of
+    /// drips and amount paid out by the faucet on the day of `timestamp`, and
+    /// returns the amount of the drip. Checking and recording happen in a
+    /// single transaction, so that concurrent requests can't all pass the
+    /// limits before any of them is recorded.
+    ///
+    /// Returns the combined drips before this one. The drip is undone with
+    /// `undo_drip` if its payout fails.
+    pub fn reserve_drip<E>(
+        &self,
+        channel: &Origin,
+        network: &str,
+        origins: &[Origin],
+        pub_addr: &str,
+        timestamp: u64,
+        check: impl Fn(Option<&DripData>, (u32, u128)) -> Result<u128, E>,
+    ) -> Result<Result<Option<DripData>, E>, StorageError> {
+        let day = DripData::day_of(timestamp);
+
+        let keys = self.drip_keys(channel, network, origins, pub_addr)?;
+        let faucet_key = faucet_key(channel, network);
+        Ok(self.drips.transaction(|drips| {
+            let mut combined = None;
+            for key in &keys {
+                if let Some(Json(data)) = drips.get(key)? {
+                    combine(&mut combined, &data, day);
+                }
+            }
+            let usage = drips
+                .get(&faucet_key)?
+                .map(|Json(data)| data.usage_on(day))
+                .unwrap_or_default();
+            let amount = match check(combined.as_ref(), usage) {
+                Ok(amount) => amount,
+                Err(err) => return Ok(Err(err)),
+            };
+
+            for key in keys.iter().chain([&faucet_key]) {
+                let data = drips.get(key)?.map(|data| data.0).unwrap_or_default();
+                let (drips_on_day, paid) = data.usage_on(day);
+                let data = Json(DripData {
+                    last_drip: timestamp,
+                    day,
+                    drips: drips_on_day + 1,
+                    amount: paid.saturating_add(amount),
+                });
+                drips.set(key, &data)?;
+            }
+            Ok(Ok(combined))
+        })?)
+    }
+
+    /// Undo the record of a drip made at `timestamp` whose payout failed.
+    ///
+    /// The beneficiaries get their drip before back, so that they can request
+    /// again right away.
+    pub fn undo_drip(
+        &self,
+        channel: &Origin,
+        network: &str,
+        record: &DripRecordData,
+        amount: u128,
+        timestamp: u64,
+    ) -> Result<(), StorageError> {
+        let day = DripData::day_of(timestamp);
+
+        let origins: Vec<Origin> = record
+            .origins
+            .iter()
+            .filter_map(|origin| Origin::try_from(origin.clone()).ok())
+            .collect();
+        let keys = self.drip_keys(channel, network, &origins, &record.address)?;
+        let faucet_key = faucet_key(channel, network);
+        for key in keys.iter().chain([&faucet_key]) {
+            let Some(Json(mut data)) = self.drips.get(key)? else {
+                continue;
+            };
+            if *key != faucet_key {
+                // The beneficiary got another drip since.
+                if data.last_drip != timestamp {
+                    continue;
+                }
+                data.last_drip = record.previous;
+            }
+            if data.day == day {
+                data.drips = data.drips.saturating_sub(1);
+                data.amount = data.amount.saturating_sub(amount);
+            }
+            self.drips.set(key, &Json(data))?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the combined drips of the faucet of a channel on a network to any
+    /// of the given origins or the address on the day of `timestamp`.
+    ///
+    /// Returns `None` if none of them ever received a drip from the faucet.
+    pub fn get_drips(
+        &self,
+        channel: &Origin,
+        network: &str,
+        origins: &[Origin],
+        pub_addr: &str,
+        timestamp: u64,
+    ) -> Result<Option<DripData>, StorageError> {
+        let day = DripData::day_of(timestamp);
+
+        let mut combined = None;
+        for key in self.drip_keys(channel, network, origins, pub_addr)? {
+            if let Some(Json(data)) = self.drips.get(&key)? {
+                combine(&mut combined, &data, day);
+            }
+        }
+
+        Ok(combined)
+    }
+
+    /// Get the number of drips and the amount paid out by the faucet of a
+    /// channel on a network on the day of `timestamp`.
+    pub fn get_faucet_usage(
+        &self,
+        channel: &Origin,
+        network: &str,
+        timestamp: u64,
+    ) -> Result<(u32, u128), StorageError> {
+        Ok(self
+            .drips
+            .get(&faucet_key(channel, network))?
+            .map(|data| data.0.usage_on(DripData::day_of(timestamp)))
+            .unwrap_or_default())
+    }
+
+    /// Move the drips of a beneficiary to another one, on all faucets.
+    pub fn merge_drips(&self, source: &str, target: &str) -> Result<(), StorageError> {
+        let suffix = format!("/{}", source);
+        let mut keys = Vec::new();
+        for item in self.drips.iter() {
+            let key = item?.key::<String>()?;
+            if key.ends_with(&suffix) {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            let Some(Json(data)) = self.drips.remove(&key)? else {
+                continue;
+            };
+            let target_key = format!("{}/{}", &key[..key.len() - suffix.len()], target);
+            let data = match self.drips.get(&target_key)? {
+                Some(Json(current)) => current.merge(&data),
+                None => data,
+            };
+            self.drips.set(&target_key, &Json(data))?;
+        }
+        Ok(())
+    }
+}
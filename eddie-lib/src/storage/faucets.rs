@@ -1,7 +1,10 @@
 use kv::Json;
 
-use super::storage::{FaucetData, Storage, StorageError};
-use crate::origin::Origin;
+use super::storage::{
+    FaucetChannelData, FaucetData, FaucetPolicy, FaucetSetting, NetworkData, Storage, StorageError,
+    UnboundFaucetData,
+};
+use crate::{chain::ChainInfo, crypto::StoredSeed, origin::Origin};
 
 impl<'a> Storage<'a> {
     pub fn register_faucet_channel(&self, channel: &Origin) -> Result<(), StorageError> {
@@ -10,34 +13,247 @@ impl<'a> Storage<'a> {
             return Err(StorageError::NotUnique);
         }
 
-        // Store the channel with empty values.
-        let data = Json(FaucetData {
-            rpc_url: String::new(),
-            wallet_seed: String::new(),
-        });
+        // Store the channel without any faucets.
+        let data = Json(FaucetChannelData::default());
         self.faucets.set(&channel.to_string(), &data)?;
 
         Ok(())
     }
 
+    /// Activate the faucet of a channel on a network.
+    ///
+    /// The wallet seed and policy are kept when the faucet was activated
+    /// before.
     pub fn activate_faucet_channel(
         &self,
         channel: &Origin,
-        rpc_url: &String,
-        wallet_seed: &String,
+        network: &str,
+        chain: ChainInfo,
     ) -> Result<(), StorageError> {
         // Return an error is the channel is not registered.
-        if self.faucets.get(&channel.to_string())?.is_none() {
-            return Err(StorageError::NotUnique);
+        let Some(Json(mut data)) = self.faucets.get(&channel.to_string())? else {
+            return Err(StorageError::NotFound);
+        };
+
+        // Store the channel with the new values.
+        match data.networks.get_mut(network) {
+            Some(faucet) => faucet.chain = chain,
+            None => {
+                data.networks.insert(
+                    network.to_string(),
+                    FaucetData {
+                        wallet_seed: StoredSeed::default(),
+                        policy: FaucetPolicy::default(),
+                        chain,
+                        low_balance: false,
+                    },
+                );
+            }
         }
+        self.faucets.set(&channel.to_string(), &Json(data))?;
 
-        // Store the channel with empty values.
-        let data = Json(FaucetData {
-            rpc_url: rpc_url.clone(),
-            wallet_seed: wallet_seed.clone(),
-        });
-        self.faucets.set(&channel.to_string(), &data)?;
+        Ok(())
+    }
+
+    /// Change a setting of the policy of the faucet of a channel on a network.
+    ///
+    /// Returns the updated policy.
+    pub fn configure_faucet_channel(
+        &self,
+        channel: &Origin,
+        network: &str,
+        setting: FaucetSetting,
+    ) -> Result<FaucetPolicy, StorageError> {
+        let Json(mut data) = self
+            .faucets
+            .get(&channel.to_string())?
+            .ok_or(StorageError::NotFound)?;
+        let faucet = data
+            .networks
+            .get_mut(network)
+            .ok_or(StorageError::NotFound)?;
+
+        faucet.policy.apply(setting);
+        let policy = faucet.policy.clone();
+        self.faucets.set(&channel.to_string(), &Json(data))?;
+
+        Ok(policy)
+    }
+
+    /// Get a faucet channel and its faucets.
+    pub fn get_faucet_channel(&self, channel: &Origin) -> Result<FaucetChannelData, StorageError> {
+        Ok(self
+            .faucets
+            .get(&channel.to_string())?
+            .ok_or(StorageError::NotFound)?
+            .0)
+    }
+
+    /// Get the faucet of a channel on a network.
+    pub fn get_faucet(&self, channel: &Origin, network: &str) -> Result<FaucetData, StorageError> {
+        self.get_faucet_channel(channel)?
+            .networks
+            .remove(network)
+            .ok_or(StorageError::NotFound)
+    }
+
+    /// Replace the wallet seed of the faucet of a channel on a network.
+    pub fn set_faucet_seed(
+        &self,
+        channel: &Origin,
+        network: &str,
+        wallet_seed: StoredSeed,
+    ) -> Result<(), StorageError> {
+        let Json(mut data) = self
+            .faucets
+            .get(&channel.to_string())?
+            .ok_or(StorageError::NotFound)?;
+        let faucet = data
+            .networks
+            .get_mut(network)
+            .ok_or(StorageError::NotFound)?;
+
+        faucet.wallet_seed = wallet_seed;
+        self.faucets.set(&channel.to_string(), &Json(data))?;
 
         Ok(())
     }
+
+    /// Set whether a low balance alert has been sent for the faucet of a
+    /// channel on a network.
+    pub fn set_faucet_low_balance(
+        &self,
+        channel: &Origin,
+        network: &str,
+        low_balance: bool,
+    ) -> Result<(), StorageError> {
+        let Json(mut data) = self
+            .faucets
+            .get(&channel.to_string())?
+            .ok_or(StorageError::NotFound)?;
+        let faucet = data
+            .networks
+            .get_mut(network)
+            .ok_or(StorageError::NotFound)?;
+
+        faucet.low_balance = low_balance;
+        self.faucets.set(&channel.to_string(), &Json(data))?;
+
+        Ok(())
+    }
+
+    /// Replace the wallet seed of a faucet of a channel that isn't bound to a
+    /// network yet.
+    pub fn set_unbound_faucet_seed(
+        &self,
+        channel: &Origin,
+        index: usize,
+        wallet_seed: StoredSeed,
+    ) -> Result<(), StorageError> {
+        let Json(mut data) = self
+            .faucets
+            .get(&channel.to_string())?
+            .ok_or(StorageError::NotFound)?;
+        let faucet = data.unbound.get_mut(index).ok_or(StorageError::NotFound)?;
+
+        faucet.wallet_seed = wallet_seed;
+        self.faucets.set(&channel.to_string(), &Json(data))?;
+
+        Ok(())
+    }
+
+    /// Get all faucets that aren't bound to a network yet, with their channel
+    /// and their index in it.
+    pub fn get_unbound_faucets(
+        &self,
+    ) -> Result<Vec<(Origin, usize, UnboundFaucetData)>, StorageError> {
+        let mut faucets = Vec::new();
+        for item in self.faucets.iter() {
+            let item = item?;
+            let channel =
+                Origin::try_from(item.key::<String>()?).map_err(|_| StorageError::InvalidOrigin)?;
+            for (index, faucet) in item
+                .value::<Json<FaucetChannelData>>()?
+                .0
+                .unbound
+                .into_iter()
+                .enumerate()
+            {
+                faucets.push((channel.clone(), index, faucet));
+            }
+        }
+        Ok(faucets)
+    }
+
+    /// Bind the faucets stored by older versions of the bot to the registered
+    /// networks they paid out on.
+    ///
+    /// A faucet is bound to the network of its name, or else to the network
+    /// its RPC url is an endpoint of, as long as it's on the chain the faucet
+    /// was activated for. Faucets of channels that already run a faucet on
+    /// the network are left for an admin to sort out.
+    ///
+    /// Returns the number of bound faucets.
+    pub fn bind_unbound_faucets(&self, networks: &[NetworkData]) -> Result<usize, StorageError> {
+        let mut channels = Vec::new();
+        for item in self.faucets.iter() {
+            let item = item?;
+            let Json(data) = item.value::<Json<FaucetChannelData>>()?;
+            if !data.unbound.is_empty() {
+                channels.push((item.key::<String>()?, data));
+            }
+        }
+
+        let mut bound = 0;
+        for (channel, mut data) in channels {
+            let mut unbound = Vec::new();
+            for faucet in std::mem::take(&mut data.unbound) {
+                let network = networks.iter().find(|network| {
+                    let matches = match (&faucet.network, &faucet.rpc_url) {
+                        (Some(name), _) => network.name.eq_ignore_ascii_case(name),
+                        (None, Some(rpc_url)) => network.rpc_urls.contains(rpc_url),
+                        (None, None) => false,
+                    };
+                    matches
+                        && faucet
+                            .chain
+                            .as_ref()
+                            .is_none_or(|chain| chain.genesis_hash == network.chain.genesis_hash)
+                });
+                match network {
+                    Some(network) if !data.networks.contains_key(&network.name) => {
+                        data.networks.insert(
+                            network.name.clone(),
+                            FaucetData {
+                                wallet_seed: faucet.wallet_seed,
+                                policy: faucet.policy,
+                                chain: network.chain.clone(),
+                                low_balance: false,
+                            },
+                        );
+                        bound += 1;
+                    }
+                    _ => unbound.push(faucet),
+                }
+            }
+            data.unbound = unbound;
+            self.faucets.set(&channel, &Json(data))?;
+        }
+
+        Ok(bound)
+    }
+
+    /// Get all faucets with their channel and network.
+    pub fn get_faucets(&self) -> Result<Vec<(Origin, String, FaucetData)>, StorageError> {
+        let mut faucets = Vec::new();
+        for item in self.faucets.iter() {
+            let item = item?;
+            let channel =
+                Origin::try_from(item.key::<String>()?).map_err(|_| StorageError::InvalidOrigin)?;
+            for (network, faucet) in item.value::<Json<FaucetChannelData>>()?.0.networks {
+                faucets.push((channel.clone(), network, faucet));
+            }
+        }
+        Ok(faucets)
+    }
 }
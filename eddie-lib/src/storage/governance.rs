@@ -0,0 +1,55 @@
+use kv::Json;
+
+use super::storage::{Storage, StorageError, VoteReminderData};
+
+impl<'a> Storage<'a> {
+    /// Start reminding a user of the referenda of a network.
+    ///
+    /// Returns an error if the user is already reminded on the network.
+    pub fn add_vote_reminder(&self, reminder: &VoteReminderData) -> Result<(), StorageError> {
+        if self.vote_reminders.get(&reminder.key())?.is_some() {
+            return Err(StorageError::NotUnique);
+        }
+        self.vote_reminders
+            .set(&reminder.key(), &Json(reminder.clone()))?;
+        Ok(())
+    }
+
+    /// Stop reminding a user of the referenda of a network.
+    pub fn remove_vote_reminder(&self, reminder: &VoteReminderData) -> Result<(), StorageError> {
+        self.vote_reminders
+            .remove(&reminder.key())?
+            .ok_or(StorageError::NotFound)?;
+        Ok(())
+    }
+
+    /// Update the referenda a user was reminded of.
+    pub fn update_vote_reminder(&self, reminder: &VoteReminderData) -> Result<(), StorageError> {
+        self.vote_reminders
+            .set(&reminder.key(), &Json(reminder.clone()))?;
+        Ok(())
+    }
+
+    /// Stop reminding users of the referenda of a network that's removed.
+    ///
+    /// Returns the number of users that were reminded on it.
+    pub fn remove_network_vote_reminders(&self, network: &str) -> Result<usize, StorageError> {
+        let mut removed = 0;
+        for reminder in self.get_vote_reminders()? {
+            if reminder.network.eq_ignore_ascii_case(network) {
+                self.vote_reminders.remove(&reminder.key())?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Get the users that get reminded to vote.
+    pub fn get_vote_reminders(&self) -> Result<Vec<VoteReminderData>, StorageError> {
+        let mut reminders = Vec::new();
+        for item in self.vote_reminders.iter() {
+            reminders.push(item?.value::<Json<VoteReminderData>>()?.0);
+        }
+        Ok(reminders)
+    }
+}
@@ -0,0 +1,140 @@
+use kv::Json;
+
+use super::storage::{LinkCodeData, Nil, Storage, StorageError, UserWalletsData};
+use crate::origin::Origin;
+
+impl<'a> Storage<'a> {
+    /// Get the identity of an origin.
+    ///
+    /// Origins that were never linked are an identity of their own, so data
+    /// of older versions of the bot, which is stored by origin, still
+    /// resolves.
+    pub fn get_identity(&self, origin: &Origin) -> Result<String, StorageError> {
+        Ok(self
+            .identities
+            .get(&origin.to_string())?
+            .unwrap_or(origin.to_string()))
+    }
+
+    /// Get all origins of an identity.
+    pub fn get_identity_origins(&self, identity: &str) -> Result<Vec<Origin>, StorageError> {
+        if let Ok(origin) = Origin::try_from(identity.to_string()) {
+            return Ok(vec![origin]);
+        }
+
+        let mut origins = Vec::new();
+        for item in self.identities.iter() {
+            let item = item?;
+            if item.value::<String>()? == identity {
+                let key = item.key::<String>()?;
+                origins.push(Origin::try_from(key).map_err(|_| StorageError::InvalidOrigin)?);
+            }
+        }
+        Ok(origins)
+    }
+
+    /// Store a one-time code to link another origin to the identity of an
+    /// origin.
+    pub fn set_link_code(&self, code: &str, link: LinkCodeData) -> Result<(), StorageError> {
+        self.link_codes.set(&code.to_string(), &Json(link))?;
+        Ok(())
+    }
+
+    /// Take the origin a link code was issued to, which can only be done
+    /// once.
+    ///
+    /// Expired codes are treated as missing.
+    pub fn take_link_code(&self, code: &str, now: u64) -> Result<Origin, StorageError> {
+        match self.link_codes.remove(&code.to_string())? {
+            Some(Json(link)) if link.expires > now => {
+                Origin::try_from(link.origin).map_err(|_| StorageError::InvalidOrigin)
+            }
+            _ => Err(StorageError::NotFound),
+        }
+    }
+
+    /// Link two origins to the same identity.
+    ///
+    /// When neither of them is linked yet, they're grouped under `new_id`.
+    /// Otherwise the existing identity is kept and the data of the other side
+    /// is merged into it. Returns the identity.
+    pub fn link_origins(
+        &self,
+        origin: &Origin,
+        other: &Origin,
+        new_id: &str,
+    ) -> Result<String, StorageError> {
+        let identity = self.get_identity(origin)?;
+        let other_identity = self.get_identity(other)?;
+        if identity == other_identity {
+            return Err(StorageError::NotUnique);
+        }
+
+        let target = if identity != origin.to_string() {
+            identity.clone()
+        } else if other_identity != other.to_string() {
+            other_identity.clone()
+        } else {
+            new_id.to_string()
+        };
+
+        for source in [identity, other_identity] {
+            if source == target {
+                continue;
+            }
+            for origin in self.get_identity_origins(&source)? {
+                self.identities.set(&origin.to_string(), &target)?;
+            }
+            self.merge_identity(&source, &target)?;
+        }
+
+        Ok(target)
+    }
+
+    /// Move the data of an identity to another one.
+    fn merge_identity(&self, source: &str, target: &str) -> Result<(), StorageError> {
+        let (source, target) = (source.to_string(), target.to_string());
+
+        if self.admins.remove(&source)?.is_some() {
+            self.admins.set(&target, &Nil)?;
+        }
+
+        // Add the source's wallets to the target's, keeping the target's
+        // default. Clashing labels get a numeric suffix.
+        if let Some(source_data) = self.user_wallets.get(&source)? {
+            let mut data: UserWalletsData = self.user_wallets.get(&target)?.unwrap_or_default();
+            for mut wallet in source_data.wallets {
+                if let Some(current) = data
+                    .wallets
+                    .iter_mut()
+                    .find(|w| w.address == wallet.address)
+                {
+                    current.verified |= wallet.verified;
+                    continue;
+                }
+                let label = wallet.label.clone();
+                let mut suffix = 2;
+                while data.get(&wallet.label).is_some() {
+                    wallet.label = format!("{label}-{suffix}");
+                    suffix += 1;
+                }
+                wallet.default = false;
+                data.wallets.push(wallet);
+            }
+            data.ensure_default();
+            self.write_user_wallets(&source, None)?;
+            self.write_user_wallets(&target, Some(&data))?;
+        }
+        self.challenges.remove(&source)?;
+
+        // The target's quiet hours win.
+        if let Some(quiet_hours) = self.quiet_hours.remove(&source)? {
+            if self.quiet_hours.get(&target)?.is_none() {
+                self.quiet_hours.set(&target, &quiet_hours)?;
+            }
+        }
+
+        self.merge_ledger(&source, &target)?;
+        self.merge_drips(&source, &target)
+    }
+}
@@ -0,0 +1,71 @@
+use kv::Json;
+
+use super::storage::{JobData, JobState, Storage, StorageError};
+
+impl<'a> Storage<'a> {
+    /// Add a job to the queue.
+    ///
+    /// Returns an error if a job with the same idempotency key was queued
+    /// before.
+    pub fn enqueue_job(&self, job: &JobData) -> Result<(), StorageError> {
+        match self
+            .jobs
+            .compare_and_swap(&job.id, None, Some(&Json(job.clone())))
+        {
+            Ok(()) => Ok(()),
+            Err(kv::Error::CompareAndSwap(_)) => Err(StorageError::NotUnique),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Queue a job again under the key of a job that failed.
+    ///
+    /// Fails with `StorageError::NotUnique` unless the job with the same
+    /// idempotency key failed.
+    pub fn requeue_job(&self, job: &JobData) -> Result<(), StorageError> {
+        let failed = self.get_job(&job.id).map_err(|err| match err {
+            StorageError::NotFound => StorageError::NotUnique,
+            err => err,
+        })?;
+        if failed.state != JobState::Failed {
+            return Err(StorageError::NotUnique);
+        }
+        match self
+            .jobs
+            .compare_and_swap(&job.id, Some(&Json(failed)), Some(&Json(job.clone())))
+        {
+            Ok(()) => Ok(()),
+            Err(kv::Error::CompareAndSwap(_)) => Err(StorageError::NotUnique),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get a job by its idempotency key.
+    pub fn get_job(&self, id: &str) -> Result<JobData, StorageError> {
+        Ok(self
+            .jobs
+            .get(&id.to_string())?
+            .ok_or(StorageError::NotFound)?
+            .0)
+    }
+
+    /// Store the new state of a job.
+    pub fn update_job(&self, job: &JobData) -> Result<(), StorageError> {
+        self.jobs.set(&job.id, &Json(job.clone()))?;
+        Ok(())
+    }
+
+    /// Get all jobs that are still pending or waiting for finality, oldest
+    /// first.
+    pub fn get_open_jobs(&self) -> Result<Vec<JobData>, StorageError> {
+        let mut jobs = Vec::new();
+        for item in self.jobs.iter() {
+            let job = item?.value::<Json<JobData>>()?.0;
+            if matches!(job.state, JobState::Pending | JobState::Submitted) {
+                jobs.push(job);
+            }
+        }
+        jobs.sort_by_key(|job| job.created);
+        Ok(jobs)
+    }
+}
@@ -0,0 +1,199 @@
+use kv::{Json, Transaction, TransactionError};
+
+use super::storage::{
+    user_account, DepositAccountData, LedgerBalanceData, LedgerEntryData, Storage, StorageError,
+};
+use crate::origin::Origin;
+
+/// Transactions of the ledger buckets.
+type Entries<'a, 'b> = Transaction<'a, 'b, String, Json<LedgerEntryData>>;
+type Balances<'a, 'b> = Transaction<'a, 'b, String, Json<LedgerBalanceData>>;
+
+/// Get the key of the totals of an account on a network.
+fn balance_key(network: &str, account: &str) -> String {
+    format!("{}/{}", network, account)
+}
+
+/// Record an entry and update the totals of its accounts.
+///
+/// Errors of the ledger itself are returned inside, before anything is
+/// written, so that the transaction still commits cleanly.
+fn apply_entry(
+    entries: &Entries,
+    balances: &Balances,
+    entry: &LedgerEntryData,
+) -> Result<Result<(), StorageError>, TransactionError<kv::Error>> {
+    if entries.get(&entry.id)?.is_some() {
+        return Ok(Err(StorageError::NotUnique));
+    }
+
+    let (debit_key, credit_key) = (
+        balance_key(&entry.network, &entry.debit),
+        balance_key(&entry.network, &entry.credit),
+    );
+    let mut debit = balances
+        .get(&debit_key)?
+        .map(|Json(b)| b)
+        .unwrap_or_default();
+    // Users can't spend more than they hold, the other accounts mirror the
+    // chain and may go either way.
+    if entry.debit.starts_with("user/") && debit.balance() < entry.amount {
+        return Ok(Err(StorageError::InsufficientFunds));
+    }
+    debit.debits += entry.amount;
+    balances.set(&debit_key, &Json(debit))?;
+
+    let mut credit = balances
+        .get(&credit_key)?
+        .map(|Json(b)| b)
+        .unwrap_or_default();
+    credit.credits += entry.amount;
+    balances.set(&credit_key, &Json(credit))?;
+
+    entries.set(&entry.id, &Json(entry.clone()))?;
+    Ok(Ok(()))
+}
+
+impl<'a> Storage<'a> {
+    /// Record an entry of the ledger.
+    ///
+    /// Fails with `StorageError::NotUnique` when an entry with the same id was
+    /// recorded before, and with `StorageError::InsufficientFunds` when a user
+    /// account doesn't hold the amount.
+    pub fn post_ledger_entry(&self, entry: &LedgerEntryData) -> Result<(), StorageError> {
+        if entry.debit == entry.credit {
+            return Err(StorageError::NotUnique);
+        }
+        self.ledger_entries
+            .transaction2(&self.ledger_balances, |entries, balances| {
+                apply_entry(&entries, &balances, entry)
+            })?
+    }
+
+    /// Get the totals of a ledger account on a network.
+    pub fn get_ledger_balance(
+        &self,
+        network: &str,
+        account: &str,
+    ) -> Result<LedgerBalanceData, StorageError> {
+        Ok(self
+            .ledger_balances
+            .get(&balance_key(network, account))?
+            .map(|Json(balance)| balance)
+            .unwrap_or_default())
+    }
+
+    /// Get the totals of a ledger account on all networks.
+    pub fn get_ledger_balances(
+        &self,
+        account: &str,
+    ) -> Result<Vec<(String, LedgerBalanceData)>, StorageError> {
+        let mut balances = Vec::new();
+        for item in self.ledger_balances.iter() {
+            let item = item?;
+            let key = item.key::<String>()?;
+            if let Some((network, item_account)) = key.split_once('/') {
+                if item_account == account {
+                    balances.push((network.to_string(), item.value::<Json<_>>()?.0));
+                }
+            }
+        }
+        Ok(balances)
+    }
+
+    /// Get the total owed to users on a network.
+    pub fn get_ledger_total(&self, network: &str) -> Result<u128, StorageError> {
+        let prefix = balance_key(network, &user_account(""));
+        let mut total = 0;
+        for item in self.ledger_balances.iter() {
+            let item = item?;
+            if item.key::<String>()?.starts_with(&prefix) {
+                total += item.value::<Json<LedgerBalanceData>>()?.0.balance();
+            }
+        }
+        Ok(total)
+    }
+
+    /// Move the ledger balances of an identity to another one.
+    pub(super) fn merge_ledger(&self, source: &str, target: &str) -> Result<(), StorageError> {
+        let (source, target) = (user_account(source), user_account(target));
+        for (network, balance) in self.get_ledger_balances(&source)? {
+            if balance.balance() == 0 {
+                continue;
+            }
+            match self.post_ledger_entry(&LedgerEntryData {
+                id: format!("merge/{}/{}/{}", network, source, target),
+                network,
+                debit: source.clone(),
+                credit: target.clone(),
+                amount: balance.balance(),
+                created: crate::functions::now(),
+            }) {
+                Ok(()) | Err(StorageError::NotUnique) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Start watching a deposit address, unless it's watched already.
+    pub fn add_deposit_account(&self, account: &DepositAccountData) -> Result<(), StorageError> {
+        let key = balance_key(&account.network, &account.origin);
+        if !self.deposit_accounts.contains(&key)? {
+            self.deposit_accounts.set(&key, &Json(account.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Get all watched deposit addresses.
+    pub fn get_deposit_accounts(&self) -> Result<Vec<DepositAccountData>, StorageError> {
+        let mut accounts = Vec::new();
+        for item in self.deposit_accounts.iter() {
+            accounts.push(item?.value::<Json<DepositAccountData>>()?.0);
+        }
+        Ok(accounts)
+    }
+
+    /// Get a watched deposit address.
+    pub fn get_deposit_account(
+        &self,
+        network: &str,
+        origin: &Origin,
+    ) -> Result<DepositAccountData, StorageError> {
+        Ok(self
+            .deposit_accounts
+            .get(&balance_key(network, &origin.to_string()))?
+            .ok_or(StorageError::NotFound)?
+            .0)
+    }
+
+    /// Credit a deposit to a user and update its deposit address in the same
+    /// transaction, so that a deposit can't be credited twice.
+    pub fn credit_deposit(
+        &self,
+        account: &DepositAccountData,
+        entry: &LedgerEntryData,
+    ) -> Result<(), StorageError> {
+        let key = balance_key(&account.network, &account.origin);
+        self.ledger_entries.transaction3(
+            &self.ledger_balances,
+            &self.deposit_accounts,
+            |entries, balances, deposits| {
+                if let Err(err) = apply_entry(&entries, &balances, entry)? {
+                    return Ok(Err(err));
+                }
+                deposits.set(&key, &Json(account.clone()))?;
+                Ok(Ok(()))
+            },
+        )?
+    }
+
+    /// Store the new state of a deposit address.
+    pub fn update_deposit_account(&self, account: &DepositAccountData) -> Result<(), StorageError> {
+        self.deposit_accounts.set(
+            &balance_key(&account.network, &account.origin),
+            &Json(account.clone()),
+        )?;
+        Ok(())
+    }
+}
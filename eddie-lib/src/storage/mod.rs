@@ -1,14 +1,31 @@
 mod admins;
+mod drips;
 mod faucets;
+mod governance;
+mod identities;
+mod jobs;
+mod ledger;
+mod networks;
+mod staking;
 pub mod storage;
+mod subscriptions;
+mod tips;
 mod user_wallets;
+mod watches;
 
 pub use storage::Storage;
 
 #[cfg(test)]
 mod tests {
-    use super::storage::{Storage, StorageError};
-    use crate::origin::Origin;
+    use super::storage::{
+        user_account, DepositAccountData, DripData, DripRecordData, EventFilter, FaucetChannelData,
+        FaucetPolicy, FaucetSetting, HeldMessagesData, JobData, JobState, LedgerEntryData,
+        LinkCodeData, NetworkData, PendingTipData, QuietHoursData, Storage, StorageError,
+        SubscriptionData, UnboundFaucetData, UserWalletsData, ValidatorAlertData, VoteReminderData,
+        WalletData, WatchData, DEFAULT_WALLET_LABEL, HOT_WALLET_ACCOUNT,
+    };
+    use crate::{chain::ChainInfo, crypto::StoredSeed, origin::Origin};
+    use kv::Json;
 
     #[test]
     fn user_wallets_flow_works() {
@@ -63,9 +80,1044 @@ mod tests {
 
         let storage = Storage::new("/tmp/admin_flow_works.edb").unwrap();
         assert!(storage.set_admin(discord_user.clone(), false).is_ok());
-        assert!(storage.is_admin(discord_user));
+        assert!(storage.is_admin(discord_user.clone()));
+        assert!(storage.get_admins().unwrap().contains(&discord_user));
 
         assert!(!storage.is_admin(telegram_user));
         assert!(storage.has_admins());
     }
+
+    #[test]
+    fn drips_flow_works() {
+        let channel = Origin::Discord("9876".into());
+        let other_channel = Origin::Discord("6789".into());
+        let discord_users = [Origin::Discord("1234".into())];
+        let telegram_users = [Origin::Telegram("4321".into())];
+        let pub_addr = String::from("W1234321");
+
+        let storage = Storage::new("/tmp/drips_flow_works.edb").unwrap();
+        storage.drips.clear().unwrap();
+
+        assert!(storage
+            .record_drip(&channel, "rococo", &discord_users, &pub_addr, 10, 1000)
+            .is_ok());
+        assert!(storage
+            .record_drip(&channel, "rococo", &telegram_users, "W5678", 20, 2000)
+            .is_ok());
+
+        let drips = storage
+            .get_drips(&channel, "rococo", &discord_users, &pub_addr, 3000)
+            .unwrap()
+            .unwrap();
+        assert_eq!((drips.last_drip, drips.drips, drips.amount), (1000, 1, 10));
+
+        // Drips to the user and to the address are combined.
+        let drips = storage
+            .get_drips(&channel, "rococo", &telegram_users, &pub_addr, 3000)
+            .unwrap()
+            .unwrap();
+        assert_eq!((drips.last_drip, drips.drips, drips.amount), (2000, 1, 20));
+
+        // Counters are reset the next day.
+        let drips = storage
+            .get_drips(&channel, "rococo", &discord_users, &pub_addr, 90000)
+            .unwrap()
+            .unwrap();
+        assert_eq!((drips.last_drip, drips.drips, drips.amount), (1000, 0, 0));
+
+        assert!(storage
+            .get_drips(&channel, "rococo", &[], "W0000", 3000)
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .get_drips(&other_channel, "rococo", &discord_users, &pub_addr, 3000)
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .get_drips(&channel, "westend", &discord_users, &pub_addr, 3000)
+            .unwrap()
+            .is_none());
+
+        assert_eq!(
+            storage.get_faucet_usage(&channel, "rococo", 3000).unwrap(),
+            (2, 30)
+        );
+        assert_eq!(
+            storage.get_faucet_usage(&channel, "westend", 3000).unwrap(),
+            (0, 0)
+        );
+        assert_eq!(
+            storage.get_faucet_usage(&channel, "rococo", 90000).unwrap(),
+            (0, 0)
+        );
+        assert_eq!(
+            storage
+                .get_faucet_usage(&other_channel, "rococo", 3000)
+                .unwrap(),
+            (0, 0)
+        );
+
+        // A failed drip is undone, back to the drip before.
+        storage
+            .record_drip(&channel, "rococo", &discord_users, &pub_addr, 10, 5000)
+            .unwrap();
+        let record = DripRecordData {
+            origins: vec![discord_users[0].to_string()],
+            address: pub_addr.clone(),
+            previous: 1000,
+        };
+        storage
+            .undo_drip(&channel, "rococo", &record, 10, 5000)
+            .unwrap();
+        let drips = storage
+            .get_drips(&channel, "rococo", &discord_users, &pub_addr, 5000)
+            .unwrap()
+            .unwrap();
+        assert_eq!((drips.last_drip, drips.drips, drips.amount), (1000, 1, 10));
+        assert_eq!(
+            storage.get_faucet_usage(&channel, "rococo", 5000).unwrap(),
+            (2, 30)
+        );
+
+        // A drip is only reserved when the check accepts it, against the
+        // totals that include the drips reserved before.
+        let within_budget = |_: Option<&DripData>, (_, paid): (u32, u128)| match paid + 10 > 40 {
+            true => Err("over budget"),
+            false => Ok(10),
+        };
+        let drips = storage
+            .reserve_drip(&channel, "rococo", &[], "W0000", 6000, within_budget)
+            .unwrap()
+            .unwrap();
+        assert!(drips.is_none());
+        assert!(matches!(
+            storage
+                .reserve_drip(&channel, "rococo", &[], "W1111", 6000, within_budget)
+                .unwrap(),
+            Err("over budget")
+        ));
+        assert_eq!(
+            storage.get_faucet_usage(&channel, "rococo", 6000).unwrap(),
+            (3, 40)
+        );
+    }
+
+    #[test]
+    fn faucets_flow_works() {
+        let channel = Origin::Telegram("-1001".into());
+        let chain = ChainInfo {
+            name: "Rococo".into(),
+            genesis_hash: "0x6408".into(),
+            token_symbol: "ROC".into(),
+            token_decimals: 12,
+            ss58_prefix: 42,
+        };
+
+        let storage = Storage::new("/tmp/faucets_flow_works.edb").unwrap();
+        storage.faucets.clear().unwrap();
+
+        assert!(matches!(
+            storage.activate_faucet_channel(&channel, "rococo", chain.clone()),
+            Err(StorageError::NotFound)
+        ));
+        storage.register_faucet_channel(&channel).unwrap();
+        assert!(matches!(
+            storage.register_faucet_channel(&channel),
+            Err(StorageError::NotUnique)
+        ));
+
+        // Every network has its own faucet.
+        storage
+            .activate_faucet_channel(&channel, "rococo", chain.clone())
+            .unwrap();
+        storage
+            .activate_faucet_channel(&channel, "westend", chain.clone())
+            .unwrap();
+        storage
+            .configure_faucet_channel(&channel, "rococo", FaucetSetting::Amount(Some(10)))
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_faucet(&channel, "rococo")
+                .unwrap()
+                .policy
+                .amount,
+            Some(10)
+        );
+        assert_eq!(
+            storage
+                .get_faucet(&channel, "westend")
+                .unwrap()
+                .policy
+                .amount,
+            None
+        );
+        assert!(matches!(
+            storage.get_faucet(&channel, "kusama"),
+            Err(StorageError::NotFound)
+        ));
+
+        // Reactivating keeps the policy.
+        storage
+            .activate_faucet_channel(&channel, "rococo", chain.clone())
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_faucet(&channel, "rococo")
+                .unwrap()
+                .policy
+                .amount,
+            Some(10)
+        );
+        assert_eq!(storage.get_faucets().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn legacy_faucets_can_be_read() {
+        let raw = br#"{"network":"rococo","wallet_seed":"//Bob","policy":{"amount":5},"chain":{"name":"Rococo","genesis_hash":"0x6408","token_symbol":"ROC","token_decimals":12}}"#;
+        let Json(data) =
+            <Json<FaucetChannelData> as kv::Value>::from_raw_value(kv::Raw::from(&raw[..]))
+                .unwrap();
+        let faucet = &data.networks["rococo"];
+        assert_eq!(faucet.wallet_seed, StoredSeed::Plain("//Bob".into()));
+        assert_eq!(faucet.policy.amount, Some(5));
+        assert_eq!(faucet.chain.ss58_prefix, 42);
+
+        // Channels that were never activated have no faucets.
+        let raw = br#"{"rpc_url":"","wallet_seed":""}"#;
+        let Json(data) =
+            <Json<FaucetChannelData> as kv::Value>::from_raw_value(kv::Raw::from(&raw[..]))
+                .unwrap();
+        assert!(data.networks.is_empty());
+        assert!(data.unbound.is_empty());
+
+        // Faucets that connected to an RPC endpoint before networks were
+        // registered keep their seed and policy until they're bound to one.
+        let raw = br#"{"rpc_url":"wss://rococo-rpc.polkadot.io","wallet_seed":"//Bob","policy":{"daily_budget":100}}"#;
+        let Json(data) =
+            <Json<FaucetChannelData> as kv::Value>::from_raw_value(kv::Raw::from(&raw[..]))
+                .unwrap();
+        assert!(data.networks.is_empty());
+        let unbound = UnboundFaucetData {
+            rpc_url: Some("wss://rococo-rpc.polkadot.io".into()),
+            network: None,
+            wallet_seed: StoredSeed::Plain("//Bob".into()),
+            policy: FaucetPolicy {
+                daily_budget: Some(100),
+                ..Default::default()
+            },
+            chain: None,
+        };
+        assert_eq!(data.unbound, vec![unbound.clone()]);
+
+        // They're kept when the channel is written back.
+        let raw = <Json<FaucetChannelData> as kv::Value>::to_raw_value(&Json(data)).unwrap();
+        let Json(data) = <Json<FaucetChannelData> as kv::Value>::from_raw_value(raw).unwrap();
+        assert_eq!(data.unbound, vec![unbound]);
+    }
+
+    #[test]
+    fn legacy_faucets_are_bound() {
+        let storage = Storage::new("/tmp/legacy_faucets_are_bound.edb").unwrap();
+        storage.faucets.clear().unwrap();
+
+        let store = |channel: &Origin, raw: &[u8]| {
+            let data =
+                <Json<FaucetChannelData> as kv::Value>::from_raw_value(kv::Raw::from(raw)).unwrap();
+            storage.faucets.set(&channel.to_string(), &data).unwrap();
+        };
+        let (first, second, third) = (
+            Origin::Discord("1".into()),
+            Origin::Discord("2".into()),
+            Origin::Discord("3".into()),
+        );
+        store(
+            &first,
+            br#"{"rpc_url":"wss://rococo-rpc.polkadot.io","wallet_seed":"//Bob","policy":{"amount":5}}"#,
+        );
+        store(
+            &second,
+            br#"{"rpc_url":"ws://127.0.0.1:9944","wallet_seed":"//Bob"}"#,
+        );
+        store(&third, br#"{"network":"kusama","wallet_seed":"//Bob"}"#);
+
+        let rococo = NetworkData {
+            name: "rococo".into(),
+            rpc_urls: vec!["wss://rococo-rpc.polkadot.io".into()],
+            chain: ChainInfo {
+                name: "Rococo".into(),
+                genesis_hash: "0x6408".into(),
+                token_symbol: "ROC".into(),
+                token_decimals: 12,
+                ss58_prefix: 42,
+            },
+            explorer_url: None,
+        };
+        assert_eq!(
+            storage
+                .bind_unbound_faucets(std::slice::from_ref(&rococo))
+                .unwrap(),
+            1
+        );
+
+        let faucet = storage.get_faucet(&first, "rococo").unwrap();
+        assert_eq!(faucet.wallet_seed, StoredSeed::Plain("//Bob".into()));
+        assert_eq!(faucet.policy.amount, Some(5));
+        assert_eq!(faucet.chain, rococo.chain);
+
+        // Faucets of other endpoints or networks wait for their network.
+        let unbound = storage.get_unbound_faucets().unwrap();
+        assert_eq!(
+            unbound
+                .iter()
+                .map(|(channel, index, _)| (channel.clone(), *index))
+                .collect::<Vec<_>>(),
+            vec![(second, 0), (third, 0)]
+        );
+        assert_eq!(storage.bind_unbound_faucets(&[rococo]).unwrap(), 0);
+    }
+
+    #[test]
+    fn identities_flow_works() {
+        let channel = Origin::Discord("9876".into());
+        let discord_user = Origin::Discord("1111".into());
+        let telegram_user = Origin::Telegram("2222".into());
+
+        let storage = Storage::new("/tmp/identities_flow_works.edb").unwrap();
+        storage.identities.clear().unwrap();
+        storage.user_wallets.clear().unwrap();
+        storage.wallet_addresses.clear().unwrap();
+        storage.admins.clear().unwrap();
+        storage.drips.clear().unwrap();
+
+        storage.set_admin(discord_user.clone(), false).unwrap();
+        storage
+            .set_user_wallet(telegram_user.clone(), "W1".into())
+            .unwrap();
+        storage
+            .record_drip(
+                &channel,
+                "rococo",
+                std::slice::from_ref(&discord_user),
+                "W2",
+                10,
+                1000,
+            )
+            .unwrap();
+        storage
+            .record_drip(
+                &channel,
+                "rococo",
+                std::slice::from_ref(&telegram_user),
+                "W1",
+                20,
+                2000,
+            )
+            .unwrap();
+
+        // Link codes can only be redeemed once and before they expire.
+        let link = LinkCodeData {
+            origin: discord_user.to_string(),
+            expires: 3000,
+        };
+        storage.set_link_code("ABCD", link.clone()).unwrap();
+        assert!(matches!(
+            storage.take_link_code("ABCD", 4000),
+            Err(StorageError::NotFound)
+        ));
+        storage.set_link_code("ABCD", link).unwrap();
+        assert_eq!(storage.take_link_code("ABCD", 2500).unwrap(), discord_user);
+        assert!(matches!(
+            storage.take_link_code("ABCD", 2500),
+            Err(StorageError::NotFound)
+        ));
+
+        let identity = storage
+            .link_origins(&discord_user, &telegram_user, "id1")
+            .unwrap();
+        assert_eq!(identity, "id1");
+        assert!(matches!(
+            storage.link_origins(&telegram_user, &discord_user, "id2"),
+            Err(StorageError::NotUnique)
+        ));
+        assert_eq!(
+            storage.get_identity_origins("id1").unwrap(),
+            vec![discord_user.clone(), telegram_user.clone()]
+        );
+
+        // Admin status, wallets and drips are shared by linked origins.
+        assert!(storage.is_admin(telegram_user.clone()));
+        assert_eq!(
+            storage.get_admins().unwrap(),
+            vec![discord_user.clone(), telegram_user.clone()]
+        );
+        assert_eq!(storage.get_user_wallet(discord_user.clone()).unwrap(), "W1");
+        let drips = storage
+            .get_drips(&channel, "rococo", &[discord_user], "W3", 3000)
+            .unwrap()
+            .unwrap();
+        assert_eq!((drips.last_drip, drips.drips, drips.amount), (2000, 2, 30));
+    }
+
+    #[test]
+    fn legacy_wallets_can_be_read() {
+        let data = <UserWalletsData as kv::Value>::from_raw_value(kv::Raw::from(&b"W1234321"[..]))
+            .unwrap();
+        let mut wallet = WalletData::new(DEFAULT_WALLET_LABEL, "W1234321".into(), None);
+        wallet.default = true;
+        assert_eq!(data.wallets, vec![wallet.clone()]);
+
+        let data = <UserWalletsData as kv::Value>::from_raw_value(kv::Raw::from(
+            &br#"{"address":"W1234321","verified":true}"#[..],
+        ))
+        .unwrap();
+        wallet.verified = true;
+        assert_eq!(data.wallets, vec![wallet]);
+    }
+
+    #[test]
+    fn wallets_flow_works() {
+        let storage = Storage::new("/tmp/eddie_wallets_test").unwrap();
+        storage.user_wallets.clear().unwrap();
+        storage.wallet_addresses.clear().unwrap();
+        storage.identities.clear().unwrap();
+
+        let user = Origin::Discord("1234".into());
+        assert!(matches!(
+            storage.get_user_wallet(user.clone()),
+            Err(StorageError::NotFound)
+        ));
+
+        // The first wallet becomes the default.
+        storage
+            .add_user_wallet(&user, WalletData::new("Main", "W1".into(), None))
+            .unwrap();
+        storage
+            .add_user_wallet(
+                &user,
+                WalletData::new("kusama", "W2".into(), Some("kusama".into())),
+            )
+            .unwrap();
+        assert!(matches!(
+            storage.add_user_wallet(&user, WalletData::new("main", "W3".into(), None)),
+            Err(StorageError::NotUnique)
+        ));
+        assert_eq!(storage.get_user_wallet(user.clone()).unwrap(), "W1");
+
+        storage.set_default_user_wallet(&user, "KUSAMA").unwrap();
+        assert_eq!(storage.get_user_wallet(user.clone()).unwrap(), "W2");
+        assert!(matches!(
+            storage.set_default_user_wallet(&user, "other"),
+            Err(StorageError::NotFound)
+        ));
+
+        // Setting the wallet replaces the default one.
+        storage.set_user_wallet(user.clone(), "W4".into()).unwrap();
+        let wallets = storage.get_user_wallets(&user).unwrap();
+        assert_eq!(wallets.get("main").unwrap().address, "W1");
+        assert_eq!(wallets.get("kusama").unwrap().address, "W4");
+        assert!(storage
+            .get_pub_address_origins("W2".into())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            storage.get_pub_address_origins("W4".into()).unwrap(),
+            vec![user.clone()]
+        );
+
+        // Removing the default makes another wallet the default.
+        storage.remove_user_wallet(&user, "kusama").unwrap();
+        assert_eq!(storage.get_user_wallet(user.clone()).unwrap(), "W1");
+        assert_eq!(
+            storage.get_pub_address_origins("W1".into()).unwrap(),
+            vec![user.clone()]
+        );
+        storage.remove_user_wallet(&user, "main").unwrap();
+        assert!(matches!(
+            storage.remove_user_wallet(&user, "main"),
+            Err(StorageError::NotFound)
+        ));
+        assert!(storage.get_user_wallets(&user).unwrap().wallets.is_empty());
+        assert!(storage
+            .get_pub_address_origins("W1".into())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn wallet_index_can_be_rebuilt() {
+        let storage = Storage::new("/tmp/eddie_wallet_index_test").unwrap();
+        storage.user_wallets.clear().unwrap();
+        storage.wallet_addresses.clear().unwrap();
+        storage.identities.clear().unwrap();
+
+        // Wallets stored by older versions of the bot aren't indexed.
+        let (alice, bob) = (Origin::Discord("1".into()), Origin::Telegram("2".into()));
+        for (origin, address) in [(&alice, "W1"), (&bob, "W1"), (&bob, "W2")] {
+            let mut data = storage.get_user_wallets(origin).unwrap();
+            let label = format!("wallet{}", data.wallets.len());
+            data.wallets
+                .push(WalletData::new(&label, address.into(), None));
+            storage
+                .user_wallets
+                .set(&origin.to_string(), &data)
+                .unwrap();
+        }
+        assert!(storage
+            .get_pub_address_origins("W1".into())
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(storage.rebuild_wallet_index().unwrap(), 2);
+        let mut origins = storage.get_pub_address_origins("W1".into()).unwrap();
+        origins.sort_by_key(|origin| origin.to_string());
+        assert_eq!(origins, vec![alice, bob.clone()]);
+        assert_eq!(
+            storage.get_pub_address_origins("W2".into()).unwrap(),
+            vec![bob]
+        );
+    }
+
+    #[test]
+    fn pending_tips_flow_works() {
+        let storage = Storage::new("/tmp/eddie_pending_tips_test").unwrap();
+        storage.pending_tips.clear().unwrap();
+        storage.identities.clear().unwrap();
+
+        let discord_user = Origin::Discord("1".into());
+        let telegram_user = Origin::Telegram("2".into());
+        let tip = |id: &str, to: &Origin, created| PendingTipData {
+            id: id.into(),
+            channel: "Discord(3)".into(),
+            from: "Discord(4)".into(),
+            to: to.to_string(),
+            network: "rococo".into(),
+            amount: 10,
+            created,
+        };
+        storage
+            .add_pending_tip(&tip("tip2", &telegram_user, 2000))
+            .unwrap();
+        storage
+            .add_pending_tip(&tip("tip1", &discord_user, 1000))
+            .unwrap();
+        assert_eq!(
+            storage.get_pending_tips(&discord_user).unwrap(),
+            vec![tip("tip1", &discord_user, 1000)]
+        );
+
+        // Tips to linked accounts can be claimed from either of them.
+        storage
+            .link_origins(&discord_user, &telegram_user, "id")
+            .unwrap();
+        let tips = storage.get_pending_tips(&discord_user).unwrap();
+        assert_eq!(
+            tips.iter().map(|tip| tip.id.as_str()).collect::<Vec<_>>(),
+            vec!["tip1", "tip2"]
+        );
+
+        assert_eq!(storage.take_pending_tip("tip1").unwrap().id, "tip1");
+        assert!(matches!(
+            storage.take_pending_tip("tip1"),
+            Err(StorageError::NotFound)
+        ));
+        assert_eq!(storage.get_pending_tips(&telegram_user).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn ledger_flow_works() {
+        let storage = Storage::new("/tmp/eddie_ledger_test").unwrap();
+        storage.ledger_entries.clear().unwrap();
+        storage.ledger_balances.clear().unwrap();
+        storage.deposit_accounts.clear().unwrap();
+        storage.identities.clear().unwrap();
+
+        let discord_user = Origin::Discord("1".into());
+        let telegram_user = Origin::Telegram("2".into());
+        let (alice, bob) = (
+            user_account(&discord_user.to_string()),
+            user_account(&telegram_user.to_string()),
+        );
+        let entry = |id: &str, debit: &str, credit: &str, amount| LedgerEntryData {
+            id: id.into(),
+            network: "rococo".into(),
+            debit: debit.into(),
+            credit: credit.into(),
+            amount,
+            created: 0,
+        };
+
+        // Deposits are credited together with the state of the address.
+        let deposit = DepositAccountData {
+            origin: discord_user.to_string(),
+            network: "rococo".into(),
+            unswept: 0,
+        };
+        storage.add_deposit_account(&deposit).unwrap();
+        let deposit = DepositAccountData {
+            unswept: 100,
+            ..deposit
+        };
+        storage
+            .credit_deposit(&deposit, &entry("deposit", HOT_WALLET_ACCOUNT, &alice, 100))
+            .unwrap();
+        assert!(matches!(
+            storage.credit_deposit(&deposit, &entry("deposit", HOT_WALLET_ACCOUNT, &alice, 100)),
+            Err(StorageError::NotUnique)
+        ));
+        storage.add_deposit_account(&deposit).unwrap();
+        assert_eq!(
+            storage
+                .get_deposit_account("rococo", &discord_user)
+                .unwrap(),
+            deposit
+        );
+        assert!(matches!(
+            storage.get_deposit_account("westend", &discord_user),
+            Err(StorageError::NotFound)
+        ));
+        assert_eq!(storage.get_deposit_accounts().unwrap(), vec![deposit]);
+
+        // Users can't spend more than they hold.
+        storage
+            .post_ledger_entry(&entry("tip", &alice, &bob, 30))
+            .unwrap();
+        assert!(matches!(
+            storage.post_ledger_entry(&entry("tip2", &bob, &alice, 31)),
+            Err(StorageError::InsufficientFunds)
+        ));
+        assert_eq!(
+            storage
+                .get_ledger_balance("rococo", &alice)
+                .unwrap()
+                .balance(),
+            70
+        );
+        assert_eq!(storage.get_ledger_total("rococo").unwrap(), 100);
+
+        // Linking the accounts merges their balances.
+        let identity = storage
+            .link_origins(&discord_user, &telegram_user, "id")
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_ledger_balances(&user_account(&identity))
+                .unwrap()
+                .into_iter()
+                .map(|(network, balance)| (network, balance.balance()))
+                .collect::<Vec<_>>(),
+            vec![("rococo".to_string(), 100)]
+        );
+        assert_eq!(storage.get_ledger_total("rococo").unwrap(), 100);
+    }
+
+    #[test]
+    fn subscriptions_flow_works() {
+        use crate::chain::{self, ChainEvent};
+
+        let address = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let account = chain::parse_address(address).unwrap();
+        let event = |pallet: &str, variant: &str| ChainEvent {
+            pallet: pallet.into(),
+            variant: variant.into(),
+            fields: String::new(),
+            transfer: None,
+            account: None,
+            referendum: None,
+        };
+        let transfer = ChainEvent {
+            transfer: Some((account.clone(), account.clone(), 1)),
+            ..event("Balances", "Transfer")
+        };
+
+        let filter = |value| EventFilter::try_from(value).unwrap();
+        assert!(filter("Democracy.*").matches(&event("Democracy", "Started")));
+        assert!(filter("democracy").matches(&event("Democracy", "Started")));
+        assert!(filter("System.CodeUpdated").matches(&event("System", "CodeUpdated")));
+        assert!(!filter("System.CodeUpdated").matches(&event("System", "NewAccount")));
+        let to =
+            EventFilter::try_from(format!("Balances.Transfer to {}", address).as_str()).unwrap();
+        assert!(to.matches(&transfer));
+        assert!(!to.matches(&event("Balances", "Transfer")));
+        assert_eq!(
+            to.to_string(),
+            format!("Balances.Transfer to {}", chain::to_address(&account))
+        );
+        assert!(EventFilter::try_from("System.CodeUpdated to 5Grw").is_err());
+        assert!(EventFilter::try_from(format!("System.* to {}", address).as_str()).is_err());
+        assert!(EventFilter::try_from("").is_err());
+
+        let storage = Storage::new("/tmp/eddie_subscriptions_test").unwrap();
+        storage.subscriptions.clear().unwrap();
+        let channel = Origin::Discord("1".into());
+        let subscription = SubscriptionData {
+            id: "abcd".into(),
+            channel: channel.to_string(),
+            network: "rococo".into(),
+            filter: filter("Democracy.*"),
+        };
+        storage.add_subscription(&subscription).unwrap();
+        assert!(matches!(
+            storage.add_subscription(&SubscriptionData {
+                id: "efgh".into(),
+                ..subscription.clone()
+            }),
+            Err(StorageError::NotUnique)
+        ));
+        assert_eq!(
+            storage.get_network_subscriptions("Rococo").unwrap(),
+            vec![subscription]
+        );
+        assert!(matches!(
+            storage.remove_subscription(&Origin::Discord("2".into()), "abcd"),
+            Err(StorageError::NotFound)
+        ));
+        storage.remove_subscription(&channel, "abcd").unwrap();
+        assert!(storage
+            .get_channel_subscriptions(&channel)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn watches_flow_works() {
+        let night = QuietHoursData::try_from("22-7").unwrap();
+        let hour = |hour: u64| hour * 60 * 60;
+        assert!(night.is_quiet(hour(23)));
+        assert!(night.is_quiet(hour(24 + 3)));
+        assert!(!night.is_quiet(hour(7)));
+        assert!(!night.is_quiet(hour(12)));
+        let day = QuietHoursData::try_from("9-17").unwrap();
+        assert!(day.is_quiet(hour(9)));
+        assert!(!day.is_quiet(hour(17)));
+        assert!(QuietHoursData::try_from("7-7").is_err());
+        assert!(QuietHoursData::try_from("22-24").is_err());
+        assert!(QuietHoursData::try_from("22").is_err());
+
+        let storage = Storage::new("/tmp/eddie_watches_test").unwrap();
+        storage.watches.clear().unwrap();
+        storage.quiet_hours.clear().unwrap();
+        storage.held_messages.clear().unwrap();
+        storage.identities.clear().unwrap();
+
+        let discord = Origin::Discord("1".into());
+        let telegram = Origin::Telegram("1".into());
+        storage.link_origins(&discord, &telegram, "id").unwrap();
+        let watch = WatchData {
+            id: "abcd".into(),
+            origin: discord.to_string(),
+            network: "rococo".into(),
+            address: "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY".into(),
+            label: None,
+        };
+        storage.add_watch(&watch).unwrap();
+        assert!(matches!(
+            storage.add_watch(&WatchData {
+                id: "efgh".into(),
+                origin: telegram.to_string(),
+                ..watch.clone()
+            }),
+            Err(StorageError::NotUnique)
+        ));
+        assert_eq!(storage.get_watches(&telegram).unwrap(), vec![watch.clone()]);
+        assert_eq!(storage.get_network_watches("Rococo").unwrap(), vec![watch]);
+
+        // Messages are held until the quiet hours of the user are over.
+        storage.set_quiet_hours(&telegram, Some(night)).unwrap();
+        assert_eq!(storage.get_quiet_hours(&discord).unwrap(), Some(night));
+        storage.hold_message(&discord, "first".into()).unwrap();
+        storage.hold_message(&discord, "second".into()).unwrap();
+        assert!(storage.get_released_messages(hour(23)).unwrap().is_empty());
+        let held = HeldMessagesData {
+            messages: vec!["first".into(), "second".into()],
+            dropped: 0,
+        };
+        assert_eq!(
+            storage.get_released_messages(hour(8)).unwrap(),
+            vec![(discord.clone(), held)]
+        );
+
+        // They're kept until they were delivered.
+        storage.remove_held_messages(&discord, 1).unwrap();
+        assert_eq!(
+            storage.get_released_messages(hour(8)).unwrap()[0]
+                .1
+                .messages,
+            vec![String::from("second")]
+        );
+        storage.remove_held_messages(&discord, 1).unwrap();
+        assert!(storage.get_released_messages(hour(8)).unwrap().is_empty());
+
+        // Only the latest messages are kept.
+        for i in 0..60 {
+            storage.hold_message(&discord, i.to_string()).unwrap();
+        }
+        let (_, held) = storage.get_released_messages(hour(8)).unwrap().remove(0);
+        assert_eq!((held.messages.len(), held.dropped), (50, 10));
+        assert_eq!(held.messages[0], "10");
+        storage.remove_held_messages(&discord, 50).unwrap();
+        storage.set_quiet_hours(&discord, None).unwrap();
+        assert_eq!(storage.get_quiet_hours(&telegram).unwrap(), None);
+
+        assert!(matches!(
+            storage.remove_watch(&Origin::Discord("2".into()), "abcd"),
+            Err(StorageError::NotFound)
+        ));
+        storage.remove_watch(&telegram, "abcd").unwrap();
+        assert!(storage.get_watches(&discord).unwrap().is_empty());
+    }
+
+    #[test]
+    fn vote_reminders_flow_works() {
+        let storage = Storage::new("/tmp/eddie_vote_reminders_test").unwrap();
+        storage.vote_reminders.clear().unwrap();
+
+        let mut reminder = VoteReminderData {
+            origin: Origin::Discord("1".into()).to_string(),
+            network: "Rococo".into(),
+            reminded: Vec::new(),
+        };
+        storage.add_vote_reminder(&reminder).unwrap();
+        assert!(matches!(
+            storage.add_vote_reminder(&VoteReminderData {
+                network: "rococo".into(),
+                ..reminder.clone()
+            }),
+            Err(StorageError::NotUnique)
+        ));
+
+        reminder.reminded.push(42);
+        storage.update_vote_reminder(&reminder).unwrap();
+        assert_eq!(
+            storage.get_vote_reminders().unwrap(),
+            vec![reminder.clone()]
+        );
+
+        storage.remove_vote_reminder(&reminder).unwrap();
+        assert!(matches!(
+            storage.remove_vote_reminder(&reminder),
+            Err(StorageError::NotFound)
+        ));
+        assert!(storage.get_vote_reminders().unwrap().is_empty());
+
+        storage.add_vote_reminder(&reminder).unwrap();
+        let other = VoteReminderData {
+            network: "Westend".into(),
+            ..reminder.clone()
+        };
+        storage.add_vote_reminder(&other).unwrap();
+        assert_eq!(storage.remove_network_vote_reminders("rococo").unwrap(), 1);
+        assert_eq!(storage.get_vote_reminders().unwrap(), vec![other]);
+    }
+
+    #[test]
+    fn validator_alerts_flow_works() {
+        let storage = Storage::new("/tmp/eddie_validator_alerts_test").unwrap();
+        storage.validator_alerts.clear().unwrap();
+        storage.identities.clear().unwrap();
+
+        let discord = Origin::Discord("1".into());
+        let telegram = Origin::Telegram("1".into());
+        storage.link_origins(&discord, &telegram, "id").unwrap();
+        let mut alert = ValidatorAlertData {
+            id: "abcd".into(),
+            origin: discord.to_string(),
+            network: "polkadot".into(),
+            stash: "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY".into(),
+            unclaimed_eras: 7,
+            active: None,
+            validating: None,
+            unclaimed_reported: None,
+        };
+        storage.add_validator_alert(&alert).unwrap();
+        assert!(matches!(
+            storage.add_validator_alert(&ValidatorAlertData {
+                id: "efgh".into(),
+                origin: telegram.to_string(),
+                network: "Polkadot".into(),
+                ..alert.clone()
+            }),
+            Err(StorageError::NotUnique)
+        ));
+
+        alert.active = Some(true);
+        alert.unclaimed_reported = Some(1200);
+        storage.update_validator_alert(&alert).unwrap();
+        assert_eq!(
+            storage.get_validator_alerts(&telegram).unwrap(),
+            vec![alert.clone()]
+        );
+        assert_eq!(
+            storage.get_all_validator_alerts().unwrap(),
+            vec![alert.clone()]
+        );
+
+        assert!(matches!(
+            storage.remove_validator_alert(&Origin::Discord("2".into()), "abcd"),
+            Err(StorageError::NotFound)
+        ));
+        storage.remove_validator_alert(&telegram, "abcd").unwrap();
+        assert!(storage.get_all_validator_alerts().unwrap().is_empty());
+
+        let other = ValidatorAlertData {
+            id: "efgh".into(),
+            network: "kusama".into(),
+            ..alert.clone()
+        };
+        storage.add_validator_alert(&alert).unwrap();
+        storage.add_validator_alert(&other).unwrap();
+        assert_eq!(
+            storage.remove_network_validator_alerts("Polkadot").unwrap(),
+            1
+        );
+        assert_eq!(storage.get_all_validator_alerts().unwrap(), vec![other]);
+    }
+
+    #[test]
+    fn networks_flow_works() {
+        let storage = Storage::new("/tmp/eddie_networks_test").unwrap();
+        storage.networks.clear().unwrap();
+
+        let network = NetworkData {
+            name: "rococo".into(),
+            rpc_urls: vec!["wss://rococo-rpc.polkadot.io".into()],
+            chain: ChainInfo {
+                name: "Rococo".into(),
+                genesis_hash: "0x6408".into(),
+                token_symbol: "ROC".into(),
+                token_decimals: 12,
+                ss58_prefix: 42,
+            },
+            explorer_url: Some("https://rococo.subscan.io/extrinsic/{hash}".into()),
+        };
+        storage.add_network(network.clone()).unwrap();
+        assert!(matches!(
+            storage.add_network(network.clone()),
+            Err(StorageError::NotUnique)
+        ));
+
+        assert_eq!(storage.get_network("Rococo").unwrap(), network);
+        assert_eq!(storage.get_networks().unwrap(), vec![network.clone()]);
+        assert_eq!(
+            network.explorer_link("0x01").as_deref(),
+            Some("https://rococo.subscan.io/extrinsic/0x01")
+        );
+
+        storage.remove_network("rococo").unwrap();
+        assert!(matches!(
+            storage.get_network("rococo"),
+            Err(StorageError::NotFound)
+        ));
+        assert!(matches!(
+            storage.remove_network("rococo"),
+            Err(StorageError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn jobs_flow_works() {
+        let storage = Storage::new("/tmp/jobs_flow_works.edb").unwrap();
+        storage.jobs.clear().unwrap();
+
+        let mut job = JobData::new(
+            "drip/1".into(),
+            "Telegram(-1001)".into(),
+            "rococo".into(),
+            "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty".into(),
+            10,
+            2000,
+        );
+        let older = JobData::new(
+            "drip/2".into(),
+            "Telegram(-1001)".into(),
+            "rococo".into(),
+            "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty".into(),
+            10,
+            1000,
+        );
+        storage.enqueue_job(&job).unwrap();
+        storage.enqueue_job(&older).unwrap();
+
+        // The same idempotency key can't be queued twice.
+        assert!(matches!(
+            storage.enqueue_job(&job),
+            Err(StorageError::NotUnique)
+        ));
+
+        // The store is shared with storages opened while this one is open.
+        let other = Storage::new("/tmp/jobs_flow_works.edb").unwrap();
+        assert_eq!(other.get_job("drip/1").unwrap(), job);
+
+        assert_eq!(
+            storage.get_open_jobs().unwrap(),
+            vec![older.clone(), job.clone()]
+        );
+
+        // Only failed jobs can be queued again.
+        assert!(matches!(
+            storage.requeue_job(&job),
+            Err(StorageError::NotUnique)
+        ));
+        job.state = JobState::Failed;
+        storage.update_job(&job).unwrap();
+        job.state = JobState::Pending;
+        storage.requeue_job(&job).unwrap();
+        assert_eq!(storage.get_job("drip/1").unwrap(), job);
+
+        job.state = JobState::Finalized;
+        storage.update_job(&job).unwrap();
+        assert_eq!(storage.get_open_jobs().unwrap(), vec![older]);
+        assert!(matches!(
+            storage.get_job("drip/3"),
+            Err(StorageError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn drips_left_works() {
+        let mut policy = FaucetPolicy::default();
+        assert_eq!(policy.drips_left(25, 10), 2);
+
+        policy.apply(FaucetSetting::MinBalance(Some(10)));
+        assert_eq!(policy.drips_left(25, 10), 1);
+        assert_eq!(policy.drips_left(5, 10), 0);
+
+        policy.apply(FaucetSetting::Amount(Some(0)));
+        assert_eq!(policy.drips_left(25, 10), 0);
+    }
+
+    #[test]
+    fn batch_size_works() {
+        let mut policy = FaucetPolicy::default();
+        assert_eq!(policy.batch_size(), None);
+
+        // A batch of a single payout is no batch.
+        policy.apply(FaucetSetting::BatchSize(Some(1)));
+        assert_eq!(policy.batch_size(), None);
+
+        policy.apply(FaucetSetting::BatchSize(Some(20)));
+        assert_eq!(policy.batch_size(), Some(20));
+    }
+
+    #[test]
+    fn faucet_setting_parsing_works() {
+        assert_eq!(
+            FaucetSetting::try_from(("amount", "1000")),
+            Ok(FaucetSetting::Amount(Some(1000)))
+        );
+        assert_eq!(
+            FaucetSetting::try_from(("MIN_BALANCE", "none")),
+            Ok(FaucetSetting::MinBalance(None))
+        );
+        assert_eq!(
+            FaucetSetting::try_from(("alert_balance", "5")),
+            Ok(FaucetSetting::AlertBalance(Some(5)))
+        );
+        assert_eq!(
+            FaucetSetting::try_from(("batch_window", "12")),
+            Ok(FaucetSetting::BatchWindow(Some(12)))
+        );
+        assert!(FaucetSetting::try_from(("cooldown", "soon")).is_err());
+        assert!(FaucetSetting::try_from(("blah", "1")).is_err());
+    }
 }
@@ -0,0 +1,48 @@
+use kv::Json;
+
+use super::storage::{NetworkData, Storage, StorageError};
+
+/// Get the key of a network, names are case insensitive.
+fn network_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+impl<'a> Storage<'a> {
+    /// Register a network.
+    pub fn add_network(&self, network: NetworkData) -> Result<(), StorageError> {
+        // Return an error if the name is already taken.
+        let key = network_key(&network.name);
+        if self.networks.get(&key)?.is_some() {
+            return Err(StorageError::NotUnique);
+        }
+
+        self.networks.set(&key, &Json(network))?;
+        Ok(())
+    }
+
+    /// Remove a network.
+    pub fn remove_network(&self, name: &str) -> Result<(), StorageError> {
+        self.networks
+            .remove(&network_key(name))?
+            .ok_or(StorageError::NotFound)?;
+        Ok(())
+    }
+
+    /// Get a network by name.
+    pub fn get_network(&self, name: &str) -> Result<NetworkData, StorageError> {
+        Ok(self
+            .networks
+            .get(&network_key(name))?
+            .ok_or(StorageError::NotFound)?
+            .0)
+    }
+
+    /// Get all registered networks.
+    pub fn get_networks(&self) -> Result<Vec<NetworkData>, StorageError> {
+        let mut networks = Vec::new();
+        for item in self.networks.iter() {
+            networks.push(item?.value::<Json<NetworkData>>()?.0);
+        }
+        Ok(networks)
+    }
+}
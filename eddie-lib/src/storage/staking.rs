@@ -0,0 +1,86 @@
+use kv::Json;
+
+use super::storage::{Storage, StorageError, ValidatorAlertData};
+use crate::origin::Origin;
+
+impl<'a> Storage<'a> {
+    /// Start alerting a user about a validator.
+    ///
+    /// Returns an error if the user already gets alerts about the validator
+    /// on the network.
+    pub fn add_validator_alert(&self, alert: &ValidatorAlertData) -> Result<(), StorageError> {
+        let origin =
+            Origin::try_from(alert.origin.clone()).map_err(|_| StorageError::InvalidOrigin)?;
+        if self
+            .get_validator_alerts(&origin)?
+            .iter()
+            .any(|a| a.network.eq_ignore_ascii_case(&alert.network) && a.stash == alert.stash)
+        {
+            return Err(StorageError::NotUnique);
+        }
+
+        self.validator_alerts.set(&alert.id, &Json(alert.clone()))?;
+        Ok(())
+    }
+
+    /// Stop alerting a user about a validator.
+    pub fn remove_validator_alert(&self, origin: &Origin, id: &str) -> Result<(), StorageError> {
+        if !self
+            .get_validator_alerts(origin)?
+            .iter()
+            .any(|alert| alert.id == id)
+        {
+            return Err(StorageError::NotFound);
+        }
+        self.validator_alerts.remove(&id.to_string())?;
+        Ok(())
+    }
+
+    /// Stop alerting users about the validators of a network that's removed.
+    ///
+    /// Returns the number of alerts that were removed.
+    pub fn remove_network_validator_alerts(&self, network: &str) -> Result<usize, StorageError> {
+        let mut removed = 0;
+        for alert in self.get_all_validator_alerts()? {
+            if alert.network.eq_ignore_ascii_case(network) {
+                self.validator_alerts.remove(&alert.id)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Update what is known about the validator of an alert.
+    pub fn update_validator_alert(&self, alert: &ValidatorAlertData) -> Result<(), StorageError> {
+        self.validator_alerts.set(&alert.id, &Json(alert.clone()))?;
+        Ok(())
+    }
+
+    /// Get the validator alerts of a user, including those made from linked
+    /// accounts.
+    pub fn get_validator_alerts(
+        &self,
+        origin: &Origin,
+    ) -> Result<Vec<ValidatorAlertData>, StorageError> {
+        let identity = self.get_identity(origin)?;
+        let mut alerts = Vec::new();
+        for alert in self.get_all_validator_alerts()? {
+            let Ok(alert_origin) = Origin::try_from(alert.origin.clone()) else {
+                continue;
+            };
+            if self.get_identity(&alert_origin)? == identity {
+                alerts.push(alert);
+            }
+        }
+        Ok(alerts)
+    }
+
+    /// Get the validator alerts of all users.
+    pub fn get_all_validator_alerts(&self) -> Result<Vec<ValidatorAlertData>, StorageError> {
+        let mut alerts = Vec::new();
+        for item in self.validator_alerts.iter() {
+            alerts.push(item?.value::<Json<ValidatorAlertData>>()?.0);
+        }
+        Ok(alerts)
+    }
+}
@@ -1,6 +1,16 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, OnceLock},
+};
+
 use kv::Json;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    chain::{self, ChainEvent, ChainInfo},
+    crypto::StoredSeed,
+};
+
 pub struct Nil;
 impl kv::Value for Nil {
     fn to_raw_value(&self) -> Result<kv::Raw, kv::Error> {
@@ -12,18 +22,834 @@ impl kv::Value for Nil {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Label of the wallet registered without a label.
+pub const DEFAULT_WALLET_LABEL: &str = "default";
+
+fn default_wallet_label() -> String {
+    DEFAULT_WALLET_LABEL.into()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A wallet registered by a user.
+pub struct WalletData {
+    /// Name the user refers to the wallet by.
+    #[serde(default = "default_wallet_label")]
+    pub label: String,
+    /// Address of the wallet, SS58 addresses are stored with the generic
+    /// prefix.
+    pub address: String,
+    /// Network the wallet is meant for, if any.
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Whether the user proved they own the wallet.
+    #[serde(default)]
+    pub verified: bool,
+    /// Whether the wallet is used when a command doesn't name one.
+    #[serde(default)]
+    pub default: bool,
+}
+
+impl WalletData {
+    /// Create an unverified wallet.
+    pub fn new(label: &str, address: String, network: Option<String>) -> Self {
+        Self {
+            label: label.trim().to_lowercase(),
+            address,
+            network,
+            verified: false,
+            default: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// All wallets registered by a user.
+pub struct UserWalletsData {
+    pub wallets: Vec<WalletData>,
+}
+
+impl UserWalletsData {
+    /// Get a wallet by label.
+    pub fn get(&self, label: &str) -> Option<&WalletData> {
+        let label = label.trim().to_lowercase();
+        self.wallets.iter().find(|wallet| wallet.label == label)
+    }
+
+    /// Get the default wallet.
+    pub fn default_wallet(&self) -> Option<&WalletData> {
+        self.wallets.iter().find(|wallet| wallet.default)
+    }
+
+    /// Get the wallet meant for a network, or the default wallet.
+    pub fn for_network(&self, network: &str) -> Option<&WalletData> {
+        self.wallets
+            .iter()
+            .find(|wallet| {
+                wallet
+                    .network
+                    .as_ref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(network))
+            })
+            .or(self.default_wallet())
+    }
+
+    /// Make sure a wallet is the default when there are any wallets.
+    pub fn ensure_default(&mut self) {
+        if self.default_wallet().is_none() {
+            if let Some(wallet) = self.wallets.first_mut() {
+                wallet.default = true;
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+/// Wallets of a user as they're kept in the storage.
+///
+/// Older versions of the bot stored a single wallet per user.
+enum StoredUserWallets {
+    Many { wallets: Vec<WalletData> },
+    Single(WalletData),
+}
+
+impl kv::Value for UserWalletsData {
+    fn to_raw_value(&self) -> Result<kv::Raw, kv::Error> {
+        kv::Value::to_raw_value(&Json(self.clone()))
+    }
+
+    fn from_raw_value(r: kv::Raw) -> Result<Self, kv::Error> {
+        let mut wallets = match <Json<StoredUserWallets> as kv::Value>::from_raw_value(r.clone()) {
+            Ok(Json(StoredUserWallets::Many { wallets })) => UserWalletsData { wallets },
+            Ok(Json(StoredUserWallets::Single(wallet))) => UserWalletsData {
+                wallets: vec![wallet],
+            },
+            // The first versions stored the address only.
+            Err(_) => UserWalletsData {
+                wallets: vec![WalletData::new(
+                    DEFAULT_WALLET_LABEL,
+                    <String as kv::Value>::from_raw_value(r)?,
+                    None,
+                )],
+            },
+        };
+        wallets.ensure_default();
+        Ok(wallets)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A pending proof of wallet ownership.
+pub struct ChallengeData {
+    /// Address the challenge was issued for.
+    pub address: String,
+    /// Message that has to be signed.
+    pub message: String,
+    /// Unix timestamp after which the challenge can't be completed anymore.
+    pub expires: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A pending link between two origins.
+pub struct LinkCodeData {
+    /// Origin the code was issued to.
+    pub origin: String,
+    /// Unix timestamp after which the code can't be redeemed anymore.
+    pub expires: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(from = "StoredFaucetChannel")]
+/// A faucet channel and the faucets it runs.
+pub struct FaucetChannelData {
+    /// Faucets of the channel by network name.
+    pub networks: BTreeMap<String, FaucetData>,
+    /// Faucets stored by older versions of the bot that aren't bound to a
+    /// registered network yet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unbound: Vec<UnboundFaucetData>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// A faucet paying out on a single network.
 pub struct FaucetData {
-    pub rpc_url: String,
-    pub wallet_seed: String, // TODO: Implement a more secure way of storing seeds.
+    #[serde(default)]
+    pub wallet_seed: StoredSeed,
+    #[serde(default)]
+    pub policy: FaucetPolicy,
+    /// The chain the faucet was activated for.
+    pub chain: ChainInfo,
+    /// Whether a low balance alert has been sent and the balance hasn't
+    /// recovered since.
+    #[serde(default)]
+    pub low_balance: bool,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A faucet stored by an older version of the bot, before faucets paid out
+/// on registered networks.
+///
+/// Its seed and policy are kept until it's bound to the network it paid out
+/// on.
+pub struct UnboundFaucetData {
+    /// RPC endpoint the faucet was activated with.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Name of the network the faucet was activated for.
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub wallet_seed: StoredSeed,
+    #[serde(default)]
+    pub policy: FaucetPolicy,
+    /// The chain the faucet was activated for, if it was checked.
+    #[serde(default)]
+    pub chain: Option<ChainInfo>,
+}
+
+#[derive(Deserialize)]
+/// A faucet channel as it's kept in the storage.
+///
+/// Older versions of the bot only ran a single faucet per channel and stored
+/// its fields next to each other instead of in `networks`. The first of them
+/// connected to `rpc_url`, later ones paid out on a registered `network`.
+struct StoredFaucetChannel {
+    #[serde(default)]
+    networks: Option<BTreeMap<String, FaucetData>>,
+    #[serde(default)]
+    unbound: Vec<UnboundFaucetData>,
+    #[serde(default)]
+    rpc_url: String,
+    #[serde(default)]
+    network: String,
+    #[serde(default)]
+    wallet_seed: StoredSeed,
+    #[serde(default)]
+    policy: FaucetPolicy,
+    #[serde(default)]
+    chain: Option<ChainInfo>,
+}
+
+impl From<StoredFaucetChannel> for FaucetChannelData {
+    fn from(value: StoredFaucetChannel) -> Self {
+        let (mut networks, mut unbound) = (BTreeMap::new(), value.unbound);
+        match (value.networks, value.chain) {
+            (Some(stored), _) => networks = stored,
+            (None, Some(chain)) if !value.network.is_empty() => {
+                networks.insert(
+                    value.network,
+                    FaucetData {
+                        wallet_seed: value.wallet_seed,
+                        policy: value.policy,
+                        chain,
+                        low_balance: false,
+                    },
+                );
+            }
+            // Channels that were registered but never activated have nothing
+            // to keep.
+            (None, chain)
+                if !value.rpc_url.is_empty()
+                    || !value.network.is_empty()
+                    || !value.wallet_seed.is_empty() =>
+            {
+                let non_empty = |value: String| Some(value).filter(|value| !value.is_empty());
+                unbound.push(UnboundFaucetData {
+                    rpc_url: non_empty(value.rpc_url),
+                    network: non_empty(value.network),
+                    wallet_seed: value.wallet_seed,
+                    policy: value.policy,
+                    chain,
+                });
+            }
+            (None, _) => {}
+        }
+        Self { networks, unbound }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A chain registered with the bot.
+pub struct NetworkData {
+    /// Name the network is registered under.
+    pub name: String,
+    /// RPC endpoints of the network, tried in order.
+    pub rpc_urls: Vec<String>,
+    /// Properties of the chain.
+    pub chain: ChainInfo,
+    /// Block explorer url template, where `{hash}` is replaced with an
+    /// extrinsic hash.
+    pub explorer_url: Option<String>,
+}
+
+impl NetworkData {
+    /// Get the block explorer link of an extrinsic, if the network has an
+    /// explorer.
+    pub fn explorer_link(&self, hash: &str) -> Option<String> {
+        self.explorer_url
+            .as_ref()
+            .map(|url| url.replace("{hash}", hash))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// Payout rules of a faucet.
+///
+/// Unset amounts and cooldowns fall back to the bot's configuration, unset
+/// limits are not enforced.
+pub struct FaucetPolicy {
+    /// Amount paid out per drip.
+    pub amount: Option<u128>,
+    /// Number of seconds a user has to wait between drips.
+    pub cooldown: Option<u64>,
+    /// Maximum number of drips per user per day.
+    pub max_drips_per_day: Option<u32>,
+    /// Maximum amount paid out by the faucet per day.
+    pub daily_budget: Option<u128>,
+    /// Balance that should always be kept in the faucet wallet.
+    pub min_balance: Option<u128>,
+    /// Balance below which admins are alerted.
+    pub alert_balance: Option<u128>,
+    /// Number of seconds payouts are collected before they're sent in a
+    /// batch.
+    #[serde(default)]
+    pub batch_window: Option<u64>,
+    /// Maximum number of payouts per batch, batching is off when unset.
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A single change to a faucet policy.
+pub enum FaucetSetting {
+    Amount(Option<u128>),
+    Cooldown(Option<u64>),
+    MaxDripsPerDay(Option<u32>),
+    DailyBudget(Option<u128>),
+    MinBalance(Option<u128>),
+    AlertBalance(Option<u128>),
+    BatchWindow(Option<u64>),
+    BatchSize(Option<u32>),
+}
+
+/// Parse a setting value, where `none` unsets the value.
+fn parse_setting_value<V: std::str::FromStr>(value: &str) -> Result<Option<V>, &'static str> {
+    match value.trim().to_lowercase().as_str() {
+        "none" | "default" => Ok(None),
+        value => value
+            .parse::<V>()
+            .map(Some)
+            .map_err(|_| "Invalid setting value"),
+    }
+}
+
+impl TryFrom<(&str, &str)> for FaucetSetting {
+    type Error = &'static str;
+
+    fn try_from((name, value): (&str, &str)) -> Result<Self, Self::Error> {
+        match name.trim().to_lowercase().as_str() {
+            "amount" => Ok(Self::Amount(parse_setting_value(value)?)),
+            "cooldown" => Ok(Self::Cooldown(parse_setting_value(value)?)),
+            "max_drips_per_day" => Ok(Self::MaxDripsPerDay(parse_setting_value(value)?)),
+            "daily_budget" => Ok(Self::DailyBudget(parse_setting_value(value)?)),
+            "min_balance" => Ok(Self::MinBalance(parse_setting_value(value)?)),
+            "alert_balance" => Ok(Self::AlertBalance(parse_setting_value(value)?)),
+            "batch_window" => Ok(Self::BatchWindow(parse_setting_value(value)?)),
+            "batch_size" => Ok(Self::BatchSize(parse_setting_value(value)?)),
+            _ => Err("Invalid setting"),
+        }
+    }
+}
+
+impl FaucetPolicy {
+    /// Apply a setting to the policy.
+    pub fn apply(&mut self, setting: FaucetSetting) {
+        match setting {
+            FaucetSetting::Amount(v) => self.amount = v,
+            FaucetSetting::Cooldown(v) => self.cooldown = v,
+            FaucetSetting::MaxDripsPerDay(v) => self.max_drips_per_day = v,
+            FaucetSetting::DailyBudget(v) => self.daily_budget = v,
+            FaucetSetting::MinBalance(v) => self.min_balance = v,
+            FaucetSetting::AlertBalance(v) => self.alert_balance = v,
+            FaucetSetting::BatchWindow(v) => self.batch_window = v,
+            FaucetSetting::BatchSize(v) => self.batch_size = v,
+        }
+    }
+
+    /// Estimate the number of drips a faucet can still pay out with the given
+    /// balance.
+    pub fn drips_left(&self, balance: u128, default_amount: u128) -> u128 {
+        let amount = self.amount.unwrap_or(default_amount);
+        if amount == 0 {
+            return 0;
+        }
+        balance.saturating_sub(self.min_balance.unwrap_or_default()) / amount
+    }
+
+    /// Get the maximum number of payouts per batch, if payouts are batched.
+    pub fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+            .filter(|size| *size > 1)
+            .map(|size| size as usize)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DripData {
+    /// Unix timestamp of the last payout.
+    pub last_drip: u64,
+    /// Day (days since the unix epoch) the counters below refer to.
+    #[serde(default)]
+    pub day: u64,
+    /// Number of drips on `day`.
+    #[serde(default)]
+    pub drips: u32,
+    /// Amount paid out on `day`.
+    #[serde(default)]
+    pub amount: u128,
+}
+
+impl DripData {
+    /// Get the day of a unix timestamp.
+    pub fn day_of(timestamp: u64) -> u64 {
+        timestamp / (24 * 60 * 60)
+    }
+
+    /// Combine the drips of two beneficiaries.
+    pub fn merge(&self, other: &DripData) -> DripData {
+        let day = self.day.max(other.day);
+        let (drips, amount) = self.usage_on(day);
+        let (other_drips, other_amount) = other.usage_on(day);
+        DripData {
+            last_drip: self.last_drip.max(other.last_drip),
+            day,
+            drips: drips + other_drips,
+            amount: amount + other_amount,
+        }
+    }
+
+    /// Get the number of drips and the amount paid out on a given day.
+    pub fn usage_on(&self, day: u64) -> (u32, u128) {
+        match self.day == day {
+            true => (self.drips, self.amount),
+            false => (0, 0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Lifecycle of a queued payout.
+pub enum JobState {
+    /// Waiting to be (re)submitted.
+    Pending,
+    /// Included in a block, waiting for finality.
+    Submitted,
+    Finalized,
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A payout waiting in, or processed by, the job queue.
+pub struct JobData {
+    /// Idempotency key of the job.
+    pub id: String,
+    /// Channel the payout was requested in.
+    pub channel: String,
+    /// Origin whose tipping account pays a tip. Faucet payouts are paid by
+    /// the faucet of the channel.
+    #[serde(default)]
+    pub tipper: Option<String>,
+    /// Ledger account a withdrawal is paid out for, from the hot wallet.
+    #[serde(default)]
+    pub withdrawal: Option<String>,
+    /// Drip a faucet payout was recorded as, undone when nothing was paid.
+    #[serde(default)]
+    pub drip: Option<DripRecordData>,
+    /// Whether the job moves all funds of the tipper's deposit address to
+    /// the hot wallet, crediting what arrives once it's final.
+    #[serde(default)]
+    pub sweep: bool,
+    pub network: String,
+    /// Address the tokens are sent to.
+    pub dest: String,
+    pub amount: u128,
+    pub state: JobState,
+    /// Nonce the extrinsic was signed with, once assigned.
+    pub nonce: Option<u32>,
+    /// Number of failed submission attempts.
+    pub attempts: u32,
+    /// Unix timestamp before which the job isn't retried.
+    pub next_attempt: u64,
+    /// Unix timestamp of the request.
+    pub created: u64,
+    /// Hash of the extrinsic, once included in a block.
+    pub hash: Option<String>,
+    pub block_hash: Option<String>,
+    pub block: Option<u32>,
+    /// Reason the last attempt failed.
+    pub error: Option<String>,
+}
+
+impl JobData {
+    /// Create a pending job.
+    pub fn new(
+        id: String,
+        channel: String,
+        network: String,
+        dest: String,
+        amount: u128,
+        created: u64,
+    ) -> Self {
+        Self {
+            id,
+            channel,
+            tipper: None,
+            withdrawal: None,
+            drip: None,
+            sweep: false,
+            network,
+            dest,
+            amount,
+            state: JobState::Pending,
+            nonce: None,
+            attempts: 0,
+            next_attempt: created,
+            created,
+            hash: None,
+            block_hash: None,
+            block: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Beneficiaries of a drip, to undo its record if the payout fails.
+pub struct DripRecordData {
+    pub origins: Vec<String>,
+    /// Generic SS58 address the drip was sent to.
+    pub address: String,
+    /// Unix timestamp of the drip before, 0 if there was none.
+    pub previous: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A tip to a user without a wallet, waiting to be claimed.
+pub struct PendingTipData {
+    pub id: String,
+    /// Channel the tip was given in.
+    pub channel: String,
+    /// Origin whose tipping account pays the tip.
+    pub from: String,
+    /// Origin the tip was given to.
+    pub to: String,
+    pub network: String,
+    pub amount: u128,
+    /// Unix timestamp of the tip.
+    pub created: u64,
+}
+
+/// Ledger account of the tokens held by the hot wallet.
+pub const HOT_WALLET_ACCOUNT: &str = "hot_wallet";
+
+/// Ledger account of the withdrawal fees the bot paid for users whose
+/// balance didn't cover them.
+pub const FEE_ACCOUNT: &str = "fees";
+
+/// Get the ledger account of a user's identity.
+pub fn user_account(identity: &str) -> String {
+    format!("user/{}", identity)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A double-entry record of the ledger, moving an amount from one account
+/// to another.
+pub struct LedgerEntryData {
+    /// Idempotency key of the entry.
+    pub id: String,
+    pub network: String,
+    /// Account the amount is taken from.
+    pub debit: String,
+    /// Account the amount is added to.
+    pub credit: String,
+    pub amount: u128,
+    /// Unix timestamp of the entry.
+    pub created: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// Totals of the entries of a ledger account on a network.
+pub struct LedgerBalanceData {
+    pub debits: u128,
+    pub credits: u128,
+}
+
+impl LedgerBalanceData {
+    /// Get the amount owed to the holder of a user account.
+    pub fn balance(&self) -> u128 {
+        self.credits.saturating_sub(self.debits)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A deposit address of a user that's watched for incoming tokens.
+pub struct DepositAccountData {
+    /// Origin the address was derived for.
+    pub origin: String,
+    pub network: String,
+    /// Amount credited to the user that's still waiting to be moved to the
+    /// hot wallet. Deposits are only credited once they arrived there, so
+    /// it's only set for deposits credited before.
+    pub unswept: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Events a channel follows, like `Democracy.*`, `System.CodeUpdated` or
+/// `Balances.Transfer to <address>`.
+pub struct EventFilter {
+    pub pallet: String,
+    /// Name of the event, any event of the pallet when it's missing.
+    pub event: Option<String>,
+    /// Recipient of a transfer, with the generic SS58 prefix.
+    pub to: Option<String>,
+    /// Sender of a transfer, with the generic SS58 prefix.
+    pub from: Option<String>,
+}
+
+impl EventFilter {
+    /// Check whether an event passes the filter.
+    pub fn matches(&self, event: &ChainEvent) -> bool {
+        if !self.pallet.eq_ignore_ascii_case(&event.pallet)
+            || self
+                .event
+                .as_ref()
+                .is_some_and(|name| !name.eq_ignore_ascii_case(&event.variant))
+        {
+            return false;
+        }
+        if self.to.is_none() && self.from.is_none() {
+            return true;
+        }
+        let Some((from, to, _)) = &event.transfer else {
+            return false;
+        };
+        self.to
+            .as_ref()
+            .is_none_or(|addr| *addr == chain::to_address(to))
+            && self
+                .from
+                .as_ref()
+                .is_none_or(|addr| *addr == chain::to_address(from))
+    }
+}
+
+impl TryFrom<&str> for EventFilter {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut args = value.split_whitespace();
+        let name = args.next().ok_or("Expected an event like Pallet.Event")?;
+        let (pallet, event) = name.split_once('.').unwrap_or((name, "*"));
+        let valid =
+            |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric());
+        if !valid(pallet) || (event != "*" && !valid(event)) {
+            return Err("Expected an event like Pallet.Event or Pallet.*");
+        }
+
+        let mut filter = Self {
+            pallet: pallet.to_string(),
+            event: Some(event.to_string()).filter(|event| event != "*"),
+            to: None,
+            from: None,
+        };
+        while let Some(arg) = args.next() {
+            let address = args
+                .next()
+                .and_then(|address| chain::parse_address(address).ok())
+                .map(|account| chain::to_address(&account))
+                .ok_or("Expected an address")?;
+            match arg.to_lowercase().as_str() {
+                "to" => filter.to = Some(address),
+                "from" => filter.from = Some(address),
+                _ => return Err("Only transfers can be filtered, by to or from"),
+            }
+        }
+        if (filter.to.is_some() || filter.from.is_some())
+            && !(filter.pallet.eq_ignore_ascii_case("balances")
+                && filter
+                    .event
+                    .as_ref()
+                    .is_some_and(|event| event.eq_ignore_ascii_case("transfer")))
+        {
+            return Err("Only Balances.Transfer can be filtered by address");
+        }
+        Ok(filter)
+    }
+}
+
+impl std::fmt::Display for EventFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}",
+            self.pallet,
+            self.event.as_deref().unwrap_or("*")
+        )?;
+        if let Some(to) = &self.to {
+            write!(f, " to {}", to)?;
+        }
+        if let Some(from) = &self.from {
+            write!(f, " from {}", from)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A channel following events of a network.
+pub struct SubscriptionData {
+    pub id: String,
+    pub channel: String,
+    pub network: String,
+    pub filter: EventFilter,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// An address a user gets a direct message about when its funds move.
+pub struct WatchData {
+    pub id: String,
+    /// Origin the messages are sent to.
+    pub origin: String,
+    pub network: String,
+    /// Watched address, with the generic SS58 prefix.
+    pub address: String,
+    /// Label of the user's wallet with the address, if any.
+    pub label: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// Messages held back during the quiet hours of a user, oldest first.
+pub struct HeldMessagesData {
+    pub messages: Vec<String>,
+    /// Number of older messages that were dropped to keep the list short.
+    pub dropped: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+/// Hours of the day, in UTC, a user doesn't want to be messaged.
+pub struct QuietHoursData {
+    /// Hour the quiet hours start at.
+    pub start: u8,
+    /// Hour the quiet hours end at, which may be on the next day.
+    pub end: u8,
+}
+
+impl QuietHoursData {
+    /// Check whether a unix timestamp falls within the quiet hours.
+    pub fn is_quiet(&self, timestamp: u64) -> bool {
+        let hour = ((timestamp % (24 * 60 * 60)) / (60 * 60)) as u8;
+        match self.start <= self.end {
+            true => self.start <= hour && hour < self.end,
+            false => hour >= self.start || hour < self.end,
+        }
+    }
+}
+
+impl TryFrom<&str> for QuietHoursData {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (start, end) = value
+            .trim()
+            .split_once('-')
+            .ok_or("Expected quiet hours like 22-7")?;
+        let hour = |value: &str| match value.trim().parse::<u8>() {
+            Ok(hour) if hour < 24 => Ok(hour),
+            _ => Err("Hours have to be 0 to 23"),
+        };
+        let (start, end) = (hour(start)?, hour(end)?);
+        if start == end {
+            return Err("The quiet hours can't start and end at the same hour");
+        }
+        Ok(Self { start, end })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A user who gets reminded of referenda their wallet hasn't voted on before
+/// they close.
+pub struct VoteReminderData {
+    /// Origin the reminders are sent to.
+    pub origin: String,
+    pub network: String,
+    /// Referenda the user was reminded of that are still open.
+    #[serde(default)]
+    pub reminded: Vec<u32>,
+}
+
+impl VoteReminderData {
+    /// Key of the reminders of a user on a network.
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.origin, self.network.to_lowercase())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A validator a user gets a direct message about when it stops validating
+/// or its payouts aren't claimed.
+pub struct ValidatorAlertData {
+    pub id: String,
+    /// Origin the alerts are sent to.
+    pub origin: String,
+    pub network: String,
+    /// Stash of the validator, with the generic SS58 prefix.
+    pub stash: String,
+    /// Number of eras after which unclaimed payouts are reported.
+    pub unclaimed_eras: u32,
+    /// Whether the validator was in the active set when last checked.
+    #[serde(default)]
+    pub active: Option<bool>,
+    /// Whether the validator intended to validate when last checked.
+    #[serde(default)]
+    pub validating: Option<bool>,
+    /// Oldest unclaimed era the user was told about.
+    #[serde(default)]
+    pub unclaimed_reported: Option<u32>,
+}
+
+/// Stores that are open in this process by path.
+///
+/// The database can only be opened once at a time, so storages opened by
+/// concurrent tasks share the same store.
+static STORES: OnceLock<Mutex<HashMap<String, kv::Store>>> = OnceLock::new();
+
 /// Holds storage functionality for the bot.
 pub struct Storage<'a> {
     // db: TinyBase,
-    pub user_wallets: kv::Bucket<'a, String, String>,
+    pub user_wallets: kv::Bucket<'a, String, UserWalletsData>,
+    /// Identities using a wallet address, kept in sync with `user_wallets`.
+    pub wallet_addresses: kv::Bucket<'a, String, Json<Vec<String>>>,
+    pub challenges: kv::Bucket<'a, String, Json<ChallengeData>>,
+    pub identities: kv::Bucket<'a, String, String>,
+    pub link_codes: kv::Bucket<'a, String, Json<LinkCodeData>>,
     pub admins: kv::Bucket<'a, String, Nil>,
-    pub faucets: kv::Bucket<'a, String, Json<FaucetData>>,
+    pub faucets: kv::Bucket<'a, String, Json<FaucetChannelData>>,
+    pub drips: kv::Bucket<'a, String, Json<DripData>>,
+    pub networks: kv::Bucket<'a, String, Json<NetworkData>>,
+    pub jobs: kv::Bucket<'a, String, Json<JobData>>,
+    pub pending_tips: kv::Bucket<'a, String, Json<PendingTipData>>,
+    pub ledger_entries: kv::Bucket<'a, String, Json<LedgerEntryData>>,
+    /// Totals of the ledger accounts by network and account.
+    pub ledger_balances: kv::Bucket<'a, String, Json<LedgerBalanceData>>,
+    pub deposit_accounts: kv::Bucket<'a, String, Json<DepositAccountData>>,
+    pub subscriptions: kv::Bucket<'a, String, Json<SubscriptionData>>,
+    pub watches: kv::Bucket<'a, String, Json<WatchData>>,
+    /// Quiet hours by identity.
+    pub quiet_hours: kv::Bucket<'a, String, Json<QuietHoursData>>,
+    /// Messages held back during quiet hours, by origin.
+    pub held_messages: kv::Bucket<'a, String, Json<HeldMessagesData>>,
+    pub vote_reminders: kv::Bucket<'a, String, Json<VoteReminderData>>,
+    pub validator_alerts: kv::Bucket<'a, String, Json<ValidatorAlertData>>,
 }
 
 #[derive(Debug)]
@@ -38,6 +864,9 @@ pub enum StorageError {
     /// An invalid origin was encountered
     InvalidOrigin,
 
+    /// The ledger account doesn't hold enough funds
+    InsufficientFunds,
+
     /// An error occured when interacting with the underlying database
     Kv(kv::Error),
 }
@@ -48,6 +877,7 @@ impl std::fmt::Display for StorageError {
             StorageError::NotFound => String::from("Record not found"),
             StorageError::NotUnique => String::from("Record ID is not unique"),
             StorageError::InvalidOrigin => String::from("Invalid origin"),
+            StorageError::InsufficientFunds => String::from("Insufficient funds"),
             StorageError::Kv(err) => format!("{}", err),
         };
         write!(f, "{}", msg)
@@ -65,19 +895,71 @@ impl From<kv::Error> for StorageError {
 impl<'a> Storage<'a> {
     /// Instantiate a new storage.
     pub fn new(db: &str) -> Result<Storage<'a>, StorageError> {
-        // Initialize database.
-        let cfg = kv::Config::new(db);
-        let store = kv::Store::new(cfg)?;
+        // Initialize database, or reuse it when it's already open.
+        let mut stores = STORES
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let store = match stores.get(db) {
+            Some(store) => store.clone(),
+            None => {
+                let store = kv::Store::new(kv::Config::new(db))?;
+                stores.insert(db.to_string(), store.clone());
+                store
+            }
+        };
 
         // Initialiaze buckers.
-        let user_wallets = store.bucket::<String, String>(Some("user_wallets"))?;
+        let user_wallets = store.bucket::<String, UserWalletsData>(Some("user_wallets"))?;
+        let wallet_addresses =
+            store.bucket::<String, Json<Vec<String>>>(Some("wallet_addresses"))?;
+        let challenges = store.bucket::<String, Json<ChallengeData>>(Some("challenges"))?;
+        let identities = store.bucket::<String, String>(Some("identities"))?;
+        let link_codes = store.bucket::<String, Json<LinkCodeData>>(Some("link_codes"))?;
         let admins = store.bucket::<String, Nil>(Some("admins"))?;
-        let faucets = store.bucket::<String, Json<FaucetData>>(Some("faucets"))?;
+        let faucets = store.bucket::<String, Json<FaucetChannelData>>(Some("faucets"))?;
+        let drips = store.bucket::<String, Json<DripData>>(Some("drips"))?;
+        let networks = store.bucket::<String, Json<NetworkData>>(Some("networks"))?;
+        let jobs = store.bucket::<String, Json<JobData>>(Some("jobs"))?;
+        let pending_tips = store.bucket::<String, Json<PendingTipData>>(Some("pending_tips"))?;
+        let ledger_entries =
+            store.bucket::<String, Json<LedgerEntryData>>(Some("ledger_entries"))?;
+        let ledger_balances =
+            store.bucket::<String, Json<LedgerBalanceData>>(Some("ledger_balances"))?;
+        let deposit_accounts =
+            store.bucket::<String, Json<DepositAccountData>>(Some("deposit_accounts"))?;
+        let subscriptions =
+            store.bucket::<String, Json<SubscriptionData>>(Some("subscriptions"))?;
+        let watches = store.bucket::<String, Json<WatchData>>(Some("watches"))?;
+        let quiet_hours = store.bucket::<String, Json<QuietHoursData>>(Some("quiet_hours"))?;
+        let held_messages =
+            store.bucket::<String, Json<HeldMessagesData>>(Some("held_messages"))?;
+        let vote_reminders =
+            store.bucket::<String, Json<VoteReminderData>>(Some("vote_reminders"))?;
+        let validator_alerts =
+            store.bucket::<String, Json<ValidatorAlertData>>(Some("validator_alerts"))?;
 
         Ok(Self {
             user_wallets,
+            wallet_addresses,
+            challenges,
+            identities,
+            link_codes,
             admins,
             faucets,
+            drips,
+            networks,
+            jobs,
+            pending_tips,
+            ledger_entries,
+            ledger_balances,
+            deposit_accounts,
+            subscriptions,
+            watches,
+            quiet_hours,
+            held_messages,
+            vote_reminders,
+            validator_alerts,
         })
     }
 }
@@ -0,0 +1,68 @@
+use kv::Json;
+
+use super::storage::{Storage, StorageError, SubscriptionData};
+use crate::origin::Origin;
+
+impl<'a> Storage<'a> {
+    /// Subscribe a channel to events.
+    ///
+    /// Returns an error if the channel already follows the same events.
+    pub fn add_subscription(&self, subscription: &SubscriptionData) -> Result<(), StorageError> {
+        let channel = Origin::try_from(subscription.channel.clone())
+            .map_err(|_| StorageError::InvalidOrigin)?;
+        if self.get_channel_subscriptions(&channel)?.iter().any(|s| {
+            s.network.eq_ignore_ascii_case(&subscription.network) && s.filter == subscription.filter
+        }) {
+            return Err(StorageError::NotUnique);
+        }
+
+        self.subscriptions
+            .set(&subscription.id, &Json(subscription.clone()))?;
+        Ok(())
+    }
+
+    /// Remove a subscription of a channel.
+    pub fn remove_subscription(&self, channel: &Origin, id: &str) -> Result<(), StorageError> {
+        match self.subscriptions.get(&id.to_string())? {
+            Some(Json(subscription)) if subscription.channel == channel.to_string() => {
+                self.subscriptions.remove(&subscription.id)?;
+                Ok(())
+            }
+            _ => Err(StorageError::NotFound),
+        }
+    }
+
+    /// Get all subscriptions.
+    pub fn get_subscriptions(&self) -> Result<Vec<SubscriptionData>, StorageError> {
+        let mut subscriptions = Vec::new();
+        for item in self.subscriptions.iter() {
+            subscriptions.push(item?.value::<Json<SubscriptionData>>()?.0);
+        }
+        Ok(subscriptions)
+    }
+
+    /// Get the subscriptions of a channel.
+    pub fn get_channel_subscriptions(
+        &self,
+        channel: &Origin,
+    ) -> Result<Vec<SubscriptionData>, StorageError> {
+        let channel = channel.to_string();
+        Ok(self
+            .get_subscriptions()?
+            .into_iter()
+            .filter(|subscription| subscription.channel == channel)
+            .collect())
+    }
+
+    /// Get the subscriptions to the events of a network.
+    pub fn get_network_subscriptions(
+        &self,
+        network: &str,
+    ) -> Result<Vec<SubscriptionData>, StorageError> {
+        Ok(self
+            .get_subscriptions()?
+            .into_iter()
+            .filter(|subscription| subscription.network.eq_ignore_ascii_case(network))
+            .collect())
+    }
+}
@@ -0,0 +1,41 @@
+use kv::Json;
+
+use super::storage::{PendingTipData, Storage, StorageError};
+use crate::origin::Origin;
+
+impl<'a> Storage<'a> {
+    /// Hold a tip until its recipient claims it.
+    pub fn add_pending_tip(&self, tip: &PendingTipData) -> Result<(), StorageError> {
+        self.pending_tips.set(&tip.id, &Json(tip.clone()))?;
+        Ok(())
+    }
+
+    /// Get the tips waiting to be claimed by a user, oldest first.
+    ///
+    /// Tips to any origin linked to the user are included.
+    pub fn get_pending_tips(&self, origin: &Origin) -> Result<Vec<PendingTipData>, StorageError> {
+        let origins: Vec<String> = self
+            .get_identity_origins(&self.get_identity(origin)?)?
+            .iter()
+            .map(Origin::to_string)
+            .collect();
+
+        let mut tips = Vec::new();
+        for item in self.pending_tips.iter() {
+            let Json(tip) = item?.value::<Json<PendingTipData>>()?;
+            if origins.contains(&tip.to) {
+                tips.push(tip);
+            }
+        }
+        tips.sort_by_key(|tip| tip.created);
+        Ok(tips)
+    }
+
+    /// Take a pending tip, which can only be done once.
+    pub fn take_pending_tip(&self, id: &str) -> Result<PendingTipData, StorageError> {
+        match self.pending_tips.remove(&id.to_string())? {
+            Some(Json(tip)) => Ok(tip),
+            None => Err(StorageError::NotFound),
+        }
+    }
+}
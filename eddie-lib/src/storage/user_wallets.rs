@@ -1,38 +1,245 @@
-use super::storage::{Storage, StorageError};
+use kv::Json;
+use std::collections::{HashMap, HashSet};
+
+use super::storage::{
+    ChallengeData, Storage, StorageError, UserWalletsData, WalletData, DEFAULT_WALLET_LABEL,
+};
 use crate::origin::Origin;
 
 impl<'a> Storage<'a> {
     /// Set a user wallet value.
     ///
-    /// Overwrites existing values.
+    /// Replaces the address of the user's default wallet, or adds a default
+    /// wallet when the user has none. The wallet stays verified when the same
+    /// address is set again. The address is expected to be validated by the
+    /// caller.
     pub fn set_user_wallet(&self, origin: Origin, pub_addr: String) -> Result<(), StorageError> {
-        // TODO: Check if the address is valid.
+        let mut data = self.get_user_wallets(&origin)?;
+        match data.wallets.iter_mut().find(|wallet| wallet.default) {
+            Some(wallet) => {
+                wallet.verified &= wallet.address == pub_addr;
+                wallet.address = pub_addr;
+            }
+            None => {
+                let mut wallet = WalletData::new(DEFAULT_WALLET_LABEL, pub_addr, None);
+                wallet.default = true;
+                data.wallets.push(wallet);
+            }
+        }
 
         // Store the wallet
-        self.user_wallets.set(&origin.to_string(), &pub_addr)?;
-        Ok(())
+        self.set_user_wallets(&origin, data)
     }
 
-    /// Get the public address from a user origin.
-    pub fn get_user_wallet(&self, origin: Origin) -> Result<String, StorageError> {
+    /// Add a wallet to the wallets of a user.
+    ///
+    /// Returns an error if the user already has a wallet with the same label.
+    /// The first wallet of a user becomes the default.
+    pub fn add_user_wallet(&self, origin: &Origin, wallet: WalletData) -> Result<(), StorageError> {
+        let mut data = self.get_user_wallets(origin)?;
+        if data.get(&wallet.label).is_some() {
+            return Err(StorageError::NotUnique);
+        }
+
+        data.wallets.push(wallet);
+        self.set_user_wallets(origin, data)
+    }
+
+    /// Remove a wallet of a user by label.
+    pub fn remove_user_wallet(&self, origin: &Origin, label: &str) -> Result<(), StorageError> {
+        let mut data = self.get_user_wallets(origin)?;
+        let label = label.trim().to_lowercase();
+        let count = data.wallets.len();
+        data.wallets.retain(|wallet| wallet.label != label);
+        if data.wallets.len() == count {
+            return Err(StorageError::NotFound);
+        }
+
+        self.set_user_wallets(origin, data)
+    }
+
+    /// Make a wallet of a user the default.
+    pub fn set_default_user_wallet(
+        &self,
+        origin: &Origin,
+        label: &str,
+    ) -> Result<(), StorageError> {
+        let mut data = self.get_user_wallets(origin)?;
+        let label = label.trim().to_lowercase();
+        if data.get(&label).is_none() {
+            return Err(StorageError::NotFound);
+        }
+
+        for wallet in data.wallets.iter_mut() {
+            wallet.default = wallet.label == label;
+        }
+        self.set_user_wallets(origin, data)
+    }
+
+    /// Get all wallets of a user.
+    pub fn get_user_wallets(&self, origin: &Origin) -> Result<UserWalletsData, StorageError> {
         Ok(self
             .user_wallets
-            .get(&origin.to_string())?
-            .ok_or(StorageError::NotFound)?)
+            .get(&self.get_identity(origin)?)?
+            .unwrap_or_default())
+    }
+
+    /// Store the wallets of a user.
+    ///
+    /// The wallets are shared by all origins linked to the user's identity.
+    fn set_user_wallets(
+        &self,
+        origin: &Origin,
+        mut data: UserWalletsData,
+    ) -> Result<(), StorageError> {
+        data.ensure_default();
+        let data = Some(data).filter(|data| !data.wallets.is_empty());
+        self.write_user_wallets(&self.get_identity(origin)?, data.as_ref())
+    }
+
+    /// Store or remove the wallets of an identity, updating the address
+    /// index in the same transaction.
+    pub(super) fn write_user_wallets(
+        &self,
+        identity: &str,
+        data: Option<&UserWalletsData>,
+    ) -> Result<(), StorageError> {
+        let identity = identity.to_string();
+        self.user_wallets
+            .transaction2(&self.wallet_addresses, |wallets, index| {
+                let old = match data {
+                    Some(data) => wallets.set(&identity, data)?,
+                    None => wallets.remove(&identity)?,
+                };
+                let old: HashSet<String> = old
+                    .into_iter()
+                    .flat_map(|data| data.wallets)
+                    .map(|wallet| wallet.address)
+                    .collect();
+                let new: HashSet<String> = data
+                    .into_iter()
+                    .flat_map(|data| data.wallets.iter())
+                    .map(|wallet| wallet.address.clone())
+                    .collect();
+
+                // Drop the mappings of overwritten or removed wallets.
+                for address in old.difference(&new) {
+                    let mut identities = index.get(address)?.map(|Json(v)| v).unwrap_or_default();
+                    identities.retain(|id| id != &identity);
+                    match identities.is_empty() {
+                        true => index.remove(address)?,
+                        false => index.set(address, &Json(identities))?,
+                    };
+                }
+                // Unchanged wallets are indexed again, which repairs entries
+                // missing from older databases.
+                for address in &new {
+                    let mut identities = index.get(address)?.map(|Json(v)| v).unwrap_or_default();
+                    if !identities.contains(&identity) {
+                        identities.push(identity.clone());
+                        index.set(address, &Json(identities))?;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(StorageError::from)
+    }
+
+    /// Rebuild the address index from the stored wallets.
+    ///
+    /// Databases of older versions of the bot have no index. Returns the
+    /// number of indexed addresses.
+    pub fn rebuild_wallet_index(&self) -> Result<usize, StorageError> {
+        let mut index: HashMap<String, Vec<String>> = HashMap::new();
+        for item in self.user_wallets.iter() {
+            let item = item?;
+            let identity = item.key::<String>()?;
+            for wallet in item.value::<UserWalletsData>()?.wallets {
+                let identities = index.entry(wallet.address).or_default();
+                if !identities.contains(&identity) {
+                    identities.push(identity.clone());
+                }
+            }
+        }
+
+        self.wallet_addresses.clear()?;
+        let mut batch = kv::Batch::new();
+        for (address, identities) in &index {
+            batch.set(address, &Json(identities.clone()))?;
+        }
+        self.wallet_addresses.batch(batch)?;
+        Ok(index.len())
+    }
+
+    /// Get the public address from a user origin.
+    pub fn get_user_wallet(&self, origin: Origin) -> Result<String, StorageError> {
+        Ok(self.get_user_wallet_data(origin)?.address)
+    }
+
+    /// Get the default wallet of a user origin.
+    pub fn get_user_wallet_data(&self, origin: Origin) -> Result<WalletData, StorageError> {
+        self.get_user_wallets(&origin)?
+            .default_wallet()
+            .cloned()
+            .ok_or(StorageError::NotFound)
+    }
+
+    /// Mark the wallets of a user with the given address as verified.
+    pub fn verify_user_wallet(&self, origin: Origin, pub_addr: &str) -> Result<(), StorageError> {
+        let mut data = self.get_user_wallets(&origin)?;
+        let mut found = false;
+        for wallet in data.wallets.iter_mut() {
+            if wallet.address == pub_addr {
+                wallet.verified = true;
+                found = true;
+            }
+        }
+        if !found {
+            return Err(StorageError::NotFound);
+        }
+
+        self.set_user_wallets(&origin, data)
     }
 
     /// Get all origins using the same public address.
     ///
-    /// This can happen when users use both Discord and Telegram.
+    /// This can happen when users use both Discord and Telegram, with or
+    /// without linking their accounts.
     pub fn get_pub_address_origins(&self, pub_addr: String) -> Result<Vec<Origin>, StorageError> {
         let mut origins: Vec<Origin> = Vec::new();
-        for item in self.user_wallets.iter() {
-            let item = item?;
-            if item.value::<String>()? == pub_addr {
-                let key = item.key::<String>()?;
-                origins.push(Origin::try_from(key).map_err(|_| StorageError::InvalidOrigin)?)
+        if let Some(Json(identities)) = self.wallet_addresses.get(&pub_addr)? {
+            for identity in identities {
+                origins.extend(self.get_identity_origins(&identity)?);
             }
         }
         Ok(origins)
     }
+
+    /// Store a wallet verification challenge of a user, replacing an earlier
+    /// one.
+    pub fn set_challenge(
+        &self,
+        origin: &Origin,
+        challenge: ChallengeData,
+    ) -> Result<(), StorageError> {
+        self.challenges
+            .set(&self.get_identity(origin)?, &Json(challenge))?;
+        Ok(())
+    }
+
+    /// Get the wallet verification challenge of a user.
+    ///
+    /// Expired challenges are treated as missing.
+    pub fn get_challenge(&self, origin: &Origin, now: u64) -> Result<ChallengeData, StorageError> {
+        match self.challenges.get(&self.get_identity(origin)?)? {
+            Some(Json(challenge)) if challenge.expires > now => Ok(challenge),
+            _ => Err(StorageError::NotFound),
+        }
+    }
+
+    /// Remove the wallet verification challenge of a user.
+    pub fn remove_challenge(&self, origin: &Origin) -> Result<(), StorageError> {
+        self.challenges.remove(&self.get_identity(origin)?)?;
+        Ok(())
+    }
 }
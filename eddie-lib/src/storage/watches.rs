@@ -0,0 +1,157 @@
+use kv::Json;
+
+use super::storage::{HeldMessagesData, QuietHoursData, Storage, StorageError, WatchData};
+use crate::origin::Origin;
+
+/// Number of messages held for a user, older ones are dropped.
+const MAX_HELD_MESSAGES: usize = 50;
+
+impl<'a> Storage<'a> {
+    /// Start watching an address for a user.
+    ///
+    /// Returns an error if the user already watches the address on the
+    /// network.
+    pub fn add_watch(&self, watch: &WatchData) -> Result<(), StorageError> {
+        let origin =
+            Origin::try_from(watch.origin.clone()).map_err(|_| StorageError::InvalidOrigin)?;
+        if self
+            .get_watches(&origin)?
+            .iter()
+            .any(|w| w.network.eq_ignore_ascii_case(&watch.network) && w.address == watch.address)
+        {
+            return Err(StorageError::NotUnique);
+        }
+
+        self.watches.set(&watch.id, &Json(watch.clone()))?;
+        Ok(())
+    }
+
+    /// Stop watching an address of a user.
+    pub fn remove_watch(&self, origin: &Origin, id: &str) -> Result<(), StorageError> {
+        if !self.get_watches(origin)?.iter().any(|watch| watch.id == id) {
+            return Err(StorageError::NotFound);
+        }
+        self.watches.remove(&id.to_string())?;
+        Ok(())
+    }
+
+    /// Get the watches of a user, including those made from linked
+    /// accounts.
+    pub fn get_watches(&self, origin: &Origin) -> Result<Vec<WatchData>, StorageError> {
+        let identity = self.get_identity(origin)?;
+        let mut watches = Vec::new();
+        for item in self.watches.iter() {
+            let watch = item?.value::<Json<WatchData>>()?.0;
+            let Ok(watch_origin) = Origin::try_from(watch.origin.clone()) else {
+                continue;
+            };
+            if self.get_identity(&watch_origin)? == identity {
+                watches.push(watch);
+            }
+        }
+        Ok(watches)
+    }
+
+    /// Get the watched addresses of all users.
+    pub fn get_all_watches(&self) -> Result<Vec<WatchData>, StorageError> {
+        let mut watches = Vec::new();
+        for item in self.watches.iter() {
+            watches.push(item?.value::<Json<WatchData>>()?.0);
+        }
+        Ok(watches)
+    }
+
+    /// Get the watched addresses of a network.
+    pub fn get_network_watches(&self, network: &str) -> Result<Vec<WatchData>, StorageError> {
+        let mut watches = Vec::new();
+        for item in self.watches.iter() {
+            let watch = item?.value::<Json<WatchData>>()?.0;
+            if watch.network.eq_ignore_ascii_case(network) {
+                watches.push(watch);
+            }
+        }
+        Ok(watches)
+    }
+
+    /// Set or clear the quiet hours of a user.
+    pub fn set_quiet_hours(
+        &self,
+        origin: &Origin,
+        quiet_hours: Option<QuietHoursData>,
+    ) -> Result<(), StorageError> {
+        let identity = self.get_identity(origin)?;
+        match quiet_hours {
+            Some(quiet_hours) => self.quiet_hours.set(&identity, &Json(quiet_hours))?,
+            None => self.quiet_hours.remove(&identity)?,
+        };
+        Ok(())
+    }
+
+    /// Get the quiet hours of a user.
+    pub fn get_quiet_hours(&self, origin: &Origin) -> Result<Option<QuietHoursData>, StorageError> {
+        Ok(self
+            .quiet_hours
+            .get(&self.get_identity(origin)?)?
+            .map(|Json(quiet_hours)| quiet_hours))
+    }
+
+    /// Keep a message for a user until their quiet hours are over.
+    ///
+    /// Only the latest `MAX_HELD_MESSAGES` are kept, older ones are counted.
+    pub fn hold_message(&self, origin: &Origin, msg: String) -> Result<(), StorageError> {
+        let key = origin.to_string();
+        let mut held = self
+            .held_messages
+            .get(&key)?
+            .map(|Json(held)| held)
+            .unwrap_or_default();
+        held.messages.push(msg);
+        if held.messages.len() > MAX_HELD_MESSAGES {
+            held.messages.remove(0);
+            held.dropped += 1;
+        }
+        self.held_messages.set(&key, &Json(held))?;
+        Ok(())
+    }
+
+    /// Get the held messages of the users whose quiet hours are over at the
+    /// given unix timestamp.
+    ///
+    /// They're kept until they're removed with `remove_held_messages`.
+    pub fn get_released_messages(
+        &self,
+        now: u64,
+    ) -> Result<Vec<(Origin, HeldMessagesData)>, StorageError> {
+        let mut released = Vec::new();
+        for item in self.held_messages.iter() {
+            let item = item?;
+            let Ok(origin) = Origin::try_from(item.key::<String>()?) else {
+                continue;
+            };
+            if self
+                .get_quiet_hours(&origin)?
+                .is_some_and(|quiet_hours| quiet_hours.is_quiet(now))
+            {
+                continue;
+            }
+            released.push((origin, item.value::<Json<HeldMessagesData>>()?.0));
+        }
+        Ok(released)
+    }
+
+    /// Remove the oldest `count` held messages of a user once they were
+    /// delivered, along with the count of dropped ones.
+    pub fn remove_held_messages(&self, origin: &Origin, count: usize) -> Result<(), StorageError> {
+        let key = origin.to_string();
+        let Some(Json(mut held)) = self.held_messages.get(&key)? else {
+            return Ok(());
+        };
+        held.messages.drain(..count.min(held.messages.len()));
+        held.dropped = 0;
+        match held.messages.is_empty() {
+            true => self.held_messages.remove(&key)?,
+            false => self.held_messages.set(&key, &Json(held))?,
+        };
+        Ok(())
+    }
+}
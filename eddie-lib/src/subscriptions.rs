@@ -0,0 +1,235 @@
+use crate::{
+    bot::Bot,
+    call::Response,
+    chain::{self, ChainEvent},
+    config::Config,
+    crypto, governance,
+    origin::Origin,
+    storage::{
+        storage::{EventFilter, NetworkData, StorageError, SubscriptionData},
+        Storage,
+    },
+};
+use support::traits::{dispatch::DispatchError, Get};
+
+/// Type to make function definitions a bit cleaner.
+type DispatchResult<T> = support::traits::dispatch::DispatchResult<Option<T>>;
+
+/// Format an event for a channel.
+fn event_message(network: &NetworkData, block: &str, event: &ChainEvent) -> String {
+    let details = match &event.transfer {
+        Some((from, to, amount)) => format!(
+            "{} from {} to {}",
+            network.chain.format_balance(*amount),
+            network.chain.address_of(from),
+            network.chain.address_of(to)
+        ),
+        None => event.fields.clone(),
+    };
+    format!(
+        "[{}] {}.{}: {}\nBlock: {}",
+        network.name, event.pallet, event.variant, details, block
+    )
+}
+
+impl<T: Config> Bot<T> {
+    pub fn subscribe(
+        who: Origin,
+        channel: &Origin,
+        network: &str,
+        filter: &str,
+    ) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        let network = Self::get_network(&storage, network)?;
+        let filter = EventFilter::try_from(filter)
+            .map_err(|err| DispatchError::Module(crate::MODULE.into(), err.into()))?;
+        let subscription = SubscriptionData {
+            id: crypto::random_hex(4),
+            channel: channel.to_string(),
+            network: network.name.clone(),
+            filter,
+        };
+        storage
+            .add_subscription(&subscription)
+            .map_err(|err| match err {
+                StorageError::NotUnique => DispatchError::Module(
+                    crate::MODULE.into(),
+                    "This channel already follows these events".into(),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+
+        Ok(Some(Response::Reply(format!(
+            "This channel now follows {} on {}. Unsubscribe with id {}.",
+            subscription.filter, network.name, subscription.id
+        ))))
+    }
+
+    pub fn unsubscribe(who: Origin, channel: &Origin, id: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        // Reject if the caller isn't an admin.
+        if !storage.is_admin(who) {
+            return Err(DispatchError::Module(
+                crate::MODULE.into(),
+                "Only an admin can perform this action".into(),
+            ));
+        }
+
+        storage
+            .remove_subscription(channel, id.trim())
+            .map_err(|err| match err {
+                StorageError::NotFound => DispatchError::Module(
+                    crate::MODULE.into(),
+                    format!("This channel has no subscription {}", id),
+                ),
+                err => DispatchError::Other(err.to_string()),
+            })?;
+        Ok(Some(Response::Reply(format!(
+            "Removed subscription {}.",
+            id
+        ))))
+    }
+
+    pub fn list_subscriptions(channel: &Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let subscriptions = storage
+            .get_channel_subscriptions(channel)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if subscriptions.is_empty() {
+            return Ok(Some(Response::Reply(
+                "This channel doesn't follow any events.".into(),
+            )));
+        }
+
+        let lines: Vec<String> = subscriptions
+            .iter()
+            .map(|s| format!("{}: {} on {}", s.id, s.filter, s.network))
+            .collect();
+        Ok(Some(Response::Reply(format!(
+            "Subscriptions of this channel:\n{}",
+            lines.join("\n")
+        ))))
+    }
+
+    /// Get the networks channels follow events of, or users watch
+    /// addresses on.
+    pub fn subscribed_networks() -> Result<Vec<String>, DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let mut networks: Vec<String> = storage
+            .get_subscriptions()
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .into_iter()
+            .map(|subscription| subscription.network)
+            .collect();
+        networks.extend(
+            storage
+                .get_all_watches()
+                .map_err(|err| DispatchError::Other(err.to_string()))?
+                .into_iter()
+                .map(|watch| watch.network),
+        );
+        networks.sort();
+        networks.dedup();
+        Ok(networks)
+    }
+
+    /// Follow the events of a network and pass a message for every
+    /// subscription an event matches, and every watched address it concerns,
+    /// to `post`.
+    ///
+    /// Blocks for as long as channels follow the network or users watch
+    /// addresses on it. The subscriptions and watches are read again for
+    /// every block, so changes apply right away.
+    pub fn watch_events(
+        network: &str,
+        mut post: impl FnMut(Response),
+    ) -> Result<(), DispatchError> {
+        let network = {
+            let storage = Storage::new(&T::DBPath::get().to_owned())
+                .map_err(|err| DispatchError::Other(err.to_string()))?;
+            Self::get_network(&storage, network)?
+        };
+        let api = chain::connect_any(&network.rpc_urls)?;
+
+        let mut result = Ok(());
+        let mut referenda_api = None;
+        chain::watch_events(&api, |block, events| {
+            let storage = match Storage::new(&T::DBPath::get().to_owned()) {
+                Ok(storage) => storage,
+                Err(err) => {
+                    result = Err(DispatchError::Other(err.to_string()));
+                    return false;
+                }
+            };
+            let (subscriptions, watches) = match storage
+                .get_network_subscriptions(&network.name)
+                .and_then(|subscriptions| {
+                    Ok((subscriptions, storage.get_network_watches(&network.name)?))
+                }) {
+                Ok(followed) => followed,
+                Err(err) => {
+                    result = Err(DispatchError::Other(err.to_string()));
+                    return false;
+                }
+            };
+
+            let block = format!("{:?}", block);
+            for event in &events {
+                let channels: Vec<Origin> = subscriptions
+                    .iter()
+                    .filter(|subscription| subscription.filter.matches(event))
+                    .filter_map(|subscription| Origin::try_from(subscription.channel.clone()).ok())
+                    .collect();
+                if channels.is_empty() {
+                    continue;
+                }
+
+                // Referenda are described with their tally and the time left,
+                // which are looked up over a connection of their own.
+                let lookup = event.referendum.map(|index| {
+                    let api = match referenda_api {
+                        Some(ref api) => api,
+                        None => referenda_api.insert(chain::connect_any(&network.rpc_urls)?),
+                    };
+                    governance::referendum_message(api, &network, index, Some(&event.variant))
+                });
+                let msg = match lookup {
+                    Some(Ok(msg)) => msg,
+                    Some(Err(err)) => {
+                        log::warn!("Couldn't look up a referendum on {}: {}", network.name, err);
+                        event_message(&network, &block, event)
+                    }
+                    None => event_message(&network, &block, event),
+                };
+                for channel in channels {
+                    post(Response::SayChan(channel, msg.clone()));
+                }
+            }
+            if let Err(err) =
+                Self::notify_watchers(&storage, &network, &watches, &events, &mut post)
+            {
+                log::warn!("Couldn't notify the watchers on {}: {}", network.name, err);
+            }
+            !subscriptions.is_empty() || !watches.is_empty()
+        })?;
+        result
+    }
+}
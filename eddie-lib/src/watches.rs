@@ -0,0 +1,337 @@
+use crate::{
+    bot::Bot,
+    call::Response,
+    chain::{self, ChainEvent},
+    config::Config,
+    crypto,
+    functions::now,
+    origin::Origin,
+    storage::{
+        storage::{NetworkData, QuietHoursData, StorageError, WatchData},
+        Storage,
+    },
+};
+use support::traits::{dispatch::DispatchError, Get};
+
+/// Type to make function definitions a bit cleaner.
+type DispatchResult<T> = support::traits::dispatch::DispatchResult<Option<T>>;
+
+/// Longest message Discord accepts, in characters.
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// Longest message Telegram accepts, in characters.
+const TELEGRAM_MESSAGE_LIMIT: usize = 4096;
+
+/// A message with some of the messages held back for a user.
+pub struct HeldMessage {
+    pub response: Response,
+    pub origin: Origin,
+    /// Number of held messages it contains.
+    pub count: usize,
+}
+
+/// Join messages under a header into as few messages of at most `limit`
+/// characters as possible, with the number of messages each contains.
+///
+/// Messages that don't fit on their own are cut short.
+fn split_messages(header: &str, messages: &[String], limit: usize) -> Vec<(String, usize)> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::from(header);
+    let mut count = 0;
+    for msg in messages {
+        if count > 0 && chunk.chars().count() + 1 + msg.chars().count() > limit {
+            chunks.push((std::mem::take(&mut chunk), count));
+            count = 0;
+        }
+        if !chunk.is_empty() {
+            chunk.push('\n');
+        }
+        let room = limit.saturating_sub(chunk.chars().count());
+        match msg.chars().count() > room {
+            true => {
+                chunk.extend(msg.chars().take(room.saturating_sub(1)));
+                chunk.push('…');
+            }
+            false => chunk.push_str(msg),
+        }
+        count += 1;
+    }
+    if count > 0 {
+        chunks.push((chunk, count));
+    }
+    chunks
+}
+
+/// Format an event for the owner of a watched address, or `None` if the
+/// event doesn't concern the address.
+fn watch_message(network: &NetworkData, watch: &WatchData, event: &ChainEvent) -> Option<String> {
+    let chain_info = &network.chain;
+    let name = match &watch.label {
+        Some(label) => format!("Your wallet {}", label),
+        None => format!("Your address {}", watch.address),
+    };
+    let is_watched = |account| chain::to_address(account) == watch.address;
+
+    if let Some((from, to, amount)) = &event.transfer {
+        let amount = chain_info.format_balance(*amount);
+        return match (is_watched(from), is_watched(to)) {
+            (_, true) => Some(format!(
+                "{} received {} from {} on {}.",
+                name,
+                amount,
+                chain_info.address_of(from),
+                network.name
+            )),
+            (true, false) => Some(format!(
+                "{} sent {} to {} on {}.",
+                name,
+                amount,
+                chain_info.address_of(to),
+                network.name
+            )),
+            (false, false) => None,
+        };
+    }
+
+    let (account, amount) = event.account.as_ref()?;
+    if !is_watched(account) {
+        return None;
+    }
+    let amount = chain_info.format_balance(*amount);
+    Some(match (event.pallet.as_str(), event.variant.as_str()) {
+        (_, "Slashed") => format!("{} was slashed {} on {}.", name, amount, network.name),
+        (_, "Locked") | (_, "Frozen") => {
+            format!("{} had {} locked on {}.", name, amount, network.name)
+        }
+        (_, "Unlocked") | (_, "Thawed") => {
+            format!("{} had {} unlocked on {}.", name, amount, network.name)
+        }
+        (pallet, variant) => format!(
+            "{} was affected by {}.{} of {} on {}.",
+            name, pallet, variant, amount, network.name
+        ),
+    })
+}
+
+impl<T: Config> Bot<T> {
+    pub fn watch(who: Origin, network: &str, target: Option<&str>) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let network = Self::get_network(&storage, network)?;
+        let address = Self::resolve_address(&storage, &who, target, Some(&network.name))?;
+        let account = chain::parse_network_address(&address, network.chain.ss58_prefix)?;
+        let address = chain::to_address(&account);
+        let label = storage
+            .get_user_wallets(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+            .wallets
+            .iter()
+            .find(|wallet| wallet.address == address)
+            .map(|wallet| wallet.label.clone());
+
+        let watch = WatchData {
+            id: crypto::random_hex(4),
+            origin: who.to_string(),
+            network: network.name.clone(),
+            address,
+            label,
+        };
+        storage.add_watch(&watch).map_err(|err| match err {
+            StorageError::NotUnique => DispatchError::Module(
+                crate::MODULE.into(),
+                "You already watch this address".into(),
+            ),
+            err => DispatchError::Other(err.to_string()),
+        })?;
+
+        Ok(Some(Response::Reply(format!(
+            "You'll get a message when funds of {} move on {}. Unwatch with id {}.",
+            network.chain.address_of(&account),
+            network.name,
+            watch.id
+        ))))
+    }
+
+    pub fn unwatch(who: Origin, id: &str) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        storage
+            .remove_watch(&who, id.trim())
+            .map_err(|err| match err {
+                StorageError::NotFound => {
+                    DispatchError::Module(crate::MODULE.into(), format!("You have no watch {}", id))
+                }
+                err => DispatchError::Other(err.to_string()),
+            })?;
+        Ok(Some(Response::Reply(format!("Removed watch {}.", id))))
+    }
+
+    pub fn list_watches(who: Origin) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let watches = storage
+            .get_watches(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let quiet_hours = storage
+            .get_quiet_hours(&who)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        if watches.is_empty() {
+            return Ok(Some(Response::Reply("You don't watch any address.".into())));
+        }
+
+        let lines: Vec<String> = watches
+            .iter()
+            .map(|watch| match &watch.label {
+                Some(label) => format!(
+                    "{}: {} ({}) on {}",
+                    watch.id, label, watch.address, watch.network
+                ),
+                None => format!("{}: {} on {}", watch.id, watch.address, watch.network),
+            })
+            .collect();
+        let quiet_hours = match quiet_hours {
+            Some(quiet_hours) => format!(
+                "Quiet hours: {}:00 to {}:00 UTC",
+                quiet_hours.start, quiet_hours.end
+            ),
+            None => "No quiet hours".into(),
+        };
+        Ok(Some(Response::Reply(format!(
+            "Your watches:\n{}\n\n{}",
+            lines.join("\n"),
+            quiet_hours
+        ))))
+    }
+
+    /// Set the hours messages about watched addresses are held back, `None`
+    /// clears them.
+    pub fn set_quiet_hours(who: Origin, quiet_hours: Option<&str>) -> DispatchResult<Response> {
+        // Get the storage.
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        let quiet_hours = quiet_hours
+            .map(QuietHoursData::try_from)
+            .transpose()
+            .map_err(|err| DispatchError::Module(crate::MODULE.into(), err.into()))?;
+        storage
+            .set_quiet_hours(&who, quiet_hours)
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+
+        Ok(Some(Response::Reply(match quiet_hours {
+            Some(quiet_hours) => format!(
+                "Messages about your watches and validators are held from {}:00 to {}:00 UTC.",
+                quiet_hours.start, quiet_hours.end
+            ),
+            None => "Quiet hours are off.".into(),
+        })))
+    }
+
+    /// Get the messages held back during quiet hours that are over, split to
+    /// fit the platforms.
+    ///
+    /// Runs in the background. The messages are only removed by
+    /// `release_held_messages` once they were delivered.
+    pub fn held_messages() -> Result<Vec<HeldMessage>, DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        let mut released = Vec::new();
+        for (origin, held) in storage
+            .get_released_messages(now())
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+        {
+            let mut header = String::from("While you were away:");
+            if held.dropped > 0 {
+                header.push_str(&format!(" ({} older message(s) left out)", held.dropped));
+            }
+            let limit = match origin {
+                Origin::Discord(_) => DISCORD_MESSAGE_LIMIT,
+                Origin::Telegram(_) => TELEGRAM_MESSAGE_LIMIT,
+            };
+            for (msg, count) in split_messages(&header, &held.messages, limit) {
+                released.push(HeldMessage {
+                    response: Response::SayDirect(origin.clone(), msg),
+                    origin: origin.clone(),
+                    count,
+                });
+            }
+        }
+        Ok(released)
+    }
+
+    /// Remove the oldest `count` held messages of a user, once they were
+    /// delivered.
+    pub fn release_held_messages(origin: &Origin, count: usize) -> Result<(), DispatchError> {
+        let storage = Storage::new(&T::DBPath::get().to_owned())
+            .map_err(|err| DispatchError::Other(err.to_string()))?;
+        storage
+            .remove_held_messages(origin, count)
+            .map_err(|err| DispatchError::Other(err.to_string()))
+    }
+
+    /// Get a direct message to a user, or hold it back until their quiet
+    /// hours are over.
+    pub(crate) fn direct_message(
+        storage: &Storage,
+        origin: &Origin,
+        msg: String,
+    ) -> Result<Option<Response>, StorageError> {
+        if storage
+            .get_quiet_hours(origin)?
+            .is_some_and(|quiet_hours| quiet_hours.is_quiet(now()))
+        {
+            storage.hold_message(origin, msg)?;
+            return Ok(None);
+        }
+        Ok(Some(Response::SayDirect(origin.clone(), msg)))
+    }
+
+    /// Pass a message to `post` for every watched address a block's events
+    /// concern, or hold it until the quiet hours of its owner are over.
+    pub(crate) fn notify_watchers(
+        storage: &Storage,
+        network: &NetworkData,
+        watches: &[WatchData],
+        events: &[ChainEvent],
+        post: &mut impl FnMut(Response),
+    ) -> Result<(), StorageError> {
+        for watch in watches {
+            let Ok(origin) = Origin::try_from(watch.origin.clone()) else {
+                continue;
+            };
+            for msg in events
+                .iter()
+                .filter_map(|event| watch_message(network, watch, event))
+            {
+                if let Some(response) = Self::direct_message(storage, &origin, msg)? {
+                    post(response);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_messages;
+
+    #[test]
+    fn split_messages_works() {
+        let messages: Vec<String> = ["a".repeat(5), "b".repeat(5), "c".repeat(25)].into();
+        assert_eq!(
+            split_messages("head", &messages, 20),
+            vec![
+                (format!("head\n{}\n{}", "a".repeat(5), "b".repeat(5)), 2),
+                (format!("{}…", "c".repeat(19)), 1),
+            ]
+        );
+        assert!(split_messages("head", &[], 20).is_empty());
+    }
+}
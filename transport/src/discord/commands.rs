@@ -1,22 +1,42 @@
-use crate::discord::discord::{Context, Error};
-use eddie_lib::{origin::Origin, Call, Config as BotConfig, Response};
-use poise::serenity_prelude::ChannelId;
-use support::traits::Dispatch;
+use crate::{
+    discord::discord::{ApplicationContext, Context, Error},
+    DispatchBlocking,
+};
+use eddie_lib::{
+    origin::Origin, storage::storage::FaucetSetting, Bot, Call, Config as BotConfig, Response,
+};
+use poise::serenity_prelude::{self as serenity, ChannelId, UserId};
+use support::traits::dispatch::DispatchError;
 
 use super::Config;
 
 async fn parse_call<T: Config + BotConfig>(
     ctx: Context<'_, T>,
     response: Option<Response>,
+) -> Result<(), Error> {
+    let responses = match response {
+        None => vec![],
+        Some(Response::Multiple(responses)) => responses,
+        Some(response) => vec![response],
+    };
+
+    for response in responses {
+        parse_response(ctx, response).await?;
+    }
+    Ok(())
+}
+
+async fn parse_response<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    response: Response,
 ) -> Result<(), Error> {
     match response {
-        None => {}
-        Some(Response::Say(msg)) => {
+        Response::Say(msg) => {
             if let Err(err) = ctx.say(msg).await {
                 log::error!("Couldn't send message to Discord: {:?}", err);
             }
         }
-        Some(Response::SayChan(reply_channel, msg)) => {
+        Response::SayChan(reply_channel, msg) => {
             if let Ok(channel_id) = reply_channel.inner().parse::<u64>() {
                 let channel = ChannelId::from(channel_id);
                 if let Err(err) = channel.say(ctx.http(), msg).await {
@@ -26,17 +46,44 @@ async fn parse_call<T: Config + BotConfig>(
                 log::error!("Invalid Discord channel ID: {}", reply_channel.inner())
             }
         }
-        Some(Response::Reply(msg)) => {
+        Response::Reply(msg) => {
             if let Err(err) = ctx.reply(msg).await {
                 log::error!("Couldn't send reply to Discord: {:?}", err);
             }
         }
-        Some(Response::ReplyDirect(msg)) => {
+        Response::ReplyDirect(msg) => {
             let dm_channel = ctx.author().id.create_dm_channel(ctx.http()).await?;
             if let Err(why) = dm_channel.say(ctx.http(), msg).await {
                 log::error!("Couldn't send DM to Discord: {:?}", why);
             }
         }
+        Response::ReplyTracked(msg, handle) => {
+            let mut reply = ctx.reply(msg).await?.into_message().await?;
+
+            // Don't hold up the command while the reply is being updated.
+            let http = ctx.serenity_context().http.clone();
+            tokio::spawn(async move {
+                while let Some(update) = crate::next_update(&handle).await {
+                    if let Err(why) = reply
+                        .edit(&http, serenity::EditMessage::new().content(update))
+                        .await
+                    {
+                        log::error!("Couldn't edit reply on Discord: {:?}", why);
+                    }
+                }
+            });
+        }
+        Response::SayDirect(user, msg) => {
+            if let Ok(user_id) = user.inner().parse::<u64>() {
+                let dm_channel = UserId::from(user_id).create_dm_channel(ctx.http()).await?;
+                if let Err(why) = dm_channel.say(ctx.http(), msg).await {
+                    log::error!("Couldn't send DM to Discord: {:?}", why);
+                }
+            } else {
+                log::error!("Invalid Discord user ID: {}", user.inner())
+            }
+        }
+        Response::Multiple(_) => log::error!("Nested multiple responses aren't supported"),
     }
     Ok(())
 }
@@ -67,7 +114,7 @@ pub async fn help<T: Config + BotConfig>(
 #[poise::command(prefix_command, slash_command)]
 pub async fn info<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
     let origin = Origin::Discord(ctx.author().id.to_string());
-    parse_call(ctx, Call::<T>::Info.dispatch(origin)?).await
+    parse_call(ctx, Call::<T>::Info.dispatch_blocking(origin).await?).await
     // if let Some(Response::Reply(info)) = (Call::<T>::Info).dispatch(origin)? {
     //     log::info!("Received bot response: {:?}", info);
     //     if let Err(why) = ctx.reply(info).await {
@@ -84,7 +131,7 @@ pub async fn info<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Erro
 #[poise::command(prefix_command, slash_command)]
 pub async fn init<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
     let origin = Origin::Discord(ctx.author().id.to_string());
-    parse_call(ctx, (Call::<T>::Init).dispatch(origin)?).await
+    parse_call(ctx, (Call::<T>::Init).dispatch_blocking(origin).await?).await
     // (Call::<T>::Init).dispatch(origin)?;
     // Ok(())
 }
@@ -102,7 +149,9 @@ pub async fn set_admin<T: Config + BotConfig>(
     let admin_origin = Origin::try_from(admin)?;
     parse_call(
         ctx,
-        (Call::<T>::SetAdmin(admin_origin, remove)).dispatch(origin)?,
+        (Call::<T>::SetAdmin(admin_origin, remove))
+            .dispatch_blocking(origin)
+            .await?,
     )
     .await
 }
@@ -116,7 +165,9 @@ pub async fn register_faucet<T: Config + BotConfig>(ctx: Context<'_, T>) -> Resu
     let channel = Origin::Discord(ctx.channel_id().to_string());
     parse_call(
         ctx,
-        Call::<T>::RegisterFaucetChannel(channel).dispatch(who)?,
+        Call::<T>::RegisterFaucetChannel(channel)
+            .dispatch_blocking(who)
+            .await?,
     )
     .await
     // if let Some(Response::ReplyDirect(msg)) =
@@ -131,15 +182,175 @@ pub async fn register_faucet<T: Config + BotConfig>(ctx: Context<'_, T>) -> Resu
     // Ok(())
 }
 
+/// Register your wallet
+///
+/// Enter `$register_wallet <address>` to register the wallet tokens are sent to by default
+#[poise::command(prefix_command, slash_command)]
+pub async fn register_wallet<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "SS58 address of your wallet"] address: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::RegisterWallet(address)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Show your registered wallet
+///
+/// Enter `$my_wallet` to see the wallet you registered
+#[poise::command(prefix_command, slash_command)]
+pub async fn my_wallet<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::MyWallet.dispatch_blocking(who).await?).await
+}
+
+/// Add a labelled wallet
+///
+/// Enter `$add_wallet <label> <address> [network]` to add a wallet you can refer to by its label
+#[poise::command(prefix_command, slash_command)]
+pub async fn add_wallet<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Label to refer to the wallet by"] label: String,
+    #[description = "SS58 or EVM (0x…) address of the wallet"] address: String,
+    #[description = "Network the wallet is meant for"]
+    #[autocomplete = "autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::AddWallet {
+            label,
+            address,
+            network,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Remove one of your wallets
+///
+/// Enter `$remove_wallet <label>` to remove a wallet
+#[poise::command(prefix_command, slash_command)]
+pub async fn remove_wallet<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Label of the wallet"] label: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::RemoveWallet(label)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// List your wallets
+///
+/// Enter `$wallets` to list the wallets you registered
+#[poise::command(prefix_command, slash_command)]
+pub async fn wallets<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::ListWallets.dispatch_blocking(who).await?).await
+}
+
+/// Set your default wallet
+///
+/// Enter `$set_default_wallet <label>` to use a wallet when a command doesn't name one
+#[poise::command(prefix_command, slash_command)]
+pub async fn set_default_wallet<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Label of the wallet"] label: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::SetDefaultWallet(label)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Start verifying your wallet
+///
+/// Enter `$start_wallet_verification [label]` to get a message to sign with your default or
+/// labelled wallet
+#[poise::command(prefix_command, slash_command)]
+pub async fn start_wallet_verification<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Label of the wallet, defaults to your default wallet"] label: Option<String>,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::StartWalletVerification(label)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Complete the verification of your wallet
+///
+/// Enter `$complete_wallet_verification <signature>` with the hex encoded signature of the
+/// verification message
+#[poise::command(prefix_command, slash_command)]
+pub async fn complete_wallet_verification<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Signature of the verification message"] signature: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::CompleteWalletVerification(signature)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Link your Discord and Telegram accounts
+///
+/// Enter `$link_account` to get a code to confirm on Telegram
+#[poise::command(prefix_command, slash_command)]
+pub async fn link_account<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::LinkAccount.dispatch_blocking(who).await?).await
+}
+
+/// Confirm a link with your Telegram account
+///
+/// Enter `$confirm_link <code>` with the code you got on Telegram
+#[poise::command(prefix_command, slash_command)]
+pub async fn confirm_link<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Code you got on Telegram"] code: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::ConfirmLink(code).dispatch_blocking(who).await?,
+    )
+    .await
+}
+
 /// Activate a faucet channel
 ///
-/// Enter `$register_faucet` to register a channel as a faucet
+/// Enter `$activate_faucet <channel id> <network>` to activate a faucet
 #[poise::command(prefix_command, slash_command)]
 pub async fn activate_faucet<T: Config + BotConfig>(
     ctx: Context<'_, T>,
     channel_id: String,
-    rpc_url: String,
-    wallet_seed: String,
+    network: String,
 ) -> Result<(), Error> {
     if let Err(why) = ctx.reply("Activating now...").await {
         log::error!("Couldn't send answer to Discord: {:?}", why);
@@ -149,18 +360,14 @@ pub async fn activate_faucet<T: Config + BotConfig>(
     let channel = Origin::Discord(channel_id.clone());
     parse_call(
         ctx,
-        Call::<T>::ActivateFaucetChannel {
-            channel,
-            rpc_url,
-            wallet_seed,
-        }
-        .dispatch(who)?,
+        Call::<T>::ActivateFaucetChannel { channel, network }
+            .dispatch_blocking(who)
+            .await?,
     )
     .await
     // if let Some(Response::SayChan(reply_channel, msg)) = (Call::<T>::ActivateFaucetChannel {
     //     channel,
     //     rpc_url,
-    //     wallet_seed,
     // })
     // .dispatch(who)?
     // {
@@ -177,6 +384,790 @@ pub async fn activate_faucet<T: Config + BotConfig>(
     // Ok(())
 }
 
+/// Import an external wallet seed for a faucet
+///
+/// Enter `$import_faucet_seed <channel id> <network> "<wallet seed>"` in a direct message to let
+/// the faucet pay out from an existing wallet instead of its derived account
+#[poise::command(prefix_command, dm_only)]
+pub async fn import_faucet_seed<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    channel_id: String,
+    network: String,
+    wallet_seed: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(channel_id);
+    parse_call(
+        ctx,
+        Call::<T>::ImportFaucetSeed {
+            channel,
+            network,
+            wallet_seed,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Suggest the networks the faucet of the current channel pays out on.
+async fn autocomplete_network<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    partial: &str,
+) -> Vec<String> {
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    Bot::<T>::faucet_networks(&channel)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|network| network.starts_with(&partial.to_lowercase()))
+        .collect()
+}
+
+/// Request tokens from a faucet
+///
+/// Enter `$drip <network> [address]` in a faucet channel to receive tokens, which are sent to
+/// your registered wallet when no address is given
+#[poise::command(prefix_command, slash_command)]
+pub async fn drip<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to receive the tokens on"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "Wallet label or address to send the tokens to, defaults to your registered wallet"]
+    address: Option<String>,
+) -> Result<(), Error> {
+    // Sending the tokens takes longer than Discord waits for an answer.
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Drip {
+            channel,
+            network,
+            address,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Show the balances of a wallet
+///
+/// Enter `$balance [network] [target]` to see the balances of your wallets, one of your
+/// labelled wallets, an address or the wallets of a mentioned user
+#[poise::command(prefix_command, slash_command)]
+pub async fn balance<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to look the balance up on, defaults to the networks of the wallets"]
+    #[autocomplete = "autocomplete_network"]
+    network: Option<String>,
+    #[description = "Wallet label, address or mentioned user, defaults to your wallets"]
+    target: Option<String>,
+) -> Result<(), Error> {
+    // Looking up the balances may take longer than Discord waits for an answer.
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let target = target.map(|target| match parse_user_mention(&target) {
+        Some(user) => Origin::Discord(user).to_string(),
+        None => target,
+    });
+    parse_call(
+        ctx,
+        Call::<T>::Balance { network, target }
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Get the user id of a mention like `<@1234>` or `<@!1234>`.
+fn parse_user_mention(input: &str) -> Option<String> {
+    let id = input
+        .trim()
+        .strip_prefix("<@")?
+        .strip_suffix('>')?
+        .trim_start_matches('!');
+    match !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) {
+        true => Some(id.to_string()),
+        false => None,
+    }
+}
+
+/// Tip another user
+///
+/// Enter `$tip <user> <amount> <network>` to send tokens from your balance to a user
+#[poise::command(prefix_command, slash_command)]
+pub async fn tip<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "User to tip"] user: serenity::User,
+    #[description = "Amount of tokens, like 1.5"] amount: String,
+    #[description = "Network to tip on"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+) -> Result<(), Error> {
+    send_tip(ctx, user, amount, network).await
+}
+
+#[derive(poise::Modal)]
+#[name = "Tip"]
+struct TipModal {
+    #[name = "Amount of tokens"]
+    #[placeholder = "1.5"]
+    amount: String,
+    #[name = "Network"]
+    network: String,
+}
+
+/// Tip the user from the context menu of Discord.
+#[poise::command(context_menu_command = "Tip")]
+pub async fn tip_user<T: Config + BotConfig>(
+    ctx: ApplicationContext<'_, T>,
+    user: serenity::User,
+) -> Result<(), Error> {
+    use poise::Modal as _;
+
+    let Some(data) = TipModal::execute(ctx).await? else {
+        return Ok(());
+    };
+    send_tip(
+        poise::Context::Application(ctx),
+        user,
+        data.amount,
+        data.network,
+    )
+    .await
+}
+
+async fn send_tip<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    user: serenity::User,
+    amount: String,
+    network: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Tip {
+            to: Origin::Discord(user.id.to_string()),
+            amount,
+            network,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Claim the tips you received before tips moved to your balance
+///
+/// Enter `$claim_tips` to add your pending tips to your balance
+#[poise::command(prefix_command, slash_command)]
+pub async fn claim_tips<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::ClaimTips.dispatch_blocking(who).await?).await
+}
+
+/// Show your deposit address
+///
+/// Enter `$deposit <network>` to see the address to send tokens to for tipping
+#[poise::command(prefix_command, slash_command)]
+pub async fn deposit<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to deposit on"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Deposit(network).dispatch_blocking(who).await?,
+    )
+    .await
+}
+
+/// Show your balance
+///
+/// Enter `$ledger_balance` to see the tokens you deposited or received as tips
+#[poise::command(prefix_command, slash_command)]
+pub async fn ledger_balance<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::LedgerBalance.dispatch_blocking(who).await?).await
+}
+
+/// Withdraw from your balance
+///
+/// Enter `$withdraw <network> <amount> [address]` to send tokens from your balance to a wallet,
+/// which is your registered wallet when no address is given. The network fee is taken from your
+/// balance
+#[poise::command(prefix_command, slash_command)]
+pub async fn withdraw<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to withdraw on"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "Amount of tokens, like 1.5"] amount: String,
+    #[description = "Wallet label or address to send the tokens to, defaults to your registered wallet"]
+    address: Option<String>,
+) -> Result<(), Error> {
+    // Sending the tokens takes longer than Discord waits for an answer.
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Withdraw {
+            channel,
+            network,
+            amount,
+            target: address,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Compare the ledger with the funds held on-chain
+///
+/// Enter `$reconcile` to see whether the hot wallet covers the balances of all users
+#[poise::command(prefix_command)]
+pub async fn reconcile<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::Reconcile.dispatch_blocking(who).await?).await
+}
+
+/// Rain tokens on active members
+///
+/// Enter `$rain <network> <total> <minutes>` to split tokens from the faucet of the channel between
+/// the members who posted in it during the last minutes
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn rain<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to send the tokens on"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "Amount of tokens to split, like 10"] total: String,
+    #[description = "Number of minutes to look back for active members"] minutes: u64,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Rain {
+            channel,
+            network,
+            total,
+            window: minutes,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Airdrop tokens to a role
+///
+/// Enter `$airdrop <network> <amount> <role>` to send tokens from the faucet of the channel to
+/// every member with the role. Listing the members needs the server members intent.
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn airdrop<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to send the tokens on"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "Amount of tokens for each member, like 1.5"] amount: String,
+    #[description = "Role of the members to send the tokens to"] role: serenity::Role,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    Bot::<T>::ensure_admin(&who)?;
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    // The everyone role has the id of the server and isn't listed in the
+    // roles of its members.
+    let everyone = role.id.get() == guild_id.get();
+    let mut audience = Vec::new();
+    let mut after = None;
+    loop {
+        let members = guild_id.members(ctx.http(), Some(1000), after).await?;
+        after = members.last().map(|member| member.user.id);
+        let done = members.len() < 1000;
+        audience.extend(
+            members
+                .into_iter()
+                .filter(|member| !member.user.bot && (everyone || member.roles.contains(&role.id)))
+                .map(|member| Origin::Discord(member.user.id.to_string())),
+        );
+        if done {
+            break;
+        }
+    }
+
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Airdrop {
+            channel,
+            network,
+            amount,
+            audience,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Follow events of a network in this channel
+///
+/// Enter `$subscribe <network> <filter>` to post events like `Referenda.*`, `System.CodeUpdated` or
+/// `Balances.Transfer to <address>` to this channel
+#[poise::command(prefix_command, slash_command)]
+pub async fn subscribe<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to follow"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "Events to post, like Referenda.* or Balances.Transfer to <address>"]
+    #[rest]
+    filter: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Subscribe {
+            channel,
+            network,
+            filter,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Stop following events in this channel
+///
+/// Enter `$unsubscribe <id>` to remove a subscription of this channel
+#[poise::command(prefix_command, slash_command)]
+pub async fn unsubscribe<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Id of the subscription"] id: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Unsubscribe { channel, id }
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Show the events this channel follows
+///
+/// Enter `$subscriptions` to list the subscriptions of this channel
+#[poise::command(prefix_command, slash_command)]
+pub async fn subscriptions<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::ListSubscriptions(channel)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Get a message when funds of an address move
+///
+/// Enter `$watch <network> <address>` to get a direct message when the wallet label or address
+/// receives or sends funds, is slashed or has a lock change, defaults to your registered wallet
+#[poise::command(prefix_command, slash_command)]
+pub async fn watch<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network to watch the address on"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "Wallet label or address to watch, defaults to your registered wallet"]
+    address: Option<String>,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Watch {
+            network,
+            target: address,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Stop watching an address
+///
+/// Enter `$unwatch <id>` to stop getting messages about an address
+#[poise::command(prefix_command, slash_command)]
+pub async fn unwatch<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Id of the watch"] id: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::Unwatch(id).dispatch_blocking(who).await?).await
+}
+
+/// Show the addresses you watch
+///
+/// Enter `$watches` to list your watches and quiet hours
+#[poise::command(prefix_command, slash_command)]
+pub async fn watches<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::ListWatches.dispatch_blocking(who).await?).await
+}
+
+/// Set the hours you don't want messages about your watches
+///
+/// Enter `$quiet_hours <start>-<end>`, like `22-7` in UTC, to get the messages afterwards, or
+/// `$quiet_hours off`
+#[poise::command(prefix_command, slash_command)]
+pub async fn quiet_hours<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Hours in UTC like 22-7, or off"] hours: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let hours = Some(hours).filter(|hours| !hours.trim().eq_ignore_ascii_case("off"));
+    parse_call(
+        ctx,
+        Call::<T>::SetQuietHours(hours)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Show a referendum
+///
+/// Enter `$referendum <id>` to see the status, tally and time left of a referendum on the network
+/// this channel follows referenda of, or `$referendum <id> <network>`
+#[poise::command(prefix_command, slash_command)]
+pub async fn referendum<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Index of the referendum"] id: u32,
+    #[description = "Network of the referendum"]
+    #[autocomplete = "autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Referendum {
+            channel,
+            network,
+            index: id,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Get reminded of referenda you haven't voted on
+///
+/// Enter `$vote_reminders <network> on` to get a direct message before a referendum closes that
+/// your registered wallet hasn't voted on, or `$vote_reminders <network> off`
+#[poise::command(prefix_command, slash_command)]
+pub async fn vote_reminders<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network of the referenda"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "on or off"] toggle: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let enabled = !toggle.trim().eq_ignore_ascii_case("off");
+    parse_call(
+        ctx,
+        Call::<T>::VoteReminders { network, enabled }
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Show the status of a validator
+///
+/// Enter `$validator <address>` to see the commission, self-stake, nominators, active status and
+/// era points of a validator on the network this channel follows staking events of, or
+/// `$validator <address> <network>`
+#[poise::command(prefix_command, slash_command)]
+pub async fn validator<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Wallet label or address of the validator's stash"] address: String,
+    #[description = "Network of the validator"]
+    #[autocomplete = "autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Validator {
+            channel,
+            network,
+            target: address,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Show the nominations of a stash
+///
+/// Enter `$nominations <address>` to see the validators a stash nominates and which of them are
+/// active, or `$nominations <address> <network>`
+#[poise::command(prefix_command, slash_command)]
+pub async fn nominations<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Wallet label or address of the nominator's stash"] address: String,
+    #[description = "Network of the nominator"]
+    #[autocomplete = "autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::Nominations {
+            channel,
+            network,
+            target: address,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Get alerts about a validator
+///
+/// Enter `$monitor_validator <network> <address> <eras>` to get a direct message when the
+/// validator drops out of the active set, gets chilled or has payouts unclaimed for the number of
+/// eras
+#[poise::command(prefix_command, slash_command)]
+pub async fn monitor_validator<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Network of the validator"]
+    #[autocomplete = "autocomplete_network"]
+    network: String,
+    #[description = "Wallet label or address of the validator's stash"] address: String,
+    #[description = "Number of eras after which unclaimed payouts are reported"] eras: Option<u32>,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::MonitorValidator {
+            network,
+            target: address,
+            unclaimed_eras: eras,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Stop getting alerts about a validator
+///
+/// Enter `$unmonitor_validator <id>` to stop getting alerts about a validator
+#[poise::command(prefix_command, slash_command)]
+pub async fn unmonitor_validator<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    #[description = "Id of the monitored validator"] id: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::UnmonitorValidator(id)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Show the validators you monitor
+///
+/// Enter `$monitored_validators` to list the validators you get alerts about
+#[poise::command(prefix_command, slash_command)]
+pub async fn monitored_validators<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::ListMonitoredValidators
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Configure a faucet channel
+///
+/// Enter `$configure_faucet <channel id> <network> <setting> <value>` to change a setting of the
+/// faucet on a network.
+///
+/// Available settings: amount, cooldown, max_drips_per_day, daily_budget, min_balance,
+/// alert_balance, batch_window and batch_size.
+/// Use `none` as value to unset a setting.
+#[poise::command(prefix_command)]
+pub async fn configure_faucet<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    channel_id: String,
+    network: String,
+    setting: String,
+    value: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(channel_id);
+    let setting = FaucetSetting::try_from((setting.as_str(), value.as_str()))
+        .map_err(|err| DispatchError::Other(err.into()))?;
+    parse_call(
+        ctx,
+        Call::<T>::ConfigureFaucet {
+            channel,
+            network,
+            setting,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Show the status of a faucet
+///
+/// Enter `$faucet_status` in a faucet channel to see the balance and limits of its faucets
+#[poise::command(prefix_command, slash_command)]
+pub async fn faucet_status<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    // Getting the balances may take longer than Discord waits for an answer.
+    ctx.defer().await?;
+
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(ctx.channel_id().to_string());
+    parse_call(
+        ctx,
+        Call::<T>::FaucetStatus(channel)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Show the balances of a faucet
+///
+/// Enter `$faucet_balance <channel id>` to see the balance of the faucet on every network and the
+/// estimated number of drips left
+#[poise::command(prefix_command)]
+pub async fn faucet_balance<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    channel_id: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let channel = Origin::Discord(channel_id);
+    parse_call(
+        ctx,
+        Call::<T>::FaucetBalance(channel)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// Re-encrypt faucet seeds with the current key
+///
+/// Enter `$rotate_seed_key` after configuring a new seed encryption key and moving the old
+/// one to the previous key setting.
+#[poise::command(prefix_command)]
+pub async fn rotate_seed_key<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::RotateSeedKey.dispatch_blocking(who).await?).await
+}
+
+/// Add a network
+///
+/// Enter `$add_network <name> <rpc urls> [explorer url]` to add a network. Separate multiple rpc
+/// urls with commas. The explorer url may contain `{hash}`, which is replaced with the hash of
+/// an extrinsic.
+#[poise::command(prefix_command)]
+pub async fn add_network<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    name: String,
+    rpc_urls: String,
+    explorer_url: Option<String>,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    let rpc_urls = rpc_urls.split(',').map(String::from).collect();
+    parse_call(
+        ctx,
+        Call::<T>::AddNetwork {
+            name,
+            rpc_urls,
+            explorer_url,
+        }
+        .dispatch_blocking(who)
+        .await?,
+    )
+    .await
+}
+
+/// Remove a network
+///
+/// Enter `$remove_network <name>` to remove a network that's no longer used by any faucet
+#[poise::command(prefix_command)]
+pub async fn remove_network<T: Config + BotConfig>(
+    ctx: Context<'_, T>,
+    name: String,
+) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(
+        ctx,
+        Call::<T>::RemoveNetwork(name)
+            .dispatch_blocking(who)
+            .await?,
+    )
+    .await
+}
+
+/// List the available networks
+///
+/// Enter `$networks` to list the networks the bot knows about
+#[poise::command(prefix_command, slash_command)]
+pub async fn networks<T: Config + BotConfig>(ctx: Context<'_, T>) -> Result<(), Error> {
+    let who = Origin::Discord(ctx.author().id.to_string());
+    parse_call(ctx, Call::<T>::ListNetworks.dispatch_blocking(who).await?).await
+}
+
 // /// Vote for something
 // ///
 // /// Enter `$vote beamish` to vote for beamish
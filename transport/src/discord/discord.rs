@@ -1,6 +1,10 @@
 use crate::discord::{commands, Config};
 use eddie_lib::Config as BotConfig;
-use poise::{serenity_prelude as serenity, serenity_prelude::ClientBuilder};
+use eddie_lib::{origin::Origin, Response};
+use poise::{
+    serenity_prelude as serenity,
+    serenity_prelude::{ChannelId, ClientBuilder, UserId},
+};
 use std::{sync::Arc, time::Duration};
 use support::traits::{dispatch::DispatchError, Get};
 
@@ -9,6 +13,7 @@ pub struct Data<T: Config + BotConfig>(std::marker::PhantomData<T>);
 // Types used by all command functions
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a, T> = poise::Context<'a, Data<T>, Error>;
+pub type ApplicationContext<'a, T> = poise::ApplicationContext<'a, Data<T>, Error>;
 
 async fn on_error<T: Config + BotConfig>(error: poise::FrameworkError<'_, Data<T>, Error>) {
     // This is our custom error handler
@@ -41,6 +46,31 @@ impl<T: Config> DiscordTransport<T> {
         DiscordTransport(std::marker::PhantomData)
     }
 
+    /// Send a response that doesn't answer a command, like an alert of a
+    /// background task.
+    pub async fn send(&self, response: Response) -> Result<(), serenity::Error> {
+        let http = serenity::Http::new(&<T as Config>::Token::get());
+        match response {
+            Response::SayChan(channel, msg) => {
+                if let Ok(channel_id) = channel.inner().parse::<u64>() {
+                    ChannelId::from(channel_id).say(&http, msg).await?;
+                } else {
+                    log::error!("Invalid Discord channel ID: {}", channel.inner())
+                }
+            }
+            Response::SayDirect(user, msg) => {
+                if let Ok(user_id) = user.inner().parse::<u64>() {
+                    let dm_channel = UserId::from(user_id).create_dm_channel(&http).await?;
+                    dm_channel.say(&http, msg).await?;
+                } else {
+                    log::error!("Invalid Discord user ID: {}", user.inner())
+                }
+            }
+            response => log::error!("Can't send {:?} without a command to answer", response),
+        }
+        Ok(())
+    }
+
     pub async fn serve(&self) -> Result<(), serenity::Error> {
         log::info!("Starting Discord bot");
 
@@ -55,7 +85,50 @@ impl<T: Config> DiscordTransport<T> {
                 commands::init::<T>(),
                 commands::set_admin::<T>(),
                 commands::register_faucet::<T>(),
+                commands::register_wallet::<T>(),
+                commands::my_wallet::<T>(),
+                commands::add_wallet::<T>(),
+                commands::remove_wallet::<T>(),
+                commands::wallets::<T>(),
+                commands::set_default_wallet::<T>(),
+                commands::start_wallet_verification::<T>(),
+                commands::complete_wallet_verification::<T>(),
+                commands::link_account::<T>(),
+                commands::confirm_link::<T>(),
                 commands::activate_faucet::<T>(),
+                commands::import_faucet_seed::<T>(),
+                commands::drip::<T>(),
+                commands::balance::<T>(),
+                commands::tip::<T>(),
+                commands::tip_user::<T>(),
+                commands::claim_tips::<T>(),
+                commands::deposit::<T>(),
+                commands::ledger_balance::<T>(),
+                commands::withdraw::<T>(),
+                commands::reconcile::<T>(),
+                commands::rain::<T>(),
+                commands::airdrop::<T>(),
+                commands::subscribe::<T>(),
+                commands::unsubscribe::<T>(),
+                commands::subscriptions::<T>(),
+                commands::watch::<T>(),
+                commands::unwatch::<T>(),
+                commands::watches::<T>(),
+                commands::quiet_hours::<T>(),
+                commands::referendum::<T>(),
+                commands::vote_reminders::<T>(),
+                commands::validator::<T>(),
+                commands::nominations::<T>(),
+                commands::monitor_validator::<T>(),
+                commands::unmonitor_validator::<T>(),
+                commands::monitored_validators::<T>(),
+                commands::configure_faucet::<T>(),
+                commands::faucet_status::<T>(),
+                commands::faucet_balance::<T>(),
+                commands::rotate_seed_key::<T>(),
+                commands::add_network::<T>(),
+                commands::remove_network::<T>(),
+                commands::networks::<T>(),
             ],
             prefix_options: poise::PrefixFrameworkOptions {
                 prefix: Some("$".into()),
@@ -100,6 +173,15 @@ impl<T: Config> DiscordTransport<T> {
                         "Got an event in event handler: {:?}",
                         event.snake_case_name()
                     );
+                    // Remember who posts where, so rains reach active members.
+                    if let serenity::FullEvent::Message { new_message } = event {
+                        if !new_message.author.bot && new_message.guild_id.is_some() {
+                            eddie_lib::Bot::<T>::record_activity(
+                                &Origin::Discord(new_message.channel_id.to_string()),
+                                &Origin::Discord(new_message.author.id.to_string()),
+                            );
+                        }
+                    }
                     Ok(())
                 })
             },
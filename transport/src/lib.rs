@@ -1,6 +1,38 @@
+use eddie_lib::{call::UpdateHandle, origin::Origin, Call, Response};
+use support::traits::{
+    dispatch::{DispatchError, DispatchResult},
+    Dispatch,
+};
+
 pub mod discord;
 pub mod telegram;
 
+/// Wait for the next update of a tracked message without blocking the
+/// runtime.
+pub(crate) async fn next_update(handle: &UpdateHandle) -> Option<String> {
+    let handle = handle.clone();
+    tokio::task::spawn_blocking(move || handle.next_update())
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Dispatch bot calls on a blocking thread.
+///
+/// Calls that reach a node wait for its answers over a blocking connection,
+/// which would otherwise stall a worker of the runtime.
+pub(crate) trait DispatchBlocking {
+    async fn dispatch_blocking(self, origin: Origin) -> DispatchResult<Option<Response>>;
+}
+
+impl<T: eddie_lib::Config> DispatchBlocking for Call<T> {
+    async fn dispatch_blocking(self, origin: Origin) -> DispatchResult<Option<Response>> {
+        tokio::task::spawn_blocking(move || self.dispatch(origin))
+            .await
+            .map_err(|err| DispatchError::Other(err.to_string()))?
+    }
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use super::*;
@@ -1,7 +1,13 @@
-use crate::telegram::Config;
-use eddie_lib::{origin::Origin, Call, Response};
-use support::traits::{dispatch::DispatchError, Dispatch, Get};
-use teloxide::{prelude::*, utils::command::BotCommands};
+use crate::{telegram::Config, DispatchBlocking};
+use eddie_lib::{
+    airdrop::MAX_RAIN_WINDOW, origin::Origin, storage::storage::FaucetSetting, Call, Response,
+};
+use support::traits::{dispatch::DispatchError, Get};
+use teloxide::{
+    prelude::*,
+    types::MessageEntityKind,
+    utils::command::{BotCommands, ParseError},
+};
 
 #[derive(BotCommands, Clone)]
 #[command(
@@ -19,50 +25,489 @@ enum Command {
     SetAdmin { admin_origin: String, remove: bool },
     #[command(description = "register a channel as a faucet")]
     RegisterFaucetChannel,
+    #[command(description = "activate a faucet channel", parse_with = "split")]
+    ActivateFaucet { channel_id: String, network: String },
+    #[command(
+        description = "import an external wallet seed for a faucet (direct message only)",
+        parse_with = parse_channel_network_and_seed
+    )]
+    ImportFaucetSeed {
+        channel_id: String,
+        network: String,
+        wallet_seed: String,
+    },
+    #[command(description = "re-encrypt faucet seeds with the current key")]
+    RotateSeedKey,
     #[command(description = "get your user id")]
     UserId,
-    // #[command(description = "get some tokens from the faucet.")]
-    // Faucet(String),
+    #[command(
+        description = "get some tokens from the faucet, sent to a wallet label or address, or to your registered wallet",
+        parse_with = parse_network_and_address
+    )]
+    Drip {
+        network: String,
+        address: Option<String>,
+    },
+    #[command(
+        description = "show the balances of your wallets, or of a wallet label, address or user, on a network or all networks",
+        parse_with = parse_balance
+    )]
+    Balance {
+        network: Option<String>,
+        target: Option<String>,
+    },
+    #[command(
+        description = "tip the author of the message you reply to",
+        parse_with = "split"
+    )]
+    Tip { amount: String, network: String },
+    #[command(description = "add the tips you got before tips moved to your balance")]
+    ClaimTips,
+    #[command(description = "show the address to deposit tokens to on a network")]
+    Deposit(String),
+    #[command(description = "show the tokens you deposited or received as tips")]
+    LedgerBalance,
+    #[command(
+        description = "send tokens from your balance to a wallet label or address, or to your registered wallet, the network fee is taken from your balance",
+        parse_with = parse_withdrawal
+    )]
+    Withdraw {
+        network: String,
+        amount: String,
+        address: Option<String>,
+    },
+    #[command(description = "compare the ledger with the funds held on-chain")]
+    Reconcile,
+    #[command(
+        description = "split tokens from the faucet between the members who posted during the last minutes",
+        parse_with = "split"
+    )]
+    Rain {
+        network: String,
+        total: String,
+        minutes: u64,
+    },
+    #[command(
+        description = "send tokens from the faucet to each member who posted during the last day",
+        parse_with = "split"
+    )]
+    Airdrop { network: String, amount: String },
+    #[command(
+        description = "post events of a network to this chat, like Referenda.* or Balances.Transfer to <address>",
+        parse_with = parse_subscription
+    )]
+    Subscribe { network: String, filter: String },
+    #[command(description = "remove a subscription of this chat")]
+    Unsubscribe(String),
+    #[command(description = "show the events this chat follows")]
+    Subscriptions,
+    #[command(
+        description = "get a direct message when funds of a wallet label or address move, defaults to your registered wallet",
+        parse_with = parse_network_and_address
+    )]
+    Watch {
+        network: String,
+        address: Option<String>,
+    },
+    #[command(description = "stop watching an address")]
+    Unwatch(String),
+    #[command(description = "show the addresses you watch")]
+    Watches,
+    #[command(
+        description = "hold messages about your watches during hours like 22-7 in UTC, or off"
+    )]
+    QuietHours(String),
+    #[command(
+        description = "show a referendum of the network this chat follows referenda of, or of another network",
+        parse_with = parse_referendum
+    )]
+    Referendum { id: u32, network: Option<String> },
+    #[command(
+        description = "get reminded on or off of referenda your wallet hasn't voted on before they close",
+        parse_with = "split"
+    )]
+    VoteReminders { network: String, toggle: String },
+    #[command(
+        description = "show the commission, self-stake, nominators, active status and era points of a validator",
+        parse_with = parse_address_and_network
+    )]
+    Validator {
+        address: String,
+        network: Option<String>,
+    },
+    #[command(
+        description = "show the validators a stash nominates and which of them are active",
+        parse_with = parse_address_and_network
+    )]
+    Nominations {
+        address: String,
+        network: Option<String>,
+    },
+    #[command(
+        description = "get a direct message when a validator leaves the active set, gets chilled or has payouts unclaimed for a number of eras",
+        parse_with = parse_validator_monitor
+    )]
+    MonitorValidator {
+        network: String,
+        address: String,
+        eras: Option<u32>,
+    },
+    #[command(description = "stop getting alerts about a validator")]
+    UnmonitorValidator(String),
+    #[command(description = "show the validators you get alerts about")]
+    MonitoredValidators,
+    #[command(description = "register your wallet")]
+    RegisterWallet(String),
+    #[command(description = "show your registered wallet")]
+    MyWallet,
+    #[command(
+        description = "add a labelled SS58 or EVM (0x…) wallet with an optional network",
+        parse_with = parse_wallet
+    )]
+    AddWallet {
+        label: String,
+        address: String,
+        network: Option<String>,
+    },
+    #[command(description = "remove one of your wallets")]
+    RemoveWallet(String),
+    #[command(description = "list your wallets")]
+    Wallets,
+    #[command(description = "set your default wallet")]
+    SetDefaultWallet(String),
+    #[command(description = "get a message to sign to verify your default or labelled wallet")]
+    StartWalletVerification(String),
+    #[command(description = "verify your wallet with the signature of the message")]
+    CompleteWalletVerification(String),
+    #[command(description = "get a code to link your Discord account")]
+    LinkAccount,
+    #[command(description = "confirm a link with the code you got on Discord")]
+    ConfirmLink(String),
+    #[command(
+        description = "change a faucet setting (amount, cooldown, max_drips_per_day, daily_budget, min_balance, alert_balance, batch_window or batch_size)",
+        parse_with = "split"
+    )]
+    ConfigureFaucet {
+        channel_id: String,
+        network: String,
+        setting: String,
+        value: String,
+    },
+    #[command(description = "show the status of the faucet")]
+    FaucetStatus,
+    #[command(description = "show the balances of a faucet")]
+    FaucetBalance(String),
+    #[command(
+        description = "add a network with comma separated rpc urls and an optional explorer url",
+        parse_with = parse_network
+    )]
+    AddNetwork {
+        name: String,
+        rpc_urls: String,
+        explorer_url: Option<String>,
+    },
+    #[command(description = "remove a network")]
+    RemoveNetwork(String),
+    #[command(description = "list the available networks")]
+    Networks,
     // #[command(description = "handle a username.")]
     // Username(String),
     // #[command(description = "handle a username and an age.", parse_with = "split")]
     // UsernameAndAge { username: String, age: u8 },
 }
 
+/// Parse a channel id and network followed by a wallet seed, which may
+/// contain spaces.
+fn parse_channel_network_and_seed(input: String) -> Result<(String, String, String), ParseError> {
+    let mut args = input.trim().splitn(3, ' ');
+    match (args.next(), args.next(), args.next()) {
+        (Some(channel_id), Some(network), Some(wallet_seed)) if !wallet_seed.trim().is_empty() => {
+            Ok((
+                channel_id.to_string(),
+                network.to_string(),
+                wallet_seed.trim().to_string(),
+            ))
+        }
+        (channel_id, network, _) => Err(ParseError::TooFewArguments {
+            expected: 3,
+            found: channel_id.iter().chain(network.iter()).count(),
+            message: "Expected a channel id, a network and a wallet seed".into(),
+        }),
+    }
+}
+
+/// Parse a network name followed by an optional address.
+fn parse_network_and_address(input: String) -> Result<(String, Option<String>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next()) {
+        (Some(network), address, None) => Ok((network.to_string(), address.map(String::from))),
+        (_, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 2,
+            found: input.split_whitespace().count(),
+            message: "Expected a network and an optional address".into(),
+        }),
+        (None, _, _) => Err(ParseError::TooFewArguments {
+            expected: 1,
+            found: 0,
+            message: "Expected a network".into(),
+        }),
+    }
+}
+
+/// Parse a network name and an amount followed by an optional address.
+fn parse_withdrawal(input: String) -> Result<(String, String, Option<String>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(network), Some(amount), address, None) => Ok((
+            network.to_string(),
+            amount.to_string(),
+            address.map(String::from),
+        )),
+        (_, _, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 3,
+            found: input.split_whitespace().count(),
+            message: "Expected a network, an amount and an optional address".into(),
+        }),
+        (network, _, _, _) => Err(ParseError::TooFewArguments {
+            expected: 2,
+            found: network.iter().count(),
+            message: "Expected a network and an amount".into(),
+        }),
+    }
+}
+
+/// Parse a network name followed by an event filter.
+fn parse_subscription(input: String) -> Result<(String, String), ParseError> {
+    match input.trim().split_once(char::is_whitespace) {
+        Some((network, filter)) if !filter.trim().is_empty() => {
+            Ok((network.to_string(), filter.trim().to_string()))
+        }
+        _ => Err(ParseError::TooFewArguments {
+            expected: 2,
+            found: input.split_whitespace().count(),
+            message: "Expected a network and an event filter".into(),
+        }),
+    }
+}
+
+/// Parse the index of a referendum followed by an optional network.
+fn parse_referendum(input: String) -> Result<(u32, Option<String>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next()) {
+        (Some(id), network, None) => Ok((
+            id.parse()
+                .map_err(|err| ParseError::IncorrectFormat(Box::new(err)))?,
+            network.map(String::from),
+        )),
+        (_, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 2,
+            found: input.split_whitespace().count(),
+            message: "Expected a referendum index and an optional network".into(),
+        }),
+        (None, _, _) => Err(ParseError::TooFewArguments {
+            expected: 1,
+            found: 0,
+            message: "Expected a referendum index".into(),
+        }),
+    }
+}
+
+/// Parse an address followed by an optional network.
+fn parse_address_and_network(input: String) -> Result<(String, Option<String>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next()) {
+        (Some(address), network, None) => Ok((address.to_string(), network.map(String::from))),
+        (_, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 2,
+            found: input.split_whitespace().count(),
+            message: "Expected an address and an optional network".into(),
+        }),
+        (None, _, _) => Err(ParseError::TooFewArguments {
+            expected: 1,
+            found: 0,
+            message: "Expected an address".into(),
+        }),
+    }
+}
+
+/// Parse a network name and an address followed by an optional number of
+/// eras.
+fn parse_validator_monitor(input: String) -> Result<(String, String, Option<u32>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(network), Some(address), eras, None) => Ok((
+            network.to_string(),
+            address.to_string(),
+            eras.map(str::parse)
+                .transpose()
+                .map_err(|err| ParseError::IncorrectFormat(Box::new(err)))?,
+        )),
+        (_, _, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 3,
+            found: input.split_whitespace().count(),
+            message: "Expected a network, an address and an optional number of eras".into(),
+        }),
+        (network, _, _, _) => Err(ParseError::TooFewArguments {
+            expected: 2,
+            found: network.iter().count(),
+            message: "Expected a network and an address".into(),
+        }),
+    }
+}
+
+/// Parse an optional network, `all` for all networks, followed by an
+/// optional target. A single argument may also be the target, which is only
+/// known once the networks are looked up.
+fn parse_balance(input: String) -> Result<(Option<String>, Option<String>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next()) {
+        (network, target, None) => Ok((
+            network
+                .filter(|network| !network.eq_ignore_ascii_case("all"))
+                .map(String::from),
+            target.map(String::from),
+        )),
+        (_, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 2,
+            found: input.split_whitespace().count(),
+            message: "Expected an optional network and an optional target".into(),
+        }),
+    }
+}
+
+/// Get the user a command is about, either mentioned by name, for users
+/// without a username, or the author of the message replied to.
+fn mentioned_user(msg: &Message) -> Option<Origin> {
+    let mentioned = msg.entities().and_then(|entities| {
+        entities.iter().find_map(|entity| match &entity.kind {
+            MessageEntityKind::TextMention { user } => Some(user.id),
+            _ => None,
+        })
+    });
+    mentioned
+        .or(msg
+            .reply_to_message()
+            .and_then(|reply| reply.from())
+            .map(|user| user.id))
+        .map(|id| Origin::Telegram(id.to_string()))
+}
+
+/// Parse a wallet label, an address and an optional network.
+fn parse_wallet(input: String) -> Result<(String, String, Option<String>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(label), Some(address), network, None) => Ok((
+            label.to_string(),
+            address.to_string(),
+            network.map(String::from),
+        )),
+        (_, _, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 3,
+            found: input.split_whitespace().count(),
+            message: "Expected a label, an address and an optional network".into(),
+        }),
+        (label, _, _, _) => Err(ParseError::TooFewArguments {
+            expected: 2,
+            found: label.iter().count(),
+            message: "Expected a label and an address".into(),
+        }),
+    }
+}
+
+/// Parse a network name, comma separated rpc urls and an optional explorer url.
+fn parse_network(input: String) -> Result<(String, String, Option<String>), ParseError> {
+    let mut args = input.split_whitespace();
+    match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(name), Some(rpc_urls), explorer_url, None) => Ok((
+            name.to_string(),
+            rpc_urls.to_string(),
+            explorer_url.map(String::from),
+        )),
+        (_, _, _, Some(_)) => Err(ParseError::TooManyArguments {
+            expected: 3,
+            found: input.split_whitespace().count(),
+            message: "Expected a name, rpc urls and an optional explorer url".into(),
+        }),
+        (name, _, _, _) => Err(ParseError::TooFewArguments {
+            expected: 2,
+            found: name.iter().count(),
+            message: "Expected a name and rpc urls".into(),
+        }),
+    }
+}
+
 async fn parse_call(
     bot: Bot,
     msg: Message,
     response: Result<Option<Response>, DispatchError>,
 ) -> ResponseResult<()> {
+    let responses = match response {
+        Ok(None) => vec![],
+        Ok(Some(Response::Multiple(responses))) => responses,
+        Ok(Some(response)) => vec![response],
+        Err(err) => {
+            bot.send_message(msg.chat.id, err.to_string())
+                .reply_to_message_id(msg.id)
+                .await?;
+            vec![]
+        }
+    };
+
+    for response in responses {
+        parse_response(&bot, &msg, response).await?;
+    }
+    Ok(())
+}
+
+async fn parse_response(bot: &Bot, msg: &Message, response: Response) -> ResponseResult<()> {
     match response {
-        Ok(None) => {}
-        Ok(Some(Response::Say(reply))) => {
+        Response::Say(reply) => {
             bot.send_message(msg.chat.id, reply).await?;
         }
-        Ok(Some(Response::SayChan(reply_channel, reply))) => {
+        Response::SayChan(reply_channel, reply) => {
             if let Ok(channel_id) = reply_channel.inner().parse::<i64>() {
                 bot.send_message(ChatId(channel_id), reply).await?;
             } else {
                 log::error!("Invalid Discord channel ID: {}", reply_channel.inner())
             }
         }
-        Ok(Some(Response::Reply(reply))) => {
+        Response::Reply(reply) => {
             bot.send_message(msg.chat.id, reply)
                 .reply_to_message_id(msg.id)
                 .await?;
         }
-        Ok(Some(Response::ReplyDirect(reply))) => {
+        Response::ReplyDirect(reply) => {
             if let Some(sender) = msg.from() {
                 bot.send_message(ChatId(sender.id.0 as i64), reply).await?;
             } else {
                 log::error!("Msg has no sender")
             }
         }
-        Err(err) => {
-            bot.send_message(msg.chat.id, err.to_string())
+        Response::ReplyTracked(reply, handle) => {
+            let sent = bot
+                .send_message(msg.chat.id, reply)
                 .reply_to_message_id(msg.id)
                 .await?;
+
+            // Don't hold up other commands in the chat while the message is
+            // being updated.
+            let bot = bot.clone();
+            tokio::spawn(async move {
+                while let Some(update) = crate::next_update(&handle).await {
+                    if let Err(err) = bot.edit_message_text(sent.chat.id, sent.id, update).await {
+                        log::error!("Couldn't edit Telegram message: {:?}", err);
+                    }
+                }
+            });
         }
+        Response::SayDirect(user, reply) => {
+            if let Ok(user_id) = user.inner().parse::<i64>() {
+                bot.send_message(ChatId(user_id), reply).await?;
+            } else {
+                log::error!("Invalid Telegram user ID: {}", user.inner())
+            }
+        }
+        Response::Multiple(_) => log::error!("Nested multiple responses aren't supported"),
     }
     Ok(())
 }
@@ -75,6 +520,23 @@ impl<T: Config> TelegramTransport<T> {
         TelegramTransport(std::marker::PhantomData)
     }
 
+    /// Send a response that doesn't answer a message, like an alert of a
+    /// background task.
+    pub async fn send(&self, response: Response) -> ResponseResult<()> {
+        let bot = Bot::new(<T as Config>::Token::get());
+        match response {
+            Response::SayChan(chat, text) | Response::SayDirect(chat, text) => {
+                if let Ok(chat_id) = chat.inner().parse::<i64>() {
+                    bot.send_message(ChatId(chat_id), text).await?;
+                } else {
+                    log::error!("Invalid Telegram chat ID: {}", chat.inner())
+                }
+            }
+            response => log::error!("Can't send {:?} without a message to answer", response),
+        }
+        Ok(())
+    }
+
     async fn process(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
         let sender = match msg.from() {
             Some(user) => user,
@@ -95,8 +557,12 @@ impl<T: Config> TelegramTransport<T> {
                     .await?;
                 Ok(())
             }
-            Command::Info => parse_call(bot, msg, Call::<T>::Info.dispatch(origin)).await,
-            Command::Init => parse_call(bot, msg, Call::<T>::Init.dispatch(origin)).await,
+            Command::Info => {
+                parse_call(bot, msg, Call::<T>::Info.dispatch_blocking(origin).await).await
+            }
+            Command::Init => {
+                parse_call(bot, msg, Call::<T>::Init.dispatch_blocking(origin).await).await
+            }
             Command::SetAdmin {
                 admin_origin,
                 remove,
@@ -105,7 +571,9 @@ impl<T: Config> TelegramTransport<T> {
                     parse_call(
                         bot,
                         msg,
-                        Call::<T>::SetAdmin(admin_origin, remove).dispatch(origin),
+                        Call::<T>::SetAdmin(admin_origin, remove)
+                            .dispatch_blocking(origin)
+                            .await,
                     )
                     .await
                 } else {
@@ -120,7 +588,565 @@ impl<T: Config> TelegramTransport<T> {
                 parse_call(
                     bot,
                     msg,
-                    Call::<T>::RegisterFaucetChannel(channel).dispatch(origin),
+                    Call::<T>::RegisterFaucetChannel(channel)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::ActivateFaucet {
+                channel_id,
+                network,
+            } => {
+                let channel = Origin::Telegram(channel_id);
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ActivateFaucetChannel { channel, network }
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::ImportFaucetSeed {
+                channel_id,
+                network,
+                wallet_seed,
+            } => {
+                // Seeds must never end up in group chats.
+                if !msg.chat.is_private() {
+                    bot.delete_message(msg.chat.id, msg.id).await?;
+                    bot.send_message(
+                        msg.chat.id,
+                        "Seeds can only be imported in a direct message. Please consider this seed compromised.",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
+                let channel = Origin::Telegram(channel_id);
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ImportFaucetSeed {
+                        channel,
+                        network,
+                        wallet_seed,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::RotateSeedKey => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::RotateSeedKey.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::Drip { network, address } => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Drip {
+                        channel,
+                        network,
+                        address,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::Balance { network, target } => {
+                // A single argument is a target unless a network goes by it.
+                let is_network = |name: &str| eddie_lib::Bot::<T>::is_network(name).unwrap_or(true);
+                let (network, target) = match (network, target) {
+                    (Some(arg), None) if !is_network(&arg) => (None, Some(arg)),
+                    args => args,
+                };
+                let target = target.or_else(|| mentioned_user(&msg).map(|user| user.to_string()));
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Balance { network, target }
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Tip { amount, network } => {
+                let Some(user) = msg.reply_to_message().and_then(|reply| reply.from()) else {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Please reply to a message of the user you want to tip.",
+                    )
+                    .reply_to_message_id(msg.id)
+                    .await?;
+                    return Ok(());
+                };
+                let to = Origin::Telegram(user.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Tip {
+                        to,
+                        amount,
+                        network,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::ClaimTips => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ClaimTips.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::Deposit(network) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Deposit(network).dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::LedgerBalance => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::LedgerBalance.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::Withdraw {
+                network,
+                amount,
+                address,
+            } => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Withdraw {
+                        channel,
+                        network,
+                        amount,
+                        target: address,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::Reconcile => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Reconcile.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::Rain {
+                network,
+                total,
+                minutes,
+            } => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Rain {
+                        channel,
+                        network,
+                        total,
+                        window: minutes,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::Subscribe { network, filter } => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Subscribe {
+                        channel,
+                        network,
+                        filter,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::Unsubscribe(id) => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Unsubscribe { channel, id }
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Subscriptions => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ListSubscriptions(channel)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Watch { network, address } => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Watch {
+                        network,
+                        target: address,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::Unwatch(id) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Unwatch(id).dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::Watches => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ListWatches.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::QuietHours(hours) => {
+                let hours = Some(hours).filter(|hours| !hours.trim().eq_ignore_ascii_case("off"));
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::SetQuietHours(hours)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Referendum { id, network } => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Referendum {
+                        channel,
+                        network,
+                        index: id,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::VoteReminders { network, toggle } => {
+                let enabled = !toggle.trim().eq_ignore_ascii_case("off");
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::VoteReminders { network, enabled }
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Validator { address, network } => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Validator {
+                        channel,
+                        network,
+                        target: address,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::Nominations { address, network } => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Nominations {
+                        channel,
+                        network,
+                        target: address,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::MonitorValidator {
+                network,
+                address,
+                eras,
+            } => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::MonitorValidator {
+                        network,
+                        target: address,
+                        unclaimed_eras: eras,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::UnmonitorValidator(id) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::UnmonitorValidator(id)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::MonitoredValidators => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ListMonitoredValidators
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Airdrop { network, amount } => {
+                // Telegram doesn't let bots list the members of a chat.
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                let audience = eddie_lib::Bot::<T>::active_members(&channel, MAX_RAIN_WINDOW);
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::Airdrop {
+                        channel,
+                        network,
+                        amount,
+                        audience,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::RegisterWallet(address) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::RegisterWallet(address)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::MyWallet => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::MyWallet.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::AddWallet {
+                label,
+                address,
+                network,
+            } => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::AddWallet {
+                        label,
+                        address,
+                        network,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::RemoveWallet(label) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::RemoveWallet(label)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Wallets => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ListWallets.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::SetDefaultWallet(label) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::SetDefaultWallet(label)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::StartWalletVerification(label) => {
+                let label = Some(label.trim().to_string()).filter(|label| !label.is_empty());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::StartWalletVerification(label)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::LinkAccount => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::LinkAccount.dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::ConfirmLink(code) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ConfirmLink(code).dispatch_blocking(origin).await,
+                )
+                .await
+            }
+            Command::CompleteWalletVerification(signature) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::CompleteWalletVerification(signature)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::ConfigureFaucet {
+                channel_id,
+                network,
+                setting,
+                value,
+            } => {
+                if let Ok(setting) = FaucetSetting::try_from((setting.as_str(), value.as_str())) {
+                    let channel = Origin::Telegram(channel_id);
+                    parse_call(
+                        bot,
+                        msg,
+                        Call::<T>::ConfigureFaucet {
+                            channel,
+                            network,
+                            setting,
+                        }
+                        .dispatch_blocking(origin)
+                        .await,
+                    )
+                    .await
+                } else {
+                    bot.send_message(msg.chat.id, "Invalid faucet setting.")
+                        .reply_to_message_id(msg.id)
+                        .await?;
+                    Ok(())
+                }
+            }
+            Command::FaucetStatus => {
+                let channel = Origin::Telegram(msg.chat.id.to_string());
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::FaucetStatus(channel)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::FaucetBalance(channel_id) => {
+                let channel = Origin::Telegram(channel_id);
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::FaucetBalance(channel)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::AddNetwork {
+                name,
+                rpc_urls,
+                explorer_url,
+            } => {
+                let rpc_urls = rpc_urls.split(',').map(String::from).collect();
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::AddNetwork {
+                        name,
+                        rpc_urls,
+                        explorer_url,
+                    }
+                    .dispatch_blocking(origin)
+                    .await,
+                )
+                .await
+            }
+            Command::RemoveNetwork(name) => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::RemoveNetwork(name)
+                        .dispatch_blocking(origin)
+                        .await,
+                )
+                .await
+            }
+            Command::Networks => {
+                parse_call(
+                    bot,
+                    msg,
+                    Call::<T>::ListNetworks.dispatch_blocking(origin).await,
                 )
                 .await
             }
@@ -131,6 +1157,27 @@ impl<T: Config> TelegramTransport<T> {
         log::info!("Starting Telegram bot");
 
         let bot = Bot::new(<T as Config>::Token::get());
-        Command::repl(bot, Self::process).await;
+        // Every message in a group is seen to remember who posts where, so
+        // rains reach active members. That needs the privacy mode of the bot
+        // to be disabled.
+        let handler = Update::filter_message()
+            .inspect(|msg: Message| {
+                if let (Some(user), false) = (msg.from(), msg.chat.is_private()) {
+                    if !user.is_bot {
+                        eddie_lib::Bot::<T>::record_activity(
+                            &Origin::Telegram(msg.chat.id.to_string()),
+                            &Origin::Telegram(user.id.to_string()),
+                        );
+                    }
+                }
+            })
+            .filter_command::<Command>()
+            .endpoint(Self::process);
+        Dispatcher::builder(bot, handler)
+            .default_handler(|_| Box::pin(async {}))
+            .enable_ctrlc_handler()
+            .build()
+            .dispatch()
+            .await;
     }
 }